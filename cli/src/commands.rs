@@ -0,0 +1,271 @@
+use crate::{ValidationChange, Validator};
+use assetman::{AssetPath, IntegrityManifest, ReferenceGraph, Tracker, WriteOptions};
+use assetman_json::{AssetPathJsonExt, JsonSchemaRegistry};
+use std::process::ExitCode;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: assetman <command> [args]
+
+commands:
+    check <dir>                     validate every asset in <dir> once
+    watch <dir> [--interval <ms>]   validate every asset in <dir>, then re-validate assets as
+                                    they change, printing errors introduced or fixed
+    refs <dir> [<entry>...]         check references between assets in <dir>, reporting missing
+                                    targets, cycles, and assets not referenced by any other
+                                    asset or given as an entry
+    manifest <dir> <out> [--key <key-file>]
+                                    write an integrity manifest for the files in <dir> to <out>,
+                                    signing it with the Ed25519 secret key in <key-file> (64 hex
+                                    digits) if given
+    schema <out-dir>                write a JSON schema for each registered config type to
+                                    <out-dir>/<name>.schema.json (see `assetman_cli::run`)
+    cook <dir> [--force]            write a cooked binary sibling (.bin) for each JSON file in
+                                    <dir>, which is loaded in place of the JSON file while it is
+                                    newer if `JsonLoadOptions::use_cooked` is set, skipping those
+                                    that are already newer unless --force is given; other .bin
+                                    files are never overwritten";
+
+/// Runs the `assetman` command-line tool with the given arguments, not including the program name.
+///
+/// JSON files are validated, and schemas are exported, using the config types in `schemas`.
+/// Applications can provide their own binary which registers their config types, as in:
+///
+/// ```ignore
+/// fn main() -> std::process::ExitCode {
+///     let mut schemas = assetman_json::JsonSchemaRegistry::default();
+///     schemas.register_loadable::<LevelConfig>("level");
+///     let args = std::env::args().skip(1).collect::<Vec<_>>();
+///     assetman_cli::run(&args, schemas)
+/// }
+/// ```
+pub fn run(args: &[String], schemas: JsonSchemaRegistry) -> ExitCode {
+    let res = match args.first().map(|s| s.as_str()) {
+        Some("check") => check(&args[1..], schemas),
+        Some("watch") => watch(&args[1..], schemas),
+        Some("refs") => refs(&args[1..]),
+        Some("manifest") => manifest(&args[1..]),
+        Some("schema") => schema(&args[1..], &schemas),
+        Some("cook") => cook(&args[1..]),
+        _ => Err(USAGE.to_owned()),
+    };
+    match res {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Implements the `check` command.
+fn check(args: &[String], schemas: JsonSchemaRegistry) -> Result<ExitCode, String> {
+    let [dir] = args else {
+        return Err(USAGE.to_owned());
+    };
+    let root = AssetPath::new_root_fs(std::path::Path::new(dir));
+    let mut validator = Validator::new_with(root, schemas);
+    validator.refresh().map_err(|err| err.to_string())?;
+    let mut num_errors = 0;
+    for (asset, error) in validator.errors() {
+        println!("error: {}: {}", asset, error);
+        num_errors += 1;
+    }
+    if num_errors > 0 {
+        println!("{} asset(s) failed to validate", num_errors);
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Implements the `watch` command.
+fn watch(args: &[String], schemas: JsonSchemaRegistry) -> Result<ExitCode, String> {
+    let (dir, interval) = match args {
+        [dir] => (dir, Duration::from_millis(200)),
+        [dir, flag, ms] if flag == "--interval" => (
+            dir,
+            Duration::from_millis(ms.parse().map_err(|_| USAGE.to_owned())?),
+        ),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let root = AssetPath::new_root_fs(std::path::Path::new(dir));
+    let mut validator = Validator::new_with(root, schemas);
+    validator.refresh().map_err(|err| err.to_string())?;
+    for (asset, error) in validator.errors() {
+        println!("error: {}: {}", asset, error);
+    }
+    println!("watching {} for changes", dir);
+    loop {
+        std::thread::sleep(interval);
+        match validator.refresh() {
+            Ok(changes) => {
+                for change in changes {
+                    match change {
+                        ValidationChange::Broken { asset, error } => {
+                            println!("error: {}: {}", asset, error)
+                        }
+                        ValidationChange::Fixed { asset } => println!("fixed: {}", asset),
+                    }
+                }
+            }
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
+
+/// Implements the `refs` command.
+fn refs(args: &[String]) -> Result<ExitCode, String> {
+    let [dir, entries @ ..] = args else {
+        return Err(USAGE.to_owned());
+    };
+    let root = AssetPath::new_root_fs(std::path::Path::new(dir));
+    let tracker = Tracker::default();
+    let assets = root
+        .get_descendants(&tracker)
+        .map_err(|err| err.to_string())?;
+    let graph = ReferenceGraph::build(assets, |asset| crate::scan_references(asset, &tracker));
+    for err in graph.errors() {
+        println!("error: {}", err);
+    }
+    let missing = graph.missing();
+    for (source, target) in missing.iter() {
+        println!("missing: {} references {}", source, target);
+    }
+    let cycles = graph.cycles();
+    for cycle in cycles.iter() {
+        let names = cycle.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        println!("cycle: {}", names.join(" -> "));
+    }
+    let entries = entries.iter().map(|e| root.relative(e)).collect::<Vec<_>>();
+    for orphan in graph.orphans(&entries) {
+        println!("orphan: {}", orphan);
+    }
+    if graph.errors().is_empty() && missing.is_empty() && cycles.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// Implements the `manifest` command.
+fn manifest(args: &[String]) -> Result<ExitCode, String> {
+    let (dir, out, key_file) = match args {
+        [dir, out] => (dir, out, None),
+        [dir, out, flag, key_file] if flag == "--key" => (dir, out, Some(key_file)),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let key = match key_file {
+        Some(key_file) => {
+            let text = std::fs::read_to_string(key_file).map_err(|err| err.to_string())?;
+            let key = assetman::parse_signing_key(&text);
+            Some(key.ok_or_else(|| format!("malformed key file {}", key_file))?)
+        }
+        None => None,
+    };
+    let dir = std::path::Path::new(dir);
+    let mut manifest =
+        IntegrityManifest::generate(&AssetPath::new_root_fs(dir)).map_err(|err| err.to_string())?;
+
+    // The manifest shouldn't list itself, if it is written into the directory
+    let out = std::path::Path::new(out);
+    if let Ok(rel_out) = out.strip_prefix(dir) {
+        let rel_out = rel_out.to_string_lossy().replace('\\', "/");
+        manifest.remove(&rel_out);
+        manifest.remove(&format!("{}.sig", rel_out));
+    }
+    let (Some(out_dir), Some(out_name)) = (out.parent(), out.file_name()) else {
+        return Err(USAGE.to_owned());
+    };
+    let out_dir = match out_dir.as_os_str().is_empty() {
+        true => std::path::Path::new("."),
+        false => out_dir,
+    };
+    let out_asset = AssetPath::new_root_fs(out_dir).relative(&out_name.to_string_lossy());
+    manifest
+        .save(&out_asset, key.as_ref())
+        .map_err(|err| err.to_string())?;
+    println!(
+        "wrote {} file(s) to {}",
+        manifest.iter().count(),
+        out.display()
+    );
+    if let Some(key) = key {
+        let public_key = assetman::format_verifying_key(&key.verifying_key());
+        println!("public key: {}", public_key);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Implements the `schema` command.
+fn schema(args: &[String], registry: &JsonSchemaRegistry) -> Result<ExitCode, String> {
+    let [out_dir] = args else {
+        return Err(USAGE.to_owned());
+    };
+    if registry.iter().next().is_none() {
+        return Err("no config types are registered; see `assetman_cli::run`".to_owned());
+    }
+    std::fs::create_dir_all(out_dir).map_err(|err| err.to_string())?;
+    let out = AssetPath::new_root_fs(std::path::Path::new(out_dir));
+    crate::export_schemas(registry, &out).map_err(|err| err.to_string())?;
+    println!("wrote {} schema(s) to {}", registry.iter().count(), out_dir);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Implements the `cook` command.
+fn cook(args: &[String]) -> Result<ExitCode, String> {
+    let (dir, force) = match args {
+        [dir] => (dir, false),
+        [dir, flag] if flag == "--force" => (dir, true),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let root = AssetPath::new_root_fs(std::path::Path::new(dir));
+    let tracker = Tracker::default();
+    let assets = root
+        .get_descendants(&tracker)
+        .map_err(|err| err.to_string())?;
+    let (mut num_cooked, mut num_skipped, mut num_errors) = (0, 0, 0);
+    for asset in assets {
+        if asset.extension().as_deref() != Some("json") {
+            continue;
+        }
+
+        // Only cooked files are overwritten, so that other files at the same path, such as the
+        // buffer of a glTF file, are left alone, even with --force
+        let target = assetman_json::cooked_json_path(&asset);
+        if target.modified(&tracker).is_some() && !assetman_json::is_cooked_json(&target, &tracker)
+        {
+            println!("error: {} exists and isn't cooked JSON", target);
+            num_errors += 1;
+            continue;
+        }
+
+        // A cooked file is stale if the file or any file it includes has changed since
+        if !force && assetman_json::is_cooked_json_current(&asset, &tracker) {
+            num_skipped += 1;
+            continue;
+        }
+        match asset.cook_json(&tracker) {
+            Ok(bytes) => {
+                target
+                    .write_bytes(&bytes, &WriteOptions::default())
+                    .map_err(|err| err.to_string())?;
+                num_cooked += 1;
+            }
+            Err(err) => {
+                println!("error: {}", err);
+                num_errors += 1;
+            }
+        }
+    }
+    println!(
+        "cooked {} file(s), skipped {} up-to-date file(s)",
+        num_cooked, num_skipped
+    );
+    if num_errors > 0 {
+        println!("{} file(s) failed to cook", num_errors);
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
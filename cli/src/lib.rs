@@ -0,0 +1,217 @@
+use assetman::{AssetLoadResult, AssetPath, AssetSaveResult, Tracker, WriteOptions};
+use assetman_gltf::AssetPathGltfExt;
+use assetman_image::{AssetPathImageExt, ImageFormat};
+use assetman_json::{AssetPathJsonExt, JsonSchemaRegistry, JsonValue};
+use std::collections::{BTreeMap, BTreeSet};
+
+mod commands;
+
+pub use commands::run;
+
+/// Validates a single asset by fully loading it with the loader for its file extension.
+///
+/// JSON files which name a schema using a `"$schema"` property, as supported by most editors,
+/// are also checked against that schema, reporting every violation. If the schema's file name is
+/// `<name>.schema.json` and `schemas` has a type registered under `<name>` using
+/// [`JsonSchemaRegistry::register_loadable`], the file is loaded as that type, exactly as the
+/// application would load it.
+///
+/// Returns `Ok(false)` if there is no loader for the asset's extension, in which case the asset
+/// is not tracked.
+pub fn validate_asset(
+    asset: &AssetPath,
+    tracker: &Tracker,
+    schemas: &JsonSchemaRegistry,
+) -> AssetLoadResult<bool> {
+    match asset.extension().as_deref() {
+        Some("json") => {
+            let value = asset.load_json_value_resolved(tracker)?;
+            let schema = value.get("$schema").and_then(JsonValue::as_str);
+            if let Some(schema) = schema.filter(|s| assetman_json::is_asset_reference(s)) {
+                let name = schema.rsplit('/').next().unwrap();
+                let loaded = name
+                    .strip_suffix(".schema.json")
+                    .and_then(|name| schemas.load(name, asset, tracker));
+                match loaded {
+                    Some(res) => res?,
+                    None => {
+                        let schema = asset.parent().unwrap().relative(schema);
+                        asset.check_json_schema(tracker, &schema.load_json_value(tracker)?)?;
+                    }
+                }
+            }
+        }
+        Some("gltf" | "glb") => {
+            let gltf = asset.load_gltf(tracker)?;
+            for (id, buffer) in gltf.info().buffers.iter().enumerate() {
+                if buffer.uri.is_some() {
+                    gltf.buffer(id as u32)?;
+                }
+            }
+            for image in gltf.info().images.iter() {
+                if let Some(uri) = &image.uri {
+                    gltf.resolve_uri(uri).load_image(tracker)?;
+                }
+            }
+        }
+        Some(ext) if ImageFormat::from_extension(ext).is_some() => {
+            asset.load_image(tracker)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Writes each schema in the given registry to a `<name>.schema.json` file in the given
+/// directory.
+pub fn export_schemas(registry: &JsonSchemaRegistry, dir: &AssetPath) -> AssetSaveResult<()> {
+    for (name, schema) in registry.iter() {
+        dir.relative(&format!("{}.schema.json", name))
+            .save_json(schema, &WriteOptions::default())?;
+    }
+    Ok(())
+}
+
+/// Enumerates the assets referenced by the given asset, using the scanner for its file extension.
+///
+/// Assets whose format can't reference other assets have no references.
+pub fn scan_references(asset: &AssetPath, tracker: &Tracker) -> AssetLoadResult<Vec<AssetPath>> {
+    match asset.extension().as_deref() {
+        Some("json") => asset.scan_json_references(tracker),
+        Some("gltf" | "glb") => asset.scan_gltf_references(tracker),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Maintains the results of validating every asset in a directory, allowing them to be
+/// incrementally updated as assets change.
+pub struct Validator {
+    dir: AssetPath,
+    schemas: JsonSchemaRegistry,
+    listing: Option<renege::Token>,
+    assets: BTreeMap<String, ValidatedAsset>,
+}
+
+/// The most recent validation result for an asset in a [`Validator`].
+struct ValidatedAsset {
+    asset: AssetPath,
+    token: renege::Token,
+    error: Option<String>,
+}
+
+/// Describes a change in the validation status of an asset, as reported by
+/// [`Validator::refresh`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationChange {
+    /// An asset which was previously valid (or unknown) now has the given error, or its error
+    /// has changed.
+    Broken { asset: AssetPath, error: String },
+
+    /// An asset which previously had an error is now valid, or has been removed.
+    Fixed { asset: AssetPath },
+}
+
+impl Validator {
+    /// Creates a new [`Validator`] for the assets in the given directory. No assets are validated
+    /// until the first call to [`Validator::refresh`].
+    pub fn new(dir: AssetPath) -> Self {
+        Self::new_with(dir, JsonSchemaRegistry::default())
+    }
+
+    /// Creates a new [`Validator`] for the assets in the given directory, which validates JSON
+    /// files using the types in the given registry. See [`validate_asset`].
+    pub fn new_with(dir: AssetPath, schemas: JsonSchemaRegistry) -> Self {
+        Self {
+            dir,
+            schemas,
+            listing: None,
+            assets: BTreeMap::new(),
+        }
+    }
+
+    /// Gets the errors for all assets which currently fail to validate.
+    pub fn errors(&self) -> impl Iterator<Item = (&AssetPath, &str)> {
+        self.assets
+            .values()
+            .filter_map(|entry| Some((&entry.asset, entry.error.as_deref()?)))
+    }
+
+    /// Re-validates exactly those assets whose [`Tracker`] tokens have been invalidated since they
+    /// were last validated, along with any new assets, and returns the changes in validation
+    /// status.
+    pub fn refresh(&mut self) -> AssetLoadResult<Vec<ValidationChange>> {
+        let mut changes = Vec::new();
+
+        // Rescan the directory tree if it has changed
+        if !self.listing.is_some_and(|token| token.is_valid()) {
+            let tracker = Tracker::default();
+            let names = self
+                .dir
+                .get_descendants(&tracker)?
+                .into_iter()
+                .map(|asset| (asset.to_string(), asset))
+                .collect::<BTreeMap<_, _>>();
+            let removed = self
+                .assets
+                .keys()
+                .filter(|name| !names.contains_key(*name))
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            for name in removed {
+                let entry = self.assets.remove(&name).unwrap();
+                if entry.error.is_some() {
+                    changes.push(ValidationChange::Fixed { asset: entry.asset });
+                }
+            }
+            for (name, asset) in names {
+                self.assets.entry(name).or_insert_with(|| ValidatedAsset {
+                    asset,
+                    token: invalid_token(),
+                    error: None,
+                });
+            }
+            self.listing = Some(tracker.get());
+        }
+
+        // Re-validate assets that have changed
+        let mut unsupported = Vec::new();
+        for (name, entry) in self.assets.iter_mut() {
+            if entry.token.is_valid() {
+                continue;
+            }
+            let tracker = Tracker::default();
+            let error = match validate_asset(&entry.asset, &tracker, &self.schemas) {
+                Ok(true) => None,
+                Ok(false) => {
+                    unsupported.push(name.clone());
+                    continue;
+                }
+                Err(err) => Some(err.to_string()),
+            };
+            entry.token = tracker.get();
+            if error != entry.error {
+                changes.push(match &error {
+                    Some(error) => ValidationChange::Broken {
+                        asset: entry.asset.clone(),
+                        error: error.clone(),
+                    },
+                    None => ValidationChange::Fixed {
+                        asset: entry.asset.clone(),
+                    },
+                });
+                entry.error = error;
+            }
+        }
+
+        // Assets without a loader are never validated, so we can stop watching them
+        for name in unsupported {
+            self.assets.remove(&name);
+        }
+        Ok(changes)
+    }
+}
+
+/// Gets a [`renege::Token`] which has already been invalidated.
+fn invalid_token() -> renege::Token {
+    renege::Condition::new().token()
+}
@@ -0,0 +1,7 @@
+use assetman_json::JsonSchemaRegistry;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    assetman_cli::run(&args, JsonSchemaRegistry::default())
+}
@@ -0,0 +1,48 @@
+use assetman::{AssetPath, Tracker};
+use assetman_json::{JsonSchema, JsonSchemaRegistry};
+use assetman_test_util::TempDir;
+
+#[derive(serdere::Deserialize, JsonSchema)]
+#[allow(dead_code)]
+pub struct Enemy {
+    health: u32,
+}
+
+#[test]
+fn test_validate_registered() {
+    let dir = TempDir::new("validate");
+    let schema = r#""$schema": "schemas/enemy.schema.json""#;
+    std::fs::write(
+        dir.join("good.json"),
+        format!(r#"{{ {}, "health": 5 }}"#, schema),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("bad.json"),
+        format!(r#"{{ {}, "health": "lots" }}"#, schema),
+    )
+    .unwrap();
+    let root = AssetPath::new_root_fs(&dir);
+    let mut schemas = JsonSchemaRegistry::default();
+    schemas.register_loadable::<Enemy>("enemy");
+
+    // Files are loaded as the registered type, even though the schema file doesn't exist
+    let tracker = Tracker::default();
+    let validate = |name| assetman_cli::validate_asset(&root.relative(name), &tracker, &schemas);
+    assert!(validate("good.json").unwrap());
+    assert!(validate("bad.json").is_err());
+}
+
+#[test]
+fn test_run_schema() {
+    let dir = TempDir::new("run_schema");
+    let mut schemas = JsonSchemaRegistry::default();
+    schemas.register_loadable::<Enemy>("enemy");
+    let out = dir.join("schemas");
+    assetman_cli::run(
+        &["schema".to_owned(), out.to_string_lossy().into_owned()],
+        schemas,
+    );
+    let text = std::fs::read_to_string(out.join("enemy.schema.json")).unwrap();
+    assert!(text.contains("\"health\""));
+}
@@ -0,0 +1,35 @@
+use assetman::AssetPath;
+use assetman_cli::{ValidationChange, Validator};
+use assetman_test_util::TempDir;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_watch_fix() {
+    let dir = TempDir::new("watch");
+    std::fs::write(dir.join("good.json"), r#"{ "a": 1 }"#).unwrap();
+    std::fs::write(dir.join("bad.json"), r#"{ "a": }"#).unwrap();
+    let root = AssetPath::new_root_fs(&dir);
+    let mut validator = Validator::new(root.clone());
+    let changes = validator.refresh().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(&changes[0], ValidationChange::Broken { asset, .. }
+        if *asset == root.relative("bad.json")));
+
+    // Fix the broken asset and wait for the change to be noticed
+    std::fs::write(dir.join("bad.json"), r#"{ "a": 2 }"#).unwrap();
+    let start = Instant::now();
+    let changes = loop {
+        let changes = validator.refresh().unwrap();
+        if !changes.is_empty() || start.elapsed() > Duration::from_secs(5) {
+            break changes;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    assert_eq!(
+        changes,
+        vec![ValidationChange::Fixed {
+            asset: root.relative("bad.json")
+        }]
+    );
+    assert_eq!(validator.errors().count(), 0);
+}
@@ -0,0 +1,492 @@
+use crate::{stats, AssetLoadResult, AssetPath, Compression, ContentHash, Tracker};
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// A shared, size-bounded cache of loaded asset values.
+///
+/// Values are accessed through [`Handle`]s. Cached values are reloaded when the assets they were
+/// loaded from are modified. When the total size of the cached values exceeds the cache's budget,
+/// the least recently used values which have no outstanding [`Handle`]s are evicted. Eviction
+/// occurs when values are loaded, when the last [`Handle`] for a value is dropped, or when the
+/// budget is changed.
+///
+/// This is cheap to clone, and all clones refer to the same cache.
+#[derive(Clone)]
+pub struct AssetCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+/// The internal state of an [`AssetCache`].
+struct CacheState {
+    /// The maximum total size of entries without outstanding handles, in bytes.
+    budget: usize,
+
+    /// The total size of all entries, in bytes.
+    size: usize,
+
+    /// The total size of the entries which were unpinned when they were last checked (see
+    /// [`CacheEntry::update_pinned`]), in bytes.
+    unpinned_size: usize,
+
+    /// The value of `last_used` for the most recently used entry.
+    tick: u64,
+    entries: HashMap<CacheKey, CacheEntry>,
+
+    /// The keys of all entries, indexed by their `last_used` value, from least to most recently
+    /// used.
+    lru: BTreeMap<u64, CacheKey>,
+}
+
+/// Identifies an entry in an [`AssetCache`].
+#[derive(PartialEq, Eq, Hash, Clone)]
+struct CacheKey {
+    asset: CacheAsset,
+    ty: TypeId,
+}
+
+/// Identifies the asset for an entry in an [`AssetCache`].
+#[derive(PartialEq, Eq, Hash, Clone)]
+enum CacheAsset {
+    Path(AssetPath),
+
+    /// An asset whose root identifies it by its contents. Values for these are shared between all
+    /// assets with the same contents and extension, since loaders may interpret the contents
+    /// differently depending on the extension.
+    Content {
+        hash: ContentHash,
+        extension: Option<String>,
+        compression: Option<Compression>,
+    },
+}
+
+/// An entry in an [`AssetCache`].
+struct CacheEntry {
+    /// The [`Slot`] for the value, shared with all [`Handle`]s for it.
+    slot: Arc<dyn Any + Send + Sync>,
+
+    /// The number of [`Handle`]s for the value, shared with the [`Slot`].
+    handles: Arc<AtomicUsize>,
+
+    /// The token which is invalidated when the value needs to be reloaded.
+    token: renege::Token,
+    size: usize,
+    last_used: u64,
+
+    /// Whether this entry had any [`Handle`]s when it was last checked, determining whether its
+    /// size is counted in [`CacheState::unpinned_size`].
+    pinned: bool,
+}
+
+impl CacheEntry {
+    /// Determines whether there are any [`Handle`]s for this entry, preventing it from being
+    /// evicted.
+    fn is_pinned(&self) -> bool {
+        self.handles.load(Ordering::Acquire) > 0
+    }
+
+    /// Updates `pinned` to reflect the current [`Handle`]s for this entry, adjusting the given
+    /// total size of unpinned entries accordingly.
+    fn update_pinned(&mut self, unpinned_size: &mut usize) {
+        let pinned = self.is_pinned();
+        if pinned != self.pinned {
+            self.pinned = pinned;
+            if pinned {
+                *unpinned_size -= self.size;
+            } else {
+                *unpinned_size += self.size;
+            }
+        }
+    }
+}
+
+impl AssetCache {
+    /// Creates a new, empty [`AssetCache`] with the given budget, in bytes.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState {
+                budget,
+                size: 0,
+                unpinned_size: 0,
+                tick: 0,
+                entries: HashMap::new(),
+                lru: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Gets the budget for this cache, in bytes.
+    pub fn budget(&self) -> usize {
+        self.state.lock().unwrap().budget
+    }
+
+    /// Sets the budget for this cache, in bytes, evicting entries if needed.
+    pub fn set_budget(&self, budget: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.budget = budget;
+        let evicted = state.evict();
+        drop(state);
+        drop(evicted);
+    }
+
+    /// Gets the total size of all values in this cache, in bytes.
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().size
+    }
+
+    /// Gets the cached value of type `T` for the given asset, or loads it using `load` if it isn't
+    /// cached or has been invalidated.
+    ///
+    /// The given [`Tracker`] is notified when the cached value is invalidated. When an invalidated
+    /// value is reloaded, the existing [`Handle`]s for it are updated to refer to the new value and
+    /// their [`Handle::generation`] is incremented.
+    ///
+    /// If the asset has a [`AssetPath::content_hash`], the value is shared with all other assets
+    /// that have the same contents and file extension (see [`AssetPath::extension`] and
+    /// [`AssetPath::compression`]), so `load` should only depend on the contents and extension of
+    /// the asset. Otherwise, use [`AssetCache::get_or_load_by_path`].
+    ///
+    /// Loads are performed without holding a lock on the cache, so concurrent requests for the
+    /// same value may each load it.
+    pub fn get_or_load<T: CacheSize + Send + Sync + 'static>(
+        &self,
+        asset: &AssetPath,
+        tracker: &Tracker,
+        load: impl FnOnce(&Tracker) -> AssetLoadResult<T>,
+    ) -> AssetLoadResult<Handle<T>> {
+        self.get_or_load_with_size(asset, tracker, load, T::cache_size)
+    }
+
+    /// Gets the cached value of type `T` for the given asset, or loads it using `load` if it isn't
+    /// cached or has been invalidated. `size` is used to compute the cost of the loaded value.
+    ///
+    /// This is useful for types which can't implement [`CacheSize`].
+    pub fn get_or_load_with_size<T: Send + Sync + 'static>(
+        &self,
+        asset: &AssetPath,
+        tracker: &Tracker,
+        load: impl FnOnce(&Tracker) -> AssetLoadResult<T>,
+        size: impl FnOnce(&T) -> usize,
+    ) -> AssetLoadResult<Handle<T>> {
+        let key = match asset.content_hash(tracker) {
+            Some(hash) => CacheAsset::Content {
+                hash,
+                extension: asset.extension().map(std::borrow::Cow::into_owned),
+                compression: asset.compression(),
+            },
+            None => CacheAsset::Path(asset.clone()),
+        };
+        self.get_or_load_keyed(key, asset, tracker, load, size)
+    }
+
+    /// Gets the cached value of type `T` for the given asset, or loads it using `load`, as with
+    /// [`AssetCache::get_or_load`], except that the value is never shared with other assets that
+    /// have the same contents. This is needed when `load` depends on the path of the asset, e.g.
+    /// because it resolves other paths relative to it.
+    pub fn get_or_load_by_path<T: CacheSize + Send + Sync + 'static>(
+        &self,
+        asset: &AssetPath,
+        tracker: &Tracker,
+        load: impl FnOnce(&Tracker) -> AssetLoadResult<T>,
+    ) -> AssetLoadResult<Handle<T>> {
+        let key = CacheAsset::Path(asset.clone());
+        self.get_or_load_keyed(key, asset, tracker, load, T::cache_size)
+    }
+
+    /// Gets the cached value of type `T` for the given asset, identified in the cache by `key`, or
+    /// loads it using `load`.
+    fn get_or_load_keyed<T: Send + Sync + 'static>(
+        &self,
+        key: CacheAsset,
+        asset: &AssetPath,
+        tracker: &Tracker,
+        load: impl FnOnce(&Tracker) -> AssetLoadResult<T>,
+        size: impl FnOnce(&T) -> usize,
+    ) -> AssetLoadResult<Handle<T>> {
+        let scope = stats::LoadScope::new(asset, "cache");
+        let key = CacheKey {
+            asset: key,
+            ty: TypeId::of::<T>(),
+        };
+        {
+            let mut state = self.state.lock().unwrap();
+            let tick = state.next_tick();
+            let state = &mut *state;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                if entry.token.is_valid() {
+                    scope.record_cache_hit(true);
+                    state.lru.remove(&entry.last_used);
+                    state.lru.insert(tick, key);
+                    entry.last_used = tick;
+                    tracker.set(tracker.get() & entry.token);
+                    let handle = Handle::new(entry.slot.clone().downcast().unwrap());
+                    entry.update_pinned(&mut state.unpinned_size);
+                    return Ok(handle);
+                }
+            }
+        }
+        scope.record_cache_hit(false);
+        let inner = Tracker::default();
+        let value = Arc::new(load(&inner)?);
+        let token = inner.get();
+        tracker.set(tracker.get() & token);
+        let size = size(&value);
+        let mut state = self.state.lock().unwrap();
+        let last_used = state.next_tick();
+
+        // Reuse the existing slot, if any, so that existing handles see the reloaded value
+        let (slot, old_value) = match state.remove(&key) {
+            Some(entry) => {
+                let slot: Arc<Slot<T>> = entry.slot.downcast().unwrap();
+                let old_value = std::mem::replace(&mut *slot.value.write().unwrap(), value);
+                slot.generation.fetch_add(1, Ordering::Release);
+                (slot, Some(old_value))
+            }
+            None => {
+                let slot = Arc::new(Slot {
+                    value: RwLock::new(value),
+                    generation: AtomicU64::new(0),
+                    handles: Arc::new(AtomicUsize::new(0)),
+                    cache: Arc::downgrade(&self.state),
+                    key: key.clone(),
+                });
+                (slot, None)
+            }
+        };
+        // The returned handle pins the entry, so no space needs to be made for it until the
+        // handle is dropped
+        let handle = Handle::new(slot.clone());
+        state.size += size;
+        state.lru.insert(last_used, key.clone());
+        state.entries.insert(
+            key,
+            CacheEntry {
+                handles: slot.handles.clone(),
+                slot,
+                token,
+                size,
+                last_used,
+                pinned: true,
+            },
+        );
+        let evicted = state.evict();
+
+        // Values may hold handles of their own, so they are dropped after releasing the lock
+        drop(state);
+        drop((evicted, old_value));
+        Ok(handle)
+    }
+
+    /// Removes all values without outstanding [`Handle`]s from the cache.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        let keys = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_pinned())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let removed = keys
+            .iter()
+            .filter_map(|key| state.remove(key))
+            .collect::<Vec<_>>();
+        drop(state);
+        drop(removed);
+    }
+}
+
+impl CacheState {
+    /// Gets the value of `last_used` for an entry that is being used now.
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Removes the entry with the given key, updating the total size of the cache.
+    fn remove(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.last_used);
+        self.size -= entry.size;
+        if !entry.pinned {
+            self.unpinned_size -= entry.size;
+        }
+        Some(entry)
+    }
+
+    /// Updates whether the entry with the given key, if any, is pinned after its [`Handle`]s
+    /// change.
+    fn update_pinned(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.update_pinned(&mut self.unpinned_size);
+        }
+    }
+
+    /// Evicts least recently used entries without outstanding handles until the total size of such
+    /// entries is within budget. The evicted entries are returned so that they can be dropped after
+    /// the lock on the state is released.
+    #[must_use]
+    fn evict(&mut self) -> Vec<CacheEntry> {
+        let mut freed = 0;
+        let mut evicted = Vec::new();
+        for key in self.lru.values() {
+            if self.unpinned_size - freed <= self.budget {
+                break;
+            }
+
+            // Handles may have been created or dropped since the entry was last checked
+            let entry = self.entries.get_mut(key).unwrap();
+            entry.update_pinned(&mut self.unpinned_size);
+            if !entry.pinned {
+                freed += entry.size;
+                evicted.push(key.clone());
+            }
+        }
+        evicted.iter().filter_map(|key| self.remove(key)).collect()
+    }
+}
+
+/// The storage for a value in an [`AssetCache`], shared between the cache and all [`Handle`]s
+/// for the value.
+struct Slot<T> {
+    value: RwLock<Arc<T>>,
+    generation: AtomicU64,
+
+    /// The number of [`Handle`]s for the value.
+    handles: Arc<AtomicUsize>,
+
+    /// The state of the cache containing the value, used to evict values once they are no longer
+    /// in use.
+    cache: Weak<Mutex<CacheState>>,
+
+    /// The key for the entry of the value in the cache.
+    key: CacheKey,
+}
+
+/// A reference to a value in an [`AssetCache`].
+///
+/// This is cheap to clone. The value will not be evicted from the cache while any [`Handle`] for
+/// it exists.
+pub struct Handle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle::new(self.slot.clone())
+    }
+}
+
+impl<T> Drop for Handle<T> {
+    fn drop(&mut self) {
+        // Once the last handle is dropped, the value may need to be evicted to bring the cache
+        // within budget
+        if self.slot.handles.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(cache) = self.slot.cache.upgrade() {
+                let mut state = cache.lock().unwrap();
+                state.update_pinned(&self.slot.key);
+                let evicted = state.evict();
+                drop(state);
+                drop(evicted);
+            }
+        }
+    }
+}
+
+impl<T> Handle<T> {
+    /// Creates a new [`Handle`] for the value in the given slot.
+    fn new(slot: Arc<Slot<T>>) -> Self {
+        slot.handles.fetch_add(1, Ordering::AcqRel);
+        Self { slot }
+    }
+
+    /// Gets the current value for this handle.
+    pub fn get(&self) -> Arc<T> {
+        self.slot.value.read().unwrap().clone()
+    }
+
+    /// Gets the number of times the value for this handle has been reloaded. This can be used to
+    /// detect when resources derived from the value need to be rebuilt.
+    pub fn generation(&self) -> u64 {
+        self.slot.generation.load(Ordering::Acquire)
+    }
+
+    /// Creates a [`WeakHandle`] for the value of this handle, which does not prevent it from being
+    /// evicted.
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            slot: Arc::downgrade(&self.slot),
+        }
+    }
+
+    /// Determines whether two handles refer to the same cached value.
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        Arc::ptr_eq(&a.slot, &b.slot)
+    }
+}
+
+/// A reference to a value in an [`AssetCache`] which does not prevent the value from being
+/// evicted. See [`Handle::downgrade`].
+pub struct WeakHandle<T> {
+    slot: Weak<Slot<T>>,
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T> WeakHandle<T> {
+    /// Attempts to get a [`Handle`] for the value, returning [`None`] if the value has been
+    /// evicted and there are no other [`Handle`]s for it.
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        let slot = self.slot.upgrade()?;
+        if slot.handles.fetch_add(1, Ordering::AcqRel) == 0 {
+            // The value is pinned again, so it no longer counts towards the budget
+            if let Some(cache) = slot.cache.upgrade() {
+                cache.lock().unwrap().update_pinned(&slot.key);
+            }
+        }
+        Some(Handle { slot })
+    }
+}
+
+/// A type of value which can report its approximate memory cost for the purposes of an
+/// [`AssetCache`] budget.
+pub trait CacheSize {
+    /// Gets the approximate size of this value, in bytes.
+    fn cache_size(&self) -> usize;
+}
+
+impl CacheSize for [u8] {
+    fn cache_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl CacheSize for str {
+    fn cache_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl CacheSize for String {
+    fn cache_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: Copy> CacheSize for Vec<T> {
+    fn cache_size(&self) -> usize {
+        std::mem::size_of_val(self.as_slice())
+    }
+}
+
+impl<T: CacheSize + ?Sized> CacheSize for Box<T> {
+    fn cache_size(&self) -> usize {
+        (**self).cache_size()
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{AssetRoot, Tracker};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A cache of directory listings used by an [`AssetRoot`] to resolve paths case-insensitively.
+#[derive(Default)]
+pub struct CaseIndex {
+    dirs: Mutex<HashMap<PathBuf, DirIndex>>,
+
+    /// The full paths, as given, for which a warning about an ambiguous match has been logged.
+    warned: Mutex<HashSet<PathBuf>>,
+}
+
+/// The cached listing of a directory in a [`CaseIndex`].
+struct DirIndex {
+    /// The token which is invalidated when the contents of the directory change.
+    token: renege::Token,
+
+    /// A mapping from lowercased names to the names of the entries with that lowercased name,
+    /// sorted.
+    entries: HashMap<String, Vec<String>>,
+}
+
+impl CaseIndex {
+    /// Gets the full path of the file or directory in the given root which best matches the given
+    /// path, ignoring case. Paths which exist exactly as given are returned as-is.
+    pub fn resolve(&self, root: &AssetRoot, tracker: &Tracker, path: &str) -> PathBuf {
+        let exact = root.path.join(path);
+        if path.is_empty() || exact.exists() {
+            return exact;
+        }
+        root.path.join(self.canonicalize(root, tracker, path))
+    }
+
+    /// Gets the path, relative to the given root, of the file or directory which best matches the
+    /// given path, ignoring case, using the casing of the entries on disk. Components which don't
+    /// match any entry are kept as given.
+    pub fn canonicalize(&self, root: &AssetRoot, tracker: &Tracker, path: &str) -> String {
+        let mut dir = root.path.clone();
+        let mut res = String::with_capacity(path.len());
+        let mut components = path.split('/');
+        for component in components.by_ref() {
+            if !res.is_empty() {
+                res.push('/');
+            }
+            match self.find(root, tracker, &dir, component) {
+                Some(name) => {
+                    res.push_str(&name);
+                    dir.push(name);
+                }
+                None => {
+                    res.push_str(component);
+                    break;
+                }
+            }
+        }
+        for component in components {
+            res.push('/');
+            res.push_str(component);
+        }
+        res
+    }
+
+    /// Finds the entry in the given directory which matches the given name, ignoring case.
+    fn find(&self, root: &AssetRoot, tracker: &Tracker, dir: &Path, name: &str) -> Option<String> {
+        let mut dirs = self.dirs.lock().unwrap();
+
+        // Without a watcher, we can't tell when the listing changes, so it is always re-read
+        if root.watcher.is_none() || !matches!(dirs.get(dir), Some(index) if index.token.is_valid())
+        {
+            let dir_tracker = Tracker::default();
+            root.track_full_path(&dir_tracker, dir.to_owned());
+            let mut entries = HashMap::<String, Vec<String>>::new();
+            if let Ok(read_dir) = std::fs::read_dir(dir) {
+                for entry in read_dir.flatten() {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    entries.entry(name.to_lowercase()).or_default().push(name);
+                }
+            }
+            for names in entries.values_mut() {
+                names.sort();
+            }
+            let token = dir_tracker.get();
+            dirs.insert(dir.to_owned(), DirIndex { token, entries });
+        }
+        let index = &dirs[dir];
+        tracker.set(tracker.get() & index.token);
+        let names = index.entries.get(&name.to_lowercase())?;
+        if let Some(exact) = names.iter().find(|other| *other == name) {
+            return Some(exact.clone());
+        }
+        if names.len() > 1 && self.warned.lock().unwrap().insert(dir.join(name)) {
+            log::warn!(
+                target: "assetman",
+                "Ambiguous case-insensitive match for {:?} in {:?}, candidates are {:?}",
+                name,
+                dir,
+                names
+            );
+        }
+        Some(names[0].clone())
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+/// A compression format which is transparently applied to assets whose path has the
+/// corresponding suffix, such as `level.json.zst`. This requires the `compression` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// The gzip format, for the `.gz` suffix.
+    Gzip,
+
+    /// The zstd format, for the `.zst` suffix.
+    Zstd,
+
+    /// The LZ4 frame format, for the `.lz4` suffix.
+    Lz4,
+}
+
+impl Compression {
+    /// Gets the [`Compression`] for the given file extension, or [`None`] if it doesn't
+    /// correspond to a compression format.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            "lz4" => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Decompresses the given data.
+    #[cfg(feature = "compression")]
+    pub fn decompress(self, data: &[u8]) -> std::io::Result<Box<[u8]>> {
+        let mut res = Vec::new();
+        match self {
+            Self::Gzip => flate2::read::MultiGzDecoder::new(data).read_to_end(&mut res)?,
+            Self::Zstd => ruzstd::decoding::StreamingDecoder::new(data)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?
+                .read_to_end(&mut res)?,
+            Self::Lz4 => lz4_flex::frame::FrameDecoder::new(data).read_to_end(&mut res)?,
+        };
+        Ok(res.into_boxed_slice())
+    }
+
+    /// Compresses the given data.
+    #[cfg(feature = "compression")]
+    pub fn compress(self, data: &[u8]) -> std::io::Result<Box<[u8]>> {
+        let res = match self {
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Self::Zstd => {
+                ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest)
+            }
+            Self::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(data)?;
+                encoder.finish().map_err(std::io::Error::other)?
+            }
+        };
+        Ok(res.into_boxed_slice())
+    }
+}
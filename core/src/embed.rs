@@ -0,0 +1,95 @@
+use crate::{has_dir, list_children, AssetPath, AssetReader, AssetSource, Tracker};
+
+/// A directory tree which has been embedded into the binary at compile time, typically using the
+/// `embed_dir!` macro (requires the `embed` feature).
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedDir {
+    source_path: &'static str,
+    files: &'static [(&'static str, &'static [u8])],
+}
+
+impl EmbeddedDir {
+    /// Constructs an [`EmbeddedDir`] from the path of the directory it was embedded from and the
+    /// contents of the files in it. `files` must be sorted by path, and paths must be relative
+    /// and use `/` as a separator.
+    pub const fn new(
+        source_path: &'static str,
+        files: &'static [(&'static str, &'static [u8])],
+    ) -> Self {
+        Self { source_path, files }
+    }
+
+    /// Gets the path of the directory this [`EmbeddedDir`] was embedded from.
+    pub fn source_path(&self) -> &'static std::path::Path {
+        std::path::Path::new(self.source_path)
+    }
+
+    /// Constructs a root [`AssetPath`] for the contents of this [`EmbeddedDir`].
+    pub fn root(&self) -> AssetPath {
+        AssetPath::new_root(*self)
+    }
+
+    /// Constructs a root [`AssetPath`] for this [`EmbeddedDir`]. If `dev` is `true` and the
+    /// original directory still exists, assets will be read from it, with changes being watched,
+    /// instead of from the embedded copy.
+    pub fn root_dev(&self, dev: bool) -> AssetPath {
+        let source_path = self.source_path();
+        if dev && source_path.is_dir() {
+            AssetPath::new_root_fs(source_path)
+        } else {
+            self.root()
+        }
+    }
+
+    /// Gets the contents of the file at the given path.
+    fn get_file(&self, path: &str) -> Option<&'static [u8]> {
+        let index = self
+            .files
+            .binary_search_by(|(file_path, _)| (*file_path).cmp(path))
+            .ok()?;
+        Some(self.files[index].1)
+    }
+}
+
+impl AssetSource for EmbeddedDir {
+    fn load_bytes(&self, _: &Tracker, path: &str) -> std::io::Result<Box<[u8]>> {
+        match self.get_file(path) {
+            Some(data) => Ok(data.into()),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn load_range(
+        &self,
+        _: &Tracker,
+        path: &str,
+        offset: u64,
+        len: usize,
+    ) -> std::io::Result<Box<[u8]>> {
+        let data = self.get_file(path).ok_or(std::io::ErrorKind::NotFound)?;
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| data.get(start..start.checked_add(len)?))
+            .map(|range| range.into())
+            .ok_or(std::io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn open(&self, _: &Tracker, path: &str) -> std::io::Result<Box<dyn AssetReader>> {
+        match self.get_file(path) {
+            Some(data) => Ok(Box::new(std::io::Cursor::new(data))),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn get_children(&self, _: &Tracker, path: &str) -> std::io::Result<Vec<String>> {
+        list_children(self.files.iter().map(|(file_path, _)| *file_path), path)
+    }
+
+    fn is_dir(&self, _: &Tracker, path: &str) -> bool {
+        has_dir(self.files.iter().map(|(file_path, _)| *file_path), path)
+    }
+
+    fn track(&self, _: &Tracker, _: &str) {
+        // Embedded files never change
+    }
+}
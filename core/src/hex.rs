@@ -0,0 +1,24 @@
+//! Conversions between bytes and hex digits, as used for the text forms of [`crate::ContentHash`]es
+//! and of the keys and signatures for integrity manifests.
+
+/// Gets the lowercase hex digits for the given bytes.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        res.push(char::from_digit((byte >> 4) as u32, 16).unwrap());
+        res.push(char::from_digit((byte & 0xf) as u32, 16).unwrap());
+    }
+    res
+}
+
+/// Parses a fixed-length byte array from exactly `2 * N` hex digits.
+pub(crate) fn parse_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 || !s.is_ascii() {
+        return None;
+    }
+    let mut res = [0u8; N];
+    for (i, byte) in res.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(res)
+}
@@ -0,0 +1,277 @@
+use crate::hex::{parse_hex, to_hex};
+use crate::{
+    AssetInnerPath, AssetLoadError, AssetLoadResult, AssetPath, AssetReader, AssetSaveResult,
+    AssetSource, ContentHash, Tracker, WriteOptions,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+
+/// A list of the expected [`ContentHash`]es of the files in a directory, used to detect tampered
+/// assets (see [`AssetPath::new_root_verified`]).
+///
+/// The text form of a manifest has one line per file, consisting of its hash, a space, and its
+/// path relative to the directory. A manifest may be signed with an Ed25519 key, in which case the
+/// signature of its text form is stored alongside it, as 128 hex digits in a file with an
+/// additional `.sig` suffix.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityManifest {
+    files: BTreeMap<String, ContentHash>,
+}
+
+impl IntegrityManifest {
+    /// Generates a manifest for all of the files in the given directory.
+    pub fn generate(dir: &AssetPath) -> AssetLoadResult<Self> {
+        let tracker = Tracker::default();
+        let mut files = BTreeMap::new();
+        for asset in dir.get_descendants(&tracker)? {
+            // Hash the stored contents, since that's what a verified root will see
+            let data = asset
+                .root
+                .load_bytes(&tracker, &asset.inner.0)
+                .map_err(|err| AssetLoadError {
+                    asset: asset.clone(),
+                    inner: crate::io_error(err),
+                })?;
+            let path = match dir.inner.0.as_str() {
+                "" => asset.inner.0.clone(),
+                prefix => asset.inner.0[prefix.len() + 1..].to_owned(),
+            };
+            files.insert(path, ContentHash::of(&data));
+        }
+        Ok(Self { files })
+    }
+
+    /// Loads the manifest stored in the given asset. If a public key is given, the manifest must
+    /// have a valid signature for it.
+    pub fn load(asset: &AssetPath, public_key: Option<&VerifyingKey>) -> AssetLoadResult<Self> {
+        let tracker = Tracker::default();
+        let text = asset.load_bytes(&tracker)?;
+        if let Some(public_key) = public_key {
+            let sig_asset = signature_asset(asset);
+            let sig_text = sig_asset.load_bytes(&tracker)?;
+            crate::with_asset(&sig_asset, || {
+                let signature = std::str::from_utf8(&sig_text)
+                    .ok()
+                    .and_then(|sig_text| parse_hex(sig_text.trim()))
+                    .ok_or(IntegrityError::MalformedSignature)?;
+                public_key
+                    .verify_strict(&text, &Signature::from_bytes(&signature))
+                    .map_err(|_| IntegrityError::InvalidSignature)?;
+                Ok(())
+            })?;
+        }
+        crate::with_asset(asset, || {
+            let text = std::str::from_utf8(&text)?;
+            Ok(text.parse()?)
+        })
+    }
+
+    /// Stores this manifest in the given asset, along with its signature if a key is given.
+    pub fn save(&self, asset: &AssetPath, key: Option<&SigningKey>) -> AssetSaveResult<()> {
+        let text = self.to_string();
+        asset.write_bytes(text.as_bytes(), &WriteOptions::default())?;
+        if let Some(key) = key {
+            let sig_text = format!("{}\n", to_hex(&self.sign(key).to_bytes()));
+            signature_asset(asset).write_bytes(sig_text.as_bytes(), &WriteOptions::default())?;
+        }
+        Ok(())
+    }
+
+    /// Signs the text form of this manifest with the given key.
+    pub fn sign(&self, key: &SigningKey) -> Signature {
+        use ed25519_dalek::Signer;
+        key.sign(self.to_string().as_bytes())
+    }
+
+    /// Gets the expected hash of the file at the given path, or [`None`] if it isn't listed.
+    pub fn get(&self, path: &str) -> Option<ContentHash> {
+        self.files.get(path).copied()
+    }
+
+    /// Sets the expected hash of the file at the given path.
+    pub fn insert(&mut self, path: String, hash: ContentHash) {
+        self.files.insert(path, hash);
+    }
+
+    /// Removes the file at the given path from this manifest, returning its expected hash.
+    pub fn remove(&mut self, path: &str) -> Option<ContentHash> {
+        self.files.remove(path)
+    }
+
+    /// Iterates over the paths and expected hashes of the files in this manifest, in order of
+    /// path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, ContentHash)> {
+        self.files.iter().map(|(path, hash)| (path.as_str(), *hash))
+    }
+}
+
+impl std::fmt::Display for IntegrityManifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (path, hash) in self.files.iter() {
+            writeln!(f, "{} {}", hash, path)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for IntegrityManifest {
+    type Err = IntegrityError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut files = BTreeMap::new();
+        for line in s.lines().filter(|line| !line.is_empty()) {
+            let malformed = || IntegrityError::MalformedManifest(line.to_owned());
+            let (hash, path) = line.split_once(' ').ok_or_else(malformed)?;
+            files.insert(path.to_owned(), hash.parse().map_err(|_| malformed())?);
+        }
+        Ok(Self { files })
+    }
+}
+
+/// Parses a [`SigningKey`] from its text form, which is 64 hex digits. Surrounding whitespace is
+/// ignored.
+pub fn parse_signing_key(text: &str) -> Option<SigningKey> {
+    parse_hex(text.trim()).map(|bytes| SigningKey::from_bytes(&bytes))
+}
+
+/// Parses a [`VerifyingKey`] from its text form, which is 64 hex digits, as produced by
+/// [`format_verifying_key`]. Surrounding whitespace is ignored.
+pub fn parse_verifying_key(text: &str) -> Option<VerifyingKey> {
+    VerifyingKey::from_bytes(&parse_hex(text.trim())?).ok()
+}
+
+/// Gets the text form of a [`VerifyingKey`], which is 64 hex digits.
+pub fn format_verifying_key(key: &VerifyingKey) -> String {
+    to_hex(key.as_bytes())
+}
+
+/// Gets the asset which stores the signature for the manifest in the given asset.
+fn signature_asset(asset: &AssetPath) -> AssetPath {
+    AssetPath {
+        root: asset.root.clone(),
+        inner: AssetInnerPath(format!("{}.sig", asset.inner.0)),
+    }
+}
+
+/// The type of error produced when an asset or [`IntegrityManifest`] fails integrity
+/// verification.
+#[derive(thiserror::Error, Debug)]
+pub enum IntegrityError {
+    #[error("asset is not listed in the integrity manifest")]
+    Unlisted,
+    #[error("asset has hash {actual}, but the integrity manifest expects {expected}")]
+    Mismatch {
+        expected: ContentHash,
+        actual: ContentHash,
+    },
+    #[error("malformed integrity manifest line {0:?}")]
+    MalformedManifest(String),
+    #[error("malformed integrity manifest signature")]
+    MalformedSignature,
+    #[error("integrity manifest signature is not valid for the given key")]
+    InvalidSignature,
+}
+
+impl AssetPath {
+    /// Interpreting the given [`AssetPath`] as a directory, constructs a root [`AssetPath`] for
+    /// its contents which checks every file loaded against the given [`IntegrityManifest`]. Files
+    /// which aren't listed, or whose contents don't match, fail to load with an
+    /// [`IntegrityError`].
+    ///
+    /// As with [`AssetPath::new_root`], this should be called once per directory.
+    pub fn new_root_verified(dir: &AssetPath, manifest: IntegrityManifest) -> Self {
+        AssetPath::new_root(VerifiedSource {
+            inner: dir.root.clone(),
+            prefix: dir.inner.0.clone(),
+            manifest,
+        })
+    }
+}
+
+/// An [`AssetSource`] which verifies the files of an inner source against an
+/// [`IntegrityManifest`].
+struct VerifiedSource {
+    inner: Arc<dyn AssetSource>,
+
+    /// The path of the verified directory within the inner source.
+    prefix: String,
+    manifest: IntegrityManifest,
+}
+
+impl VerifiedSource {
+    /// Gets the path in the inner source for the given path.
+    fn inner_path(&self, path: &str) -> String {
+        match (self.prefix.as_str(), path) {
+            ("", path) => path.to_owned(),
+            (prefix, "") => prefix.to_owned(),
+            (prefix, path) => format!("{}/{}", prefix, path),
+        }
+    }
+}
+
+impl AssetSource for VerifiedSource {
+    fn load_bytes(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<[u8]>> {
+        let integrity_error = |err| std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+        let Some(expected) = self.manifest.get(path) else {
+            return Err(integrity_error(IntegrityError::Unlisted));
+        };
+        let data = self.inner.load_bytes(tracker, &self.inner_path(path))?;
+        let actual = ContentHash::of(&data);
+        if actual != expected {
+            return Err(integrity_error(IntegrityError::Mismatch {
+                expected,
+                actual,
+            }));
+        }
+        Ok(data)
+    }
+
+    fn get_children(&self, tracker: &Tracker, path: &str) -> std::io::Result<Vec<String>> {
+        self.inner.get_children(tracker, &self.inner_path(path))
+    }
+
+    fn is_dir(&self, tracker: &Tracker, path: &str) -> bool {
+        self.inner.is_dir(tracker, &self.inner_path(path))
+    }
+
+    fn track(&self, tracker: &Tracker, path: &str) {
+        self.inner.track(tracker, &self.inner_path(path))
+    }
+
+    fn normalize_path<'a>(&self, path: &'a str) -> std::borrow::Cow<'a, str> {
+        self.inner.normalize_path(path)
+    }
+
+    fn canonicalize_path(&self, path: &str) -> Option<String> {
+        let canonical = self.inner.canonicalize_path(&self.inner_path(path))?;
+        if self.prefix.is_empty() {
+            return Some(canonical);
+        }
+        let rest = canonical
+            .strip_prefix(self.prefix.as_str())?
+            .strip_prefix('/')?;
+        Some(rest.to_owned())
+    }
+
+    fn is_case_insensitive(&self) -> bool {
+        self.inner.is_case_insensitive()
+    }
+
+    fn open(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<dyn AssetReader>> {
+        // The whole file must be hashed before any of it can be trusted
+        Ok(Box::new(std::io::Cursor::new(
+            self.load_bytes(tracker, path)?,
+        )))
+    }
+
+    fn content_hash(&self, tracker: &Tracker, path: &str) -> Option<ContentHash> {
+        // Files which don't match the manifest can't be loaded, so the expected hash is accurate
+        self.track(tracker, path);
+        self.manifest.get(path)
+    }
+
+    fn modified(&self, tracker: &Tracker, path: &str) -> Option<std::time::SystemTime> {
+        self.inner.modified(tracker, &self.inner_path(path))
+    }
+}
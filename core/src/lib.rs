@@ -1,5 +1,29 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
+
+mod cache;
+mod compat;
+mod compress;
+mod embed;
+mod hex;
+#[cfg(feature = "integrity")]
+mod integrity;
+mod reader;
+mod refs;
+pub mod stats;
+mod store;
+
+pub use cache::*;
+pub use compress::*;
+pub use embed::*;
+#[cfg(feature = "integrity")]
+pub use integrity::*;
+pub use reader::*;
+pub use refs::*;
+pub use store::*;
+
+#[cfg(feature = "embed")]
+pub use assetman_embed::embed_dir;
 
 /// Represents a game asset or a directory of assets.
 ///
@@ -10,13 +34,13 @@ use std::sync::{Arc, Mutex};
 ///    assets.
 #[derive(Clone)]
 pub struct AssetPath {
-    root: Arc<AssetRoot>,
+    root: Arc<dyn AssetSource>,
     inner: AssetInnerPath,
 }
 
 impl PartialEq for AssetPath {
     fn eq(&self, other: &Self) -> bool {
-        std::ptr::eq(self.root.as_ref(), other.root.as_ref()) && self.inner == other.inner
+        std::ptr::eq(root_ptr(&self.root), root_ptr(&other.root)) && self.inner == other.inner
     }
 }
 
@@ -24,11 +48,16 @@ impl Eq for AssetPath {}
 
 impl std::hash::Hash for AssetPath {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        std::ptr::hash(self.root.as_ref(), state);
+        std::ptr::hash(root_ptr(&self.root), state);
         self.inner.hash(state);
     }
 }
 
+/// Gets a pointer which identifies the given root, ignoring its vtable.
+fn root_ptr(root: &Arc<dyn AssetSource>) -> *const () {
+    Arc::as_ptr(root) as *const ()
+}
+
 impl std::fmt::Debug for AssetPath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AssetPath")
@@ -37,6 +66,39 @@ impl std::fmt::Debug for AssetPath {
     }
 }
 
+/// A weak reference to the root of an [`AssetPath`], which identifies the root without keeping its
+/// [`AssetSource`] alive. See [`AssetPath::downgrade_root`].
+///
+/// Two [`WeakAssetRoot`]s are equal if they refer to the same root. Since a weak reference keeps
+/// the allocation for the root alive, it is never equal to the root of an unrelated
+/// [`AssetPath`] created after the root it refers to was dropped.
+#[derive(Clone)]
+pub struct WeakAssetRoot(Weak<dyn AssetSource>);
+
+impl WeakAssetRoot {
+    /// Gets the root [`AssetPath`] this refers to, or [`None`] if it has been dropped.
+    pub fn upgrade(&self) -> Option<AssetPath> {
+        Some(AssetPath {
+            root: self.0.upgrade()?,
+            inner: AssetInnerPath::root(),
+        })
+    }
+}
+
+impl PartialEq for WeakAssetRoot {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0.as_ptr() as *const (), other.0.as_ptr() as *const ())
+    }
+}
+
+impl Eq for WeakAssetRoot {}
+
+impl std::hash::Hash for WeakAssetRoot {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0.as_ptr() as *const (), state);
+    }
+}
+
 impl std::fmt::Display for AssetPath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         std::fmt::Debug::fmt(&self.inner.0, f)
@@ -47,15 +109,46 @@ impl AssetPath {
     /// Constructs a "root" [`AssetPath`] from the given file system path.
     ///
     /// As a root, the returned [`AssetPath`] does not allow access to any files or directories
-    /// outside of the given path. For best performance, this should be called once per asset
-    /// source, and all inner [`AssetPath`]s should be derived from the result of that call.
+    /// outside of the given path, including through symbolic links (see [`SymlinkPolicy`]).
+    /// Attempts to do so fail with a [`RootEscapeError`].
+    ///
+    /// For best performance, this should be called once per asset source, and all inner
+    /// [`AssetPath`]s should be derived from the result of that call.
     pub fn new_root_fs(path: &std::path::Path) -> Self {
+        Self::new_root_fs_with(path, &FsRootOptions::default())
+    }
+
+    /// Constructs a "root" [`AssetPath`] from the given file system path, using the given options.
+    ///
+    /// As with [`AssetPath::new_root_fs`], this should be called once per asset source.
+    pub fn new_root_fs_with(path: &std::path::Path, options: &FsRootOptions) -> Self {
+        Self::new_root(AssetRoot::new(path, options))
+    }
+
+    /// Constructs a "root" [`AssetPath`] for the given [`AssetSource`].
+    ///
+    /// As with [`AssetPath::new_root_fs`], this should be called once per asset source.
+    pub fn new_root(source: impl AssetSource) -> Self {
         Self {
-            root: Arc::new(AssetRoot::new(path)),
+            root: Arc::new(source),
             inner: AssetInnerPath::root(),
         }
     }
 
+    /// Gets the root [`AssetPath`] that this [`AssetPath`] was derived from.
+    pub fn root(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            inner: AssetInnerPath::root(),
+        }
+    }
+
+    /// Gets a weak reference to the root that this [`AssetPath`] was derived from, which can be
+    /// used to associate data with the root without keeping it alive.
+    pub fn downgrade_root(&self) -> WeakAssetRoot {
+        WeakAssetRoot(Arc::downgrade(&self.root))
+    }
+
     /// Gets the [`AssetPath`] for the directory this asset is in, or [`None`] if this is the root
     /// directory.
     pub fn parent(&self) -> Option<Self> {
@@ -68,18 +161,204 @@ impl AssetPath {
     /// Interpreting this [`AssetPath`] as a directory, constructs an [`AssetPath`] for an asset
     /// relative to it.
     pub fn relative(&self, path: &str) -> Self {
+        let mut inner = self.inner.relative(&self.root.normalize_path(path));
+        if let Some(path) = self.root.canonicalize_path(&inner.0) {
+            inner = AssetInnerPath(path);
+        }
         Self {
             root: self.root.clone(),
-            inner: self.inner.relative(path),
+            inner,
         }
     }
 
-    /// Gets the file extension of this asset, or [`None`] if not present.
-    pub fn extension(&self) -> Option<&str> {
-        self.inner.extension()
+    /// Gets the file extension of this asset, or [`None`] if not present. For compressed assets
+    /// (see [`AssetPath::compression`]), this is the extension of the decompressed asset, e.g.
+    /// `json` for `level.json.zst`.
+    ///
+    /// If the root of this asset is case-insensitive (see [`FsRootOptions::case_insensitive`]),
+    /// the extension is converted to lowercase, so that `.PNG` files get the same loaders as
+    /// `.png` files. Otherwise, it is returned as written, and is always borrowed.
+    pub fn extension(&self) -> Option<std::borrow::Cow<'_, str>> {
+        let extension = self.inner.extension()?;
+        if self.compression().is_some() {
+            let stem = &self.inner.0[..self.inner.0.len() - extension.len() - 1];
+            return stem.rfind('.').map(|pos| self.fold_case(&stem[pos + 1..]));
+        }
+        Some(self.fold_case(extension))
+    }
+
+    /// Converts the given part of this asset's path to lowercase if its root is
+    /// case-insensitive, avoiding an allocation if it is already lowercase.
+    fn fold_case<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.root.is_case_insensitive() && text.bytes().any(|b| b.is_ascii_uppercase()) {
+            std::borrow::Cow::Owned(text.to_ascii_lowercase())
+        } else {
+            std::borrow::Cow::Borrowed(text)
+        }
+    }
+
+    /// Gets the [`AssetPath`] for the sibling of this asset with the given file extension in
+    /// place of its own, e.g. `level.bin` for `level.json`. If this asset has no extension, the
+    /// given extension is appended.
+    pub fn with_extension(&self, extension: &str) -> Self {
+        Self {
+            root: self.root.clone(),
+            inner: self.inner.with_extension(extension),
+        }
+    }
+
+    /// Gets the [`Compression`] which is transparently applied to this asset, based on its
+    /// extension. When the `compression` feature is disabled, this is always [`None`].
+    pub fn compression(&self) -> Option<Compression> {
+        if cfg!(feature = "compression") {
+            Compression::from_extension(&self.fold_case(self.inner.extension()?))
+        } else {
+            None
+        }
     }
 }
 
+/// A virtual file system which provides the assets for a root [`AssetPath`].
+///
+/// Paths given to an [`AssetSource`] are relative to the root of the source, use `/` as a
+/// separator, and are empty for the root directory itself.
+pub trait AssetSource: Send + Sync + 'static {
+    /// Loads the full contents of the file at the given path.
+    fn load_bytes(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<[u8]>>;
+
+    /// Gets the names of the immediate children of the directory at the given path.
+    fn get_children(&self, tracker: &Tracker, path: &str) -> std::io::Result<Vec<String>>;
+
+    /// Determines whether there is a directory at the given path. By default, this checks whether
+    /// the directory can be listed using [`AssetSource::get_children`].
+    fn is_dir(&self, tracker: &Tracker, path: &str) -> bool {
+        self.get_children(tracker, path).is_ok()
+    }
+
+    /// Ensures that the given [`Tracker`] is notified when the file at the given path is
+    /// modified.
+    fn track(&self, tracker: &Tracker, path: &str);
+
+    /// Converts a path given to [`AssetPath::relative`] into the form used by this source, with `/`
+    /// as the only separator.
+    fn normalize_path<'a>(&self, path: &'a str) -> std::borrow::Cow<'a, str> {
+        std::borrow::Cow::Borrowed(path)
+    }
+
+    /// Gets the canonical form of the given path, as constructed by [`AssetPath::relative`], so
+    /// that paths which refer to the same file are equal, or [`None`] if the path is already in
+    /// canonical form. By default, every path is canonical.
+    fn canonicalize_path(&self, path: &str) -> Option<String> {
+        let _ = path;
+        None
+    }
+
+    /// Determines whether paths in this source are matched case-insensitively, in which case
+    /// [`AssetPath::extension`] is converted to lowercase. By default, paths are case-sensitive.
+    fn is_case_insensitive(&self) -> bool {
+        false
+    }
+
+    /// Opens the file at the given path for streaming reads. By default, this loads the full
+    /// contents of the file using [`AssetSource::load_bytes`].
+    fn open(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<dyn AssetReader>> {
+        let bytes = self.load_bytes(tracker, path)?;
+        Ok(Box::new(std::io::Cursor::new(bytes)))
+    }
+
+    /// Loads `len` bytes starting at `offset` from the file at the given path, failing if the file
+    /// is too short. By default, this seeks within the reader returned by [`AssetSource::open`].
+    fn load_range(
+        &self,
+        tracker: &Tracker,
+        path: &str,
+        offset: u64,
+        len: usize,
+    ) -> std::io::Result<Box<[u8]>> {
+        use std::io::{Read, Seek};
+        let mut reader = self.open(tracker, path)?;
+
+        // Avoid allocating a buffer for a range which can't be read
+        if let Some(size) = reader.size_hint() {
+            if offset.saturating_add(len as u64) > size {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        reader.seek(std::io::SeekFrom::Start(offset))?;
+        let mut data = vec![0u8; len].into_boxed_slice();
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Opens the file at the given path on the native file system. This is only supported by
+    /// sources which are backed by the native file system.
+    fn open_file(&self, tracker: &Tracker, path: &str) -> std::io::Result<std::fs::File> {
+        let _ = (tracker, path);
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Atomically replaces the contents of the file at the given path, creating it and its parent
+    /// directories if needed. This is only supported by writable sources.
+    fn write_bytes(&self, path: &str, data: &[u8], options: &WriteOptions) -> std::io::Result<()> {
+        let _ = (path, data, options);
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Gets the hash of the contents of the file at the given path, if this source identifies
+    /// files by their contents. Files with the same [`ContentHash`] have identical contents.
+    fn content_hash(&self, tracker: &Tracker, path: &str) -> Option<ContentHash> {
+        let _ = (tracker, path);
+        None
+    }
+
+    /// Gets the time when the file at the given path was last modified, if this source keeps
+    /// track of modification times and the file exists.
+    fn modified(&self, tracker: &Tracker, path: &str) -> Option<std::time::SystemTime> {
+        let _ = (tracker, path);
+        None
+    }
+}
+
+/// Gets the names of the immediate children of the directory at the given path, given the
+/// sorted paths of all files in a source.
+fn list_children<'a>(
+    files: impl IntoIterator<Item = &'a str>,
+    path: &str,
+) -> std::io::Result<Vec<String>> {
+    let mut children = Vec::<String>::new();
+    for file_path in files {
+        let rest = if path.is_empty() {
+            file_path
+        } else if let Some(rest) = file_path
+            .strip_prefix(path)
+            .and_then(|rest| rest.strip_prefix('/'))
+        {
+            rest
+        } else {
+            continue;
+        };
+        let name = rest.split('/').next().unwrap();
+        if children.last().map(|last| last.as_str()) != Some(name) {
+            children.push(name.to_owned());
+        }
+    }
+    if children.is_empty() && !path.is_empty() {
+        return Err(std::io::ErrorKind::NotFound.into());
+    }
+    Ok(children)
+}
+
+/// Determines whether there is a directory at the given path, given the paths of all files in a
+/// source.
+fn has_dir<'a>(files: impl IntoIterator<Item = &'a str>, path: &str) -> bool {
+    path.is_empty()
+        || files.into_iter().any(|file_path| {
+            file_path
+                .strip_prefix(path)
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+}
+
 /// Identifies a directory on the file system where assets are stored and watches for changes in
 /// the directory.
 struct AssetRoot {
@@ -89,6 +368,65 @@ struct AssetRoot {
     /// The file system watcher used to detect changes in the directory, or [`None`] if watching is
     /// disabled or if we failed to create a watcher.
     watcher: Option<AssetRootWatcher>,
+
+    /// Whether `\` is interpreted as a path separator.
+    backslash_separators: bool,
+
+    /// The index used to resolve paths case-insensitively, or [`None`] if paths are
+    /// case-sensitive.
+    case_index: Option<compat::CaseIndex>,
+
+    /// Determines which symbolic links may be followed when accessing files in the directory.
+    symlinks: SymlinkPolicy,
+}
+
+/// Options for a file system root created using [`AssetPath::new_root_fs_with`].
+///
+/// These are useful for assets authored on Windows, where paths are case-insensitive and `\` is a
+/// path separator.
+#[derive(Debug, Clone, Default)]
+pub struct FsRootOptions {
+    /// If `true`, `\` is treated as a path separator in paths given to [`AssetPath::relative`].
+    pub backslash_separators: bool,
+
+    /// If `true`, paths which don't exactly match a file or directory are resolved by comparing
+    /// against directory listings, ignoring case. When multiple entries match, a warning is
+    /// logged once and the first in lexicographical order is used. Paths constructed by
+    /// [`AssetPath::relative`] use the casing of the matched entries, so that paths which refer
+    /// to the same file are equal.
+    pub case_insensitive: bool,
+
+    /// Determines which symbolic links may be followed.
+    pub symlinks: SymlinkPolicy,
+}
+
+/// Determines which symbolic links may be followed when accessing assets in a file system root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Symbolic links are followed only if their targets are inside the root.
+    #[default]
+    WithinRoot,
+
+    /// Symbolic links are followed regardless of their targets.
+    Follow,
+
+    /// Paths which pass through a symbolic link can't be accessed.
+    Deny,
+}
+
+/// The type of error produced when an asset path refers to a file outside of its file system
+/// root, or to a symbolic link that is not allowed by the root's [`SymlinkPolicy`].
+#[derive(thiserror::Error, Debug)]
+pub enum RootEscapeError {
+    /// The path, or the target of a symbolic link it passes through, is outside of the root. The
+    /// path which was found to be outside of the root is given.
+    #[error("path resolves to {0:?}, which is outside of the asset root")]
+    OutsideRoot(std::path::PathBuf),
+
+    /// The path passes through a symbolic link, and the root's [`SymlinkPolicy`] is
+    /// [`SymlinkPolicy::Deny`].
+    #[error("path passes through a symbolic link, which is not allowed for this asset root")]
+    Symlink,
 }
 
 /// Provides information about the file system watcher used to detect changes in an asset root
@@ -101,11 +439,35 @@ struct AssetRootWatcher {
     /// A mapping from files and directories that are being watched to the [`renege::Condition`]
     /// that must be invalidated when the file or directory contents are changed.
     paths: std::sync::Arc<Mutex<HashMap<std::path::PathBuf, renege::Condition>>>,
+
+    /// A mapping from files written with [`WriteOptions::suppress_invalidation`] to the
+    /// [`FileStamp`] they had after the write, or [`None`] for the temporary files used for the
+    /// write. Changes are ignored while a file still has its stamp, so that invalidations caused
+    /// by our own writes are suppressed, but later changes by other programs are not.
+    suppressed: std::sync::Arc<Mutex<HashMap<std::path::PathBuf, Option<FileStamp>>>>,
+}
+
+/// Identifies a version of a file by its size and modification time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: std::time::SystemTime,
+}
+
+impl FileStamp {
+    /// Gets the [`FileStamp`] for the file at the given path, or [`None`] if it can't be accessed.
+    fn get(path: &std::path::Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
 }
 
 impl AssetRoot {
     /// Creates a new [`AssetRoot`] for the given directory.
-    pub fn new(path: &std::path::Path) -> Self {
+    pub fn new(path: &std::path::Path, options: &FsRootOptions) -> Self {
         let path = path.canonicalize().unwrap();
         let watcher = AssetRootWatcher::new(&path)
             .map_err(|err| {
@@ -117,37 +479,103 @@ impl AssetRoot {
                 );
             })
             .ok();
-        Self { path, watcher }
+        Self {
+            path,
+            watcher,
+            backslash_separators: options.backslash_separators,
+            case_index: options.case_insensitive.then(compat::CaseIndex::default),
+            symlinks: options.symlinks,
+        }
+    }
+
+    /// Gets the full path on the file system for the given path within this root.
+    fn full_path(&self, tracker: &Tracker, path: &str) -> std::path::PathBuf {
+        match &self.case_index {
+            Some(case_index) => case_index.resolve(self, tracker, path),
+            None => self.path.join(path),
+        }
     }
 
-    /// Opens a file given its relative path in the asset root directory.
-    pub fn open_file(
+    /// Checks that the file or directory at the given full path, which need not exist, can be
+    /// accessed according to the [`SymlinkPolicy`] for this root, returning the path that should
+    /// be used to access it. Unless any symbolic link may be followed, this is the canonical path
+    /// that was checked, so that a symbolic link replaced after the check isn't followed instead.
+    fn check_access(&self, full_path: &std::path::Path) -> std::io::Result<std::path::PathBuf> {
+        use std::path::Component;
+        let escape =
+            |err: RootEscapeError| std::io::Error::new(std::io::ErrorKind::PermissionDenied, err);
+        let rel_path = full_path
+            .strip_prefix(&self.path)
+            .map_err(|_| escape(RootEscapeError::OutsideRoot(full_path.to_owned())))?;
+        if !rel_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(escape(RootEscapeError::OutsideRoot(full_path.to_owned())));
+        }
+        if self.symlinks == SymlinkPolicy::Follow {
+            return Ok(full_path.to_owned());
+        }
+
+        // The path may not exist yet, in which case we check the closest ancestor that does
+        let Some((existing, target)) = full_path
+            .ancestors()
+            .find_map(|path| Some((path, path.canonicalize().ok()?)))
+        else {
+            return Ok(full_path.to_owned());
+        };
+        if !target.starts_with(&self.path) {
+            return Err(escape(RootEscapeError::OutsideRoot(target)));
+        }
+        if self.symlinks == SymlinkPolicy::Deny && target != existing {
+            return Err(escape(RootEscapeError::Symlink));
+        }
+        match full_path.strip_prefix(existing).unwrap() {
+            rest if rest.as_os_str().is_empty() => Ok(target),
+            rest => Ok(target.join(rest)),
+        }
+    }
+
+    /// Replaces the file at the given path by writing the given data to a temporary file and
+    /// renaming it, suppressing the watcher events for both files if requested.
+    fn replace_file(
         &self,
-        tracker: &Tracker,
-        relative_path: &std::path::Path,
-    ) -> std::io::Result<std::fs::File> {
-        let full_path = self.path.join(relative_path);
-        let file = std::fs::File::open(&full_path)?;
+        temp_path: &std::path::Path,
+        write_path: &std::path::Path,
+        data: &[u8],
+        options: &WriteOptions,
+    ) -> std::io::Result<()> {
         if let Some(watcher) = &self.watcher {
-            use std::collections::hash_map::Entry::*;
-            let mut paths = watcher.paths.lock().unwrap();
-            let token = match paths.entry(full_path) {
-                Occupied(entry) => entry.get().token(),
-                Vacant(entry) => entry.insert(renege::Condition::new()).token(),
+            if options.suppress_invalidation {
+                watcher
+                    .suppressed
+                    .lock()
+                    .unwrap()
+                    .insert(temp_path.to_owned(), None);
+            }
+        }
+        std::fs::create_dir_all(write_path.parent().unwrap())?;
+        std::fs::write(temp_path, data)?;
+
+        // Renaming preserves the stamp of the temporary file, so the stamp of the written file is
+        // known before it appears
+        if let Some(watcher) = &self.watcher {
+            let mut suppressed = watcher.suppressed.lock().unwrap();
+            match FileStamp::get(temp_path).filter(|_| options.suppress_invalidation) {
+                Some(stamp) => suppressed.insert(write_path.to_owned(), Some(stamp)),
+                None => suppressed.remove(write_path),
             };
-            tracker.set(tracker.get() & token);
-        };
-        Ok(file)
+        }
+        if let Err(err) = std::fs::rename(temp_path, write_path) {
+            let _ = std::fs::remove_file(temp_path);
+            return Err(err);
+        }
+        Ok(())
     }
 
-    /// Ensures that the given [`Tracker`] is notified when the file at the given relative path
-    /// is modified.
-    pub fn track_file(
-        &self,
-        tracker: &Tracker,
-        relative_path: &std::path::Path,
-    ) {
-        let full_path = self.path.join(relative_path);
+    /// Ensures that the given [`Tracker`] is notified when the file or directory at the given
+    /// full path is modified.
+    fn track_full_path(&self, tracker: &Tracker, full_path: std::path::PathBuf) {
         if let Some(watcher) = &self.watcher {
             use std::collections::hash_map::Entry::*;
             let mut paths = watcher.paths.lock().unwrap();
@@ -158,28 +586,117 @@ impl AssetRoot {
             tracker.set(tracker.get() & token);
         };
     }
+}
 
-    /// Gets the names of the immediate children of a given directory in the asset root directory.
-    pub fn get_children(
-        &self,
-        tracker: &Tracker,
-        relative_path: &std::path::Path,
-    ) -> std::io::Result<Vec<std::ffi::OsString>> {
-        let full_path = self.path.join(relative_path);
-        let children = std::fs::read_dir(&full_path)?
-            .map(|entry| entry.map(|entry| entry.file_name()))
-            .collect::<Result<_, _>>()?;
+impl AssetSource for AssetRoot {
+    fn load_bytes(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<[u8]>> {
+        let mut file = self.open_file(tracker, path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut bytes = Vec::with_capacity(size as usize);
+        std::io::Read::read_to_end(&mut file, &mut bytes)?;
+        Ok(bytes.into_boxed_slice())
+    }
+
+    fn open(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<dyn AssetReader>> {
+        Ok(Box::new(self.open_file(tracker, path)?))
+    }
+
+    fn open_file(&self, tracker: &Tracker, path: &str) -> std::io::Result<std::fs::File> {
+        // Track the file before opening it, so that the tracker is notified if a missing file is
+        // created
+        let full_path = self.full_path(tracker, path);
+        self.track_full_path(tracker, full_path.clone());
+        std::fs::File::open(self.check_access(&full_path)?)
+    }
+
+    fn track(&self, tracker: &Tracker, path: &str) {
+        let full_path = self.full_path(tracker, path);
+        self.track_full_path(tracker, full_path);
+    }
+
+    fn normalize_path<'a>(&self, path: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.backslash_separators && path.contains('\\') {
+            std::borrow::Cow::Owned(path.replace('\\', "/"))
+        } else {
+            std::borrow::Cow::Borrowed(path)
+        }
+    }
+
+    fn is_case_insensitive(&self) -> bool {
+        self.case_index.is_some()
+    }
+
+    fn canonicalize_path(&self, path: &str) -> Option<String> {
+        let case_index = self.case_index.as_ref().filter(|_| !path.is_empty())?;
+        let canonical = case_index.canonicalize(self, &Tracker::default(), path);
+        (canonical != path).then_some(canonical)
+    }
+
+    fn write_bytes(&self, path: &str, data: &[u8], options: &WriteOptions) -> std::io::Result<()> {
+        let full_path = self.full_path(&Tracker::default(), path);
+        let write_path = self.check_access(&full_path)?;
+        let file_name = write_path
+            .file_name()
+            .ok_or(std::io::ErrorKind::InvalidInput)?;
+        let temp_path = write_path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+        let existed = write_path.exists();
+        let res = self.replace_file(&temp_path, &write_path, data, options);
+
+        // The entries for the temporary file, and for the written file if it wasn't written, are
+        // no longer needed
+        if let Some(watcher) = &self.watcher {
+            let mut suppressed = watcher.suppressed.lock().unwrap();
+            suppressed.remove(&temp_path);
+            if res.is_err() {
+                suppressed.remove(&write_path);
+            }
+        }
+        res?;
+
+        // Notify trackers now, rather than waiting for the watcher
         if let Some(watcher) = &self.watcher {
-            use std::collections::hash_map::Entry::*;
             let mut paths = watcher.paths.lock().unwrap();
-            let token = match paths.entry(full_path) {
-                Occupied(entry) => entry.get().token(),
-                Vacant(entry) => entry.insert(renege::Condition::new()).token(),
-            };
-            tracker.set(tracker.get() & token);
-        };
+            if !options.suppress_invalidation {
+                paths.remove(&full_path);
+            }
+            if !existed {
+                // Creating the file may have created its parent directories as well
+                for dir in full_path.ancestors().skip(1) {
+                    paths.remove(dir);
+                    if dir == self.path {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_children(&self, tracker: &Tracker, path: &str) -> std::io::Result<Vec<String>> {
+        let full_path = self.full_path(tracker, path);
+        let children = std::fs::read_dir(self.check_access(&full_path)?)?
+            .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<_, _>>()?;
+        self.track_full_path(tracker, full_path);
         Ok(children)
     }
+
+    fn is_dir(&self, tracker: &Tracker, path: &str) -> bool {
+        let full_path = self.full_path(tracker, path);
+        self.track_full_path(tracker, full_path.clone());
+        self.check_access(&full_path)
+            .and_then(std::fs::metadata)
+            .is_ok_and(|metadata| metadata.is_dir())
+    }
+
+    fn modified(&self, tracker: &Tracker, path: &str) -> Option<std::time::SystemTime> {
+        let full_path = self.full_path(tracker, path);
+        self.track_full_path(tracker, full_path.clone());
+        std::fs::metadata(self.check_access(&full_path).ok()?)
+            .ok()?
+            .modified()
+            .ok()
+    }
 }
 
 impl AssetRootWatcher {
@@ -187,13 +704,48 @@ impl AssetRootWatcher {
     pub fn new(path: &std::path::Path) -> notify::Result<Self> {
         use notify::Watcher;
         let paths = std::sync::Arc::new(Mutex::new(HashMap::new()));
+        let suppressed = std::sync::Arc::new(Mutex::new(HashMap::new()));
         let mut source = notify::RecommendedWatcher::new(
             {
                 let paths = paths.clone();
+                let suppressed = suppressed.clone();
                 move |res: notify::Result<notify::Event>| {
                     if let Ok(event) = res {
+                        // Loading an asset accesses it, which shouldn't invalidate it
+                        use notify::event::{AccessKind, AccessMode};
+                        if let notify::EventKind::Access(kind) = event.kind {
+                            if kind != AccessKind::Close(AccessMode::Write) {
+                                return;
+                            }
+                        }
+                        // Adding or removing a file also changes the contents of its directory
+                        use notify::event::ModifyKind;
+                        let changes_dir = matches!(
+                            event.kind,
+                            notify::EventKind::Create(_)
+                                | notify::EventKind::Remove(_)
+                                | notify::EventKind::Modify(ModifyKind::Name(_))
+                        );
+                        let mut suppressed = suppressed.lock().unwrap();
                         let mut paths = paths.lock().unwrap();
                         for path in event.paths {
+                            match suppressed.get(&path) {
+                                Some(None) => continue,
+                                Some(Some(stamp)) => {
+                                    // Once the file has changed from what we wrote, changes are no
+                                    // longer ours
+                                    if FileStamp::get(&path) == Some(*stamp) {
+                                        continue;
+                                    }
+                                    suppressed.remove(&path);
+                                }
+                                None => {}
+                            }
+                            if changes_dir {
+                                if let Some(parent) = path.parent() {
+                                    paths.remove(parent);
+                                }
+                            }
                             paths.remove(&path);
                         }
                     }
@@ -202,7 +754,11 @@ impl AssetRootWatcher {
             Default::default(),
         )?;
         source.watch(path, notify::RecursiveMode::Recursive)?;
-        Ok(Self { source, paths })
+        Ok(Self {
+            source,
+            paths,
+            suppressed,
+        })
     }
 }
 
@@ -268,6 +824,17 @@ impl AssetInnerPath {
             None
         }
     }
+
+    /// Gets the [`AssetInnerPath`] for the sibling of this asset with the given file extension in
+    /// place of its own.
+    pub fn with_extension(&self, extension: &str) -> Self {
+        let name_start = self.0.rfind('/').map_or(0, |pos| pos + 1);
+        let stem = match self.0[name_start..].rfind('.') {
+            Some(pos) if pos > 0 => &self.0[..name_start + pos],
+            _ => &self.0,
+        };
+        Self(format!("{}.{}", stem, extension))
+    }
 }
 
 impl From<String> for AssetInnerPath {
@@ -280,52 +847,199 @@ impl From<String> for AssetInnerPath {
 pub type Tracker = std::cell::Cell<renege::Token>;
 
 impl AssetPath {
-    /// Loads a data file as raw bytes.
+    /// Loads a data file as raw bytes, decompressing it if needed (see
+    /// [`AssetPath::compression`]).
     pub fn load_bytes(&self, tracker: &Tracker) -> AssetLoadResult<Box<[u8]>> {
-        let mut file = self.open_file(tracker)?;
-        with_asset(self, || {
-            let size = file.metadata().map(|m| m.len()).unwrap_or(0);
-            let mut bytes = Vec::with_capacity(size as usize);
-            std::io::Read::read_to_end(&mut file, &mut bytes)?;
-            Ok(bytes.into_boxed_slice())
+        let _scope = stats::LoadScope::new(self, "bytes");
+        let res = self
+            .root
+            .load_bytes(tracker, &self.inner.0)
+            .and_then(|bytes| {
+                stats::record_bytes_read(bytes.len() as u64);
+                self.decompress(bytes)
+            });
+        res.map_err(|err| AssetLoadError {
+            asset: self.clone(),
+            inner: io_error(err),
         })
     }
 
-    /// Opens the file for the given asset.
+    /// Decompresses the stored contents of this asset according to [`AssetPath::compression`].
+    fn decompress(&self, data: Box<[u8]>) -> std::io::Result<Box<[u8]>> {
+        #[cfg(feature = "compression")]
+        if let Some(compression) = self.compression() {
+            return compression.decompress(&data);
+        }
+        Ok(data)
+    }
+
+    /// Loads `len` bytes starting at `offset` from a data file, failing if the file is too short.
+    /// This avoids loading the rest of the file, for sources which support it and assets which
+    /// aren't compressed.
+    pub fn load_range(
+        &self,
+        tracker: &Tracker,
+        offset: u64,
+        len: usize,
+    ) -> AssetLoadResult<Box<[u8]>> {
+        if self.compression().is_some() {
+            let bytes = self.load_bytes(tracker)?;
+            let range = usize::try_from(offset)
+                .ok()
+                .and_then(|start| bytes.get(start..start.checked_add(len)?));
+            return match range {
+                Some(range) => Ok(range.into()),
+                None => Err(AssetLoadError {
+                    asset: self.clone(),
+                    inner: Box::new(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)),
+                }),
+            };
+        }
+        let _scope = stats::LoadScope::new(self, "bytes");
+        match self.root.load_range(tracker, &self.inner.0, offset, len) {
+            Ok(bytes) => {
+                stats::record_bytes_read(bytes.len() as u64);
+                Ok(bytes)
+            }
+            Err(err) => Err(AssetLoadError {
+                asset: self.clone(),
+                inner: io_error(err),
+            }),
+        }
+    }
+
+    /// Opens the given asset for streaming reads. Unlike [`AssetPath::load_bytes`], this allows
+    /// reading part of an asset without loading all of it, for sources which support it.
+    ///
+    /// For load statistics, bytes are recorded as they are read, for the innermost active
+    /// [`stats::LoadScope`] at the time of the read.
+    ///
+    /// Compressed assets (see [`AssetPath::compression`]) are decompressed in full when opened.
+    pub fn open(&self, tracker: &Tracker) -> AssetLoadResult<Box<dyn AssetReader>> {
+        if self.compression().is_some() {
+            return Ok(Box::new(std::io::Cursor::new(self.load_bytes(tracker)?)));
+        }
+        match self.root.open(tracker, &self.inner.0) {
+            Ok(reader) => Ok(Box::new(reader::StatsReader(reader))),
+            Err(err) => Err(AssetLoadError {
+                asset: self.clone(),
+                inner: io_error(err),
+            }),
+        }
+    }
+
+    /// Opens the file for the given asset. This is only supported for assets whose root is backed
+    /// by the native file system, such as those created by [`AssetPath::new_root_fs`]. The file
+    /// is not decompressed.
+    ///
+    /// For load statistics, the entire file is assumed to be read by the innermost active
+    /// [`stats::LoadScope`], since reads from the returned file can't be observed.
     pub fn open_file(&self, tracker: &Tracker) -> AssetLoadResult<std::fs::File> {
-        match self
-            .root
-            .open_file(tracker, std::path::Path::new(&*self.inner.0))
-        {
-            Ok(file) => Ok(file),
+        match self.root.open_file(tracker, &self.inner.0) {
+            Ok(file) => {
+                #[cfg(feature = "stats")]
+                if let Ok(metadata) = file.metadata() {
+                    stats::record_bytes_read(metadata.len());
+                }
+                Ok(file)
+            }
             Err(err) => Err(AssetLoadError {
                 asset: self.clone(),
-                inner: err.into(),
+                inner: io_error(err),
             }),
         }
     }
 
     /// Ensures that the given [`Tracker`] is notified when this asset is modified.
     pub fn track(&self, tracker: &Tracker) {
-        self.root.track_file(tracker, std::path::Path::new(&*self.inner.0));
+        self.root.track(tracker, &self.inner.0);
+    }
+
+    /// Atomically replaces the contents of this asset with the given data, creating it if needed.
+    /// This is only supported for assets whose root is writable, such as those created by
+    /// [`AssetPath::new_root_fs`]. The data is compressed if needed (see
+    /// [`AssetPath::compression`]).
+    pub fn write_bytes(&self, data: &[u8], options: &WriteOptions) -> AssetSaveResult<()> {
+        self.compress(data)
+            .and_then(|data| self.root.write_bytes(&self.inner.0, &data, options))
+            .map_err(|err| AssetSaveError {
+                asset: self.clone(),
+                inner: io_error(err),
+            })
+    }
+
+    /// Compresses data to be stored for this asset according to [`AssetPath::compression`].
+    fn compress<'a>(&self, data: &'a [u8]) -> std::io::Result<std::borrow::Cow<'a, [u8]>> {
+        #[cfg(feature = "compression")]
+        if let Some(compression) = self.compression() {
+            return Ok(compression.compress(data)?.into_vec().into());
+        }
+        Ok(data.into())
+    }
+
+    /// Gets the hash of the contents of this asset, if its root identifies assets by their
+    /// contents, such as a [`ContentStore`].
+    pub fn content_hash(&self, tracker: &Tracker) -> Option<ContentHash> {
+        self.root.content_hash(tracker, &self.inner.0)
+    }
+
+    /// Gets the time when this asset was last modified, if its root keeps track of modification
+    /// times and the asset exists. The given [`Tracker`] is notified when the asset changes.
+    pub fn modified(&self, tracker: &Tracker) -> Option<std::time::SystemTime> {
+        self.root.modified(tracker, &self.inner.0)
     }
 
     /// Gets the names of the immediate children of the given asset directory.
     pub fn get_children(&self, tracker: &Tracker) -> AssetLoadResult<Vec<String>> {
-        match self
-            .root
-            .get_children(tracker, std::path::Path::new(&*self.inner.0))
-        {
-            Ok(children) => Ok(children
-                .into_iter()
-                .map(|s| s.to_string_lossy().into_owned())
-                .collect()),
+        match self.root.get_children(tracker, &self.inner.0) {
+            Ok(children) => Ok(children),
             Err(err) => Err(AssetLoadError {
                 asset: self.clone(),
-                inner: err.into(),
+                inner: io_error(err),
             }),
         }
     }
+
+    /// Determines whether this asset is a directory. The given [`Tracker`] is notified when this
+    /// changes.
+    pub fn is_dir(&self, tracker: &Tracker) -> bool {
+        self.root.is_dir(tracker, &self.inner.0)
+    }
+
+    /// Gets all assets within the given asset directory, recursively. Directories themselves are
+    /// not included.
+    pub fn get_descendants(&self, tracker: &Tracker) -> AssetLoadResult<Vec<AssetPath>> {
+        let mut assets = Vec::new();
+        let mut stack = vec![self.clone()];
+        while let Some(dir) = stack.pop() {
+            for name in dir.get_children(tracker)? {
+                let child = dir.relative(&name);
+                if child.is_dir(tracker) {
+                    stack.push(child);
+                } else {
+                    assets.push(child);
+                }
+            }
+        }
+        Ok(assets)
+    }
+}
+
+/// Converts an error from an [`AssetSource`] into an [`AssetLoadInnerError`], unwrapping
+/// [`RootEscapeError`]s and integrity errors so that they can be downcast to directly.
+fn io_error(err: std::io::Error) -> AssetLoadInnerError {
+    let unwrap = err.get_ref().is_some_and(|inner| {
+        #[cfg(feature = "integrity")]
+        if inner.is::<IntegrityError>() {
+            return true;
+        }
+        inner.is::<RootEscapeError>()
+    });
+    if unwrap {
+        err.into_inner().unwrap()
+    } else {
+        err.into()
+    }
 }
 
 /// Executes an inner closure and tags errors that occur with a particular asset path.
@@ -356,3 +1070,26 @@ pub struct AssetLoadError {
 
 /// The inner content of an [`AssetLoadError`], which doesn't specify the asset path.
 pub type AssetLoadInnerError = Box<dyn std::error::Error>;
+
+/// Options for writing an asset using [`AssetPath::write_bytes`].
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// If `true`, trackers which have observed the asset are not notified of the write. This is
+    /// useful for editors which save a value they already have, and don't want to reload it.
+    pub suppress_invalidation: bool,
+}
+
+/// The result of saving an asset.
+pub type AssetSaveResult<T> = Result<T, AssetSaveError>;
+
+/// Describes an error that can occur while saving an asset.
+#[derive(thiserror::Error, Debug)]
+#[error("failed to save asset {asset}: {inner}")]
+pub struct AssetSaveError {
+    /// The path to the asset that we attempted to save.
+    pub asset: AssetPath,
+
+    /// Describes the error that occurred.
+    #[source]
+    pub inner: AssetLoadInnerError,
+}
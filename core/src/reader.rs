@@ -0,0 +1,53 @@
+use crate::stats;
+use std::io::{Read, Seek, SeekFrom};
+
+/// A readable and seekable stream for the contents of an asset, as returned by
+/// [`crate::AssetPath::open`].
+///
+/// This allows loaders to read only the parts of an asset they need, such as a file header.
+pub trait AssetReader: Read + Seek + Send {
+    /// Gets the total size of the asset, in bytes, if known.
+    fn size_hint(&self) -> Option<u64>;
+}
+
+impl AssetReader for std::fs::File {
+    fn size_hint(&self) -> Option<u64> {
+        self.metadata().ok().map(|metadata| metadata.len())
+    }
+}
+
+impl<T: AsRef<[u8]> + Send> AssetReader for std::io::Cursor<T> {
+    fn size_hint(&self) -> Option<u64> {
+        Some(self.get_ref().as_ref().len() as u64)
+    }
+}
+
+impl<R: AssetReader + ?Sized> AssetReader for Box<R> {
+    fn size_hint(&self) -> Option<u64> {
+        (**self).size_hint()
+    }
+}
+
+/// Wraps a reader to record the bytes read from it in load statistics, for the innermost active
+/// [`stats::LoadScope`] at the time of each read.
+pub(crate) struct StatsReader<R>(pub R);
+
+impl<R: Read> Read for StatsReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.0.read(buf)?;
+        stats::record_bytes_read(len as u64);
+        Ok(len)
+    }
+}
+
+impl<R: Seek> Seek for StatsReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl<R: AssetReader> AssetReader for StatsReader<R> {
+    fn size_hint(&self) -> Option<u64> {
+        self.0.size_hint()
+    }
+}
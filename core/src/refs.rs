@@ -0,0 +1,158 @@
+use crate::{AssetLoadError, AssetLoadResult, AssetPath};
+use std::collections::{HashMap, HashSet};
+
+/// Describes the outgoing references of every asset in a project, used to detect dangling
+/// references, orphaned assets and reference cycles without loading asset payloads.
+pub struct ReferenceGraph {
+    assets: Vec<AssetPath>,
+    references: HashMap<AssetPath, Vec<AssetPath>>,
+    errors: Vec<AssetLoadError>,
+}
+
+impl ReferenceGraph {
+    /// Builds a [`ReferenceGraph`] for the given set of assets, using `scan` to enumerate the
+    /// outgoing references of each asset.
+    ///
+    /// Assets which fail to scan are treated as having no references, and their errors are
+    /// available from [`ReferenceGraph::errors`].
+    pub fn build(
+        assets: impl IntoIterator<Item = AssetPath>,
+        mut scan: impl FnMut(&AssetPath) -> AssetLoadResult<Vec<AssetPath>>,
+    ) -> Self {
+        let assets = assets.into_iter().collect::<Vec<_>>();
+        let mut references = HashMap::new();
+        let mut errors = Vec::new();
+        for asset in assets.iter() {
+            match scan(asset) {
+                Ok(targets) => {
+                    references.insert(asset.clone(), targets);
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+        Self {
+            assets,
+            references,
+            errors,
+        }
+    }
+
+    /// The assets in this graph.
+    pub fn assets(&self) -> &[AssetPath] {
+        &self.assets
+    }
+
+    /// Gets the outgoing references of the given asset.
+    pub fn references(&self, asset: &AssetPath) -> &[AssetPath] {
+        self.references.get(asset).map_or(&[], |targets| targets)
+    }
+
+    /// The errors that occurred while scanning assets for references.
+    pub fn errors(&self) -> &[AssetLoadError] {
+        &self.errors
+    }
+
+    /// Gets all references whose target is not an asset in this graph, as `(source, target)`
+    /// pairs.
+    pub fn missing(&self) -> Vec<(AssetPath, AssetPath)> {
+        let assets = self.assets.iter().collect::<HashSet<_>>();
+        let mut res = Vec::new();
+        for source in self.assets.iter() {
+            for target in self.references(source) {
+                if !assets.contains(target) {
+                    res.push((source.clone(), target.clone()));
+                }
+            }
+        }
+        res
+    }
+
+    /// Gets all assets which are not referenced by any other asset, excluding the given entry
+    /// point assets.
+    pub fn orphans(&self, entries: &[AssetPath]) -> Vec<AssetPath> {
+        let mut referenced = entries.iter().collect::<HashSet<_>>();
+        for source in self.assets.iter() {
+            for target in self.references(source) {
+                if target != source {
+                    referenced.insert(target);
+                }
+            }
+        }
+        self.assets
+            .iter()
+            .filter(|asset| !referenced.contains(asset))
+            .cloned()
+            .collect()
+    }
+
+    /// Gets the reference cycles in this graph. Each cycle is given as a set of assets which are
+    /// all reachable from one another.
+    pub fn cycles(&self) -> Vec<Vec<AssetPath>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: HashMap::new(),
+            low_link: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
+            components: Vec::new(),
+        };
+        for asset in self.assets.iter() {
+            if !tarjan.index.contains_key(asset) {
+                tarjan.visit(asset);
+            }
+        }
+        tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.references(&component[0]).contains(&component[0])
+            })
+            .collect()
+    }
+}
+
+/// The state for Tarjan's strongly connected components algorithm, used to find cycles in a
+/// [`ReferenceGraph`].
+struct Tarjan<'a> {
+    graph: &'a ReferenceGraph,
+    index: HashMap<&'a AssetPath, usize>,
+    low_link: HashMap<&'a AssetPath, usize>,
+    stack: Vec<&'a AssetPath>,
+    on_stack: HashSet<&'a AssetPath>,
+    components: Vec<Vec<AssetPath>>,
+}
+
+impl<'a> Tarjan<'a> {
+    /// Visits the given asset, adding all strongly connected components reachable from it to
+    /// `components`.
+    fn visit(&mut self, asset: &'a AssetPath) {
+        let index = self.index.len();
+        self.index.insert(asset, index);
+        self.low_link.insert(asset, index);
+        self.stack.push(asset);
+        self.on_stack.insert(asset);
+        for target in self.graph.references(asset) {
+            if !self.index.contains_key(target) {
+                self.visit(target);
+                let low_link = self.low_link[asset].min(self.low_link[target]);
+                self.low_link.insert(asset, low_link);
+            } else if self.on_stack.contains(target) {
+                let low_link = self.low_link[asset].min(self.index[target]);
+                self.low_link.insert(asset, low_link);
+            }
+        }
+        if self.low_link[asset] == index {
+            let mut component = Vec::new();
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack.remove(member);
+                component.push(member.clone());
+                if member == asset {
+                    break;
+                }
+            }
+            component.reverse();
+            self.components.push(component);
+        }
+    }
+}
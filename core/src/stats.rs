@@ -0,0 +1,283 @@
+//! Instrumentation for asset loading.
+//!
+//! Loaders mark the extent of each load with a [`LoadScope`]. When the `stats` feature is
+//! enabled, scopes are recorded into a process-wide [`LoadReport`] (available from [`report`])
+//! and emitted as [`tracing`] spans. Otherwise, scopes do nothing.
+use crate::AssetPath;
+#[cfg(feature = "stats")]
+use std::collections::HashMap;
+#[cfg(feature = "stats")]
+use std::sync::Mutex;
+#[cfg(feature = "stats")]
+use std::time::{Duration, Instant};
+
+/// Marks the extent of an asset load for the purposes of load statistics. The load ends when the
+/// scope is dropped.
+///
+/// Scopes created while another scope is active on the same thread are recorded as nested loads
+/// of that scope.
+pub struct LoadScope {
+    /// The `(generation, index)` of the [`LoadRecord`] for this scope.
+    #[cfg(feature = "stats")]
+    record: (u64, usize),
+    #[cfg(feature = "stats")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl LoadScope {
+    /// Begins a load of the given asset. `kind` identifies the loader being used (e.g. `"json"`).
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub fn new(asset: &AssetPath, kind: &'static str) -> Self {
+        #[cfg(feature = "stats")]
+        {
+            let span = tracing::info_span!("load_asset", asset = %asset, kind).entered();
+            let parent = STACK.with_borrow(|stack| stack.last().copied());
+            let mut state = STATE.lock().unwrap();
+            let state = state.get_or_insert_with(|| State {
+                epoch: Instant::now(),
+                generation: 0,
+                records: Vec::new(),
+            });
+            let index = state.records.len();
+            state.records.push(LoadRecord {
+                asset: asset.to_string(),
+                kind,
+                parent: parent
+                    .filter(|(generation, _)| *generation == state.generation)
+                    .map(|(_, index)| index),
+                thread: THREAD_ID.with(|id| *id),
+                start: state.epoch.elapsed(),
+                duration: Duration::ZERO,
+                bytes_read: 0,
+                cache_hit: None,
+            });
+            let record = (state.generation, index);
+            STACK.with_borrow_mut(|stack| stack.push(record));
+            Self {
+                record,
+                _span: span,
+            }
+        }
+        #[cfg(not(feature = "stats"))]
+        Self {}
+    }
+
+    /// Records whether this load was satisfied from a cache.
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub fn record_cache_hit(&self, hit: bool) {
+        #[cfg(feature = "stats")]
+        self.with_record(|record, _| record.cache_hit = Some(hit));
+    }
+
+    /// Updates the [`LoadRecord`] for this scope, if it hasn't been discarded by [`reset`]. The
+    /// time the first load was recorded is also provided.
+    #[cfg(feature = "stats")]
+    fn with_record(&self, f: impl FnOnce(&mut LoadRecord, Instant)) {
+        let (generation, index) = self.record;
+        let mut state = STATE.lock().unwrap();
+        if let Some(state) = state.as_mut() {
+            if state.generation == generation {
+                let epoch = state.epoch;
+                f(&mut state.records[index], epoch);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Drop for LoadScope {
+    fn drop(&mut self) {
+        self.with_record(|record, epoch| record.duration = epoch.elapsed() - record.start);
+        STACK.with_borrow_mut(|stack| {
+            if let Some(pos) = stack.iter().rposition(|record| *record == self.record) {
+                stack.remove(pos);
+            }
+        });
+    }
+}
+
+/// Records that the given number of bytes were read as part of the innermost active
+/// [`LoadScope`] on this thread, if any.
+#[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+pub fn record_bytes_read(bytes: u64) {
+    #[cfg(feature = "stats")]
+    {
+        let Some((generation, index)) = STACK.with_borrow(|stack| stack.last().copied()) else {
+            return;
+        };
+        if let Some(state) = STATE.lock().unwrap().as_mut() {
+            if state.generation == generation {
+                state.records[index].bytes_read += bytes;
+            }
+        }
+    }
+}
+
+/// The process-wide state for load statistics.
+#[cfg(feature = "stats")]
+struct State {
+    epoch: Instant,
+    generation: u64,
+    records: Vec<LoadRecord>,
+}
+
+#[cfg(feature = "stats")]
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+#[cfg(feature = "stats")]
+thread_local! {
+    /// The `(generation, index)` of the [`LoadRecord`]s for the active scopes on this thread.
+    static STACK: std::cell::RefCell<Vec<(u64, usize)>> = const { std::cell::RefCell::new(Vec::new()) };
+
+    /// A small identifier for this thread, used in traces.
+    static THREAD_ID: u64 = {
+        static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+        NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    };
+}
+
+/// Gets a snapshot of all loads recorded since the start of the process, or since the last call to
+/// [`reset`].
+#[cfg(feature = "stats")]
+pub fn report() -> LoadReport {
+    let state = STATE.lock().unwrap();
+    LoadReport {
+        records: state
+            .as_ref()
+            .map(|state| state.records.clone())
+            .unwrap_or_default(),
+    }
+}
+
+/// Discards all recorded loads.
+#[cfg(feature = "stats")]
+pub fn reset() {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.generation += 1;
+        state.records.clear();
+    }
+}
+
+/// Describes a single asset load recorded by a [`LoadScope`].
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone)]
+pub struct LoadRecord {
+    /// The path of the asset that was loaded.
+    pub asset: String,
+
+    /// Identifies the loader that was used.
+    pub kind: &'static str,
+
+    /// The index of the load that this load was nested in, if any.
+    pub parent: Option<usize>,
+
+    /// An identifier for the thread the load occurred on.
+    pub thread: u64,
+
+    /// The time the load started, relative to the first recorded load.
+    pub start: Duration,
+
+    /// The time the load took, or zero if it is still in progress.
+    pub duration: Duration,
+
+    /// The number of bytes read directly by this load, excluding nested loads.
+    pub bytes_read: u64,
+
+    /// Whether the load was satisfied from a cache, or [`None`] if no cache was consulted.
+    pub cache_hit: Option<bool>,
+}
+
+/// A queryable collection of [`LoadRecord`]s.
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    records: Vec<LoadRecord>,
+}
+
+#[cfg(feature = "stats")]
+impl LoadReport {
+    /// Gets all records in this report, in the order the loads started.
+    pub fn records(&self) -> &[LoadRecord] {
+        &self.records
+    }
+
+    /// Iterates over the records for loads of the given asset.
+    pub fn for_asset<'a>(&'a self, asset: &AssetPath) -> impl Iterator<Item = &'a LoadRecord> {
+        let asset = asset.to_string();
+        self.records
+            .iter()
+            .filter(move |record| record.asset == asset)
+    }
+
+    /// Iterates over the indices of the loads directly nested in the load with the given index.
+    pub fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        (index + 1..self.records.len()).filter(move |i| self.records[*i].parent == Some(index))
+    }
+
+    /// Gets the total time spent loading each asset, excluding time spent in nested loads, sorted
+    /// from slowest to fastest.
+    pub fn slowest(&self) -> Vec<(&str, Duration)> {
+        let mut nested = vec![Duration::ZERO; self.records.len()];
+        for record in self.records.iter() {
+            if let Some(parent) = record.parent {
+                nested[parent] += record.duration;
+            }
+        }
+        let mut totals = HashMap::<&str, Duration>::new();
+        for (record, nested) in self.records.iter().zip(nested) {
+            *totals.entry(&record.asset).or_default() += record.duration.saturating_sub(nested);
+        }
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by_key(|(_, total)| std::cmp::Reverse(*total));
+        totals
+    }
+
+    /// Writes this report in the Chrome trace event format, which can be viewed in
+    /// `chrome://tracing` or Perfetto.
+    pub fn write_chrome_trace(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(b"[")?;
+        for (i, record) in self.records.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b",\n")?;
+            }
+            write!(writer, "{{\"name\":")?;
+            write_json_string(&mut writer, &record.asset)?;
+            write!(
+                writer,
+                ",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":{},\
+                \"args\":{{\"bytes_read\":{}",
+                record.kind,
+                record.start.as_micros(),
+                record.duration.as_micros(),
+                record.thread,
+                record.bytes_read
+            )?;
+            if let Some(hit) = record.cache_hit {
+                write!(writer, ",\"cache_hit\":{}", hit)?;
+            }
+            writer.write_all(b"}}")?;
+        }
+        writer.write_all(b"]\n")
+    }
+
+    /// Saves this report to a file in the Chrome trace event format.
+    pub fn save_chrome_trace(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        self.write_chrome_trace(std::io::BufWriter::new(file))
+    }
+}
+
+/// Writes a string as a quoted and escaped JSON string.
+#[cfg(feature = "stats")]
+fn write_json_string(writer: &mut impl std::io::Write, value: &str) -> std::io::Result<()> {
+    writer.write_all(b"\"")?;
+    for ch in value.chars() {
+        match ch {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            ch if (ch as u32) < 0x20 => write!(writer, "\\u{:04x}", ch as u32)?,
+            ch => write!(writer, "{}", ch)?,
+        }
+    }
+    writer.write_all(b"\"")
+}
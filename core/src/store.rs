@@ -0,0 +1,275 @@
+use crate::hex::{parse_hex, to_hex};
+use crate::{has_dir, list_children, AssetPath, AssetReader, AssetSource, Tracker, WriteOptions};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The SHA-256 hash of the contents of an asset, used to identify it in a [`ContentStore`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ContentHash(pub [u8; 32]);
+
+impl ContentHash {
+    /// Computes the [`ContentHash`] for the given data.
+    pub fn of(data: &[u8]) -> Self {
+        use sha2::Digest;
+        Self(sha2::Sha256::digest(data).into())
+    }
+}
+
+impl std::fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl std::fmt::Debug for ContentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContentHash({})", self)
+    }
+}
+
+impl std::str::FromStr for ContentHash {
+    type Err = ParseContentHashError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hex(s).map(Self).ok_or(ParseContentHashError)
+    }
+}
+
+/// The type of error produced when parsing a malformed [`ContentHash`].
+#[derive(Debug, thiserror::Error)]
+#[error("malformed content hash")]
+pub struct ParseContentHashError;
+
+/// A content-addressed asset store.
+///
+/// Files are stored as blobs named by their [`ContentHash`], so that files with identical contents
+/// are only stored once, and an index file maps asset paths to hashes. The layout of the store
+/// directory is:
+///  * `index`: one line per asset, consisting of its hash, a space, and its path.
+///  * `blobs/<first two hash digits>/<remaining hash digits>`: the contents of each blob.
+///
+/// Assets in the store are accessed through the [`AssetPath`] returned by [`ContentStore::root`].
+/// Since these assets report a [`AssetPath::content_hash`], an [`crate::AssetCache`] will share
+/// values between all paths with the same contents and extension.
+///
+/// This is cheap to clone, and all clones refer to the same store.
+#[derive(Clone)]
+pub struct ContentStore {
+    inner: Arc<ContentStoreInner>,
+}
+
+/// The shared state of a [`ContentStore`].
+struct ContentStoreInner {
+    /// The path to the store directory.
+    dir: PathBuf,
+
+    /// A mapping from asset paths to the hashes of their contents.
+    index: Mutex<BTreeMap<String, ContentHash>>,
+
+    /// A mapping from asset paths which have been observed to the [`renege::Condition`] that must
+    /// be invalidated when the asset, or the listing of the directory, changes.
+    conditions: Mutex<HashMap<String, renege::Condition>>,
+}
+
+impl ContentStore {
+    /// Opens the content store in the given directory, creating it if it doesn't exist.
+    pub fn open(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir.join("blobs"))?;
+        let mut index = BTreeMap::new();
+        match std::fs::read_to_string(dir.join("index")) {
+            Ok(source) => {
+                for line in source.lines().filter(|line| !line.is_empty()) {
+                    let Some((hash, path)) = line.split_once(' ') else {
+                        return Err(invalid_index_error(line));
+                    };
+                    let hash = hash.parse().map_err(|_| invalid_index_error(line))?;
+                    index.insert(path.to_owned(), hash);
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        Ok(Self {
+            inner: Arc::new(ContentStoreInner {
+                dir: dir.to_owned(),
+                index: Mutex::new(index),
+                conditions: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+
+    /// Constructs a root [`AssetPath`] for the assets in this store.
+    ///
+    /// As with [`AssetPath::new_root`], this should be called once per store.
+    pub fn root(&self) -> AssetPath {
+        AssetPath::new_root(self.clone())
+    }
+
+    /// Gets the hash of the contents of the asset at the given path, or [`None`] if there is no
+    /// such asset in the store.
+    pub fn hash(&self, path: &str) -> Option<ContentHash> {
+        self.inner.index.lock().unwrap().get(path).copied()
+    }
+
+    /// Stores the given data as the asset at the given path, replacing any existing asset at that
+    /// path. The data is only written if the store does not already contain a blob with the same
+    /// contents.
+    pub fn import(&self, path: &str, data: &[u8]) -> std::io::Result<ContentHash> {
+        self.import_with(path, data, &WriteOptions::default())
+    }
+
+    /// Stores the given data as the asset at the given path, using the given options.
+    fn import_with(
+        &self,
+        path: &str,
+        data: &[u8],
+        options: &WriteOptions,
+    ) -> std::io::Result<ContentHash> {
+        let hash = ContentHash::of(data);
+        self.write_blob(hash, data)?;
+        let mut index = self.inner.index.lock().unwrap();
+        let old_hash = index.insert(path.to_owned(), hash);
+        if old_hash != Some(hash) {
+            self.save_index(&index)?;
+            drop(index);
+            if old_hash.is_none() || !options.suppress_invalidation {
+                self.invalidate(path, options.suppress_invalidation);
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Recursively imports all files in the given directory on the file system, placing them at
+    /// the same relative paths in the store.
+    pub fn import_dir(&self, dir: &Path) -> std::io::Result<()> {
+        self.import_dir_at(dir, "")
+    }
+
+    /// Recursively imports all files in the given directory on the file system, placing them in
+    /// the store directory at the given path.
+    fn import_dir_at(&self, dir: &Path, path: &str) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let child_path = if path.is_empty() {
+                name
+            } else {
+                format!("{}/{}", path, name)
+            };
+            if entry.file_type()?.is_dir() {
+                self.import_dir_at(&entry.path(), &child_path)?;
+            } else {
+                self.import(&child_path, &std::fs::read(entry.path())?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the path to the blob file for the given hash.
+    fn blob_path(&self, hash: ContentHash) -> PathBuf {
+        let hash = hash.to_string();
+        self.inner
+            .dir
+            .join("blobs")
+            .join(&hash[..2])
+            .join(&hash[2..])
+    }
+
+    /// Writes a blob to the store, if it doesn't already exist.
+    fn write_blob(&self, hash: ContentHash, data: &[u8]) -> std::io::Result<()> {
+        let path = self.blob_path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        write_atomic(&path, data)
+    }
+
+    /// Writes the given index to the index file.
+    fn save_index(&self, index: &BTreeMap<String, ContentHash>) -> std::io::Result<()> {
+        let mut source = Vec::new();
+        for (path, hash) in index.iter() {
+            writeln!(source, "{} {}", hash, path)?;
+        }
+        write_atomic(&self.inner.dir.join("index"), &source)
+    }
+
+    /// Notifies trackers that have observed the asset at the given path, or the listing of any of
+    /// its parent directories. If `dirs_only` is set, only the directory listings are invalidated.
+    fn invalidate(&self, path: &str, dirs_only: bool) {
+        let mut conditions = self.inner.conditions.lock().unwrap();
+        if !dirs_only {
+            conditions.remove(path);
+        }
+        for (i, _) in path.match_indices('/') {
+            conditions.remove(&path[..i]);
+        }
+        conditions.remove("");
+    }
+}
+
+/// Writes a file by writing to a temporary file and renaming it, so that readers never observe a
+/// partially-written file.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Constructs the error produced when the index file of a [`ContentStore`] contains the given
+/// malformed line.
+fn invalid_index_error(line: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("malformed content store index entry: {:?}", line),
+    )
+}
+
+impl AssetSource for ContentStore {
+    fn load_bytes(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<[u8]>> {
+        let Some(hash) = self.content_hash(tracker, path) else {
+            return Err(std::io::ErrorKind::NotFound.into());
+        };
+        Ok(std::fs::read(self.blob_path(hash))?.into_boxed_slice())
+    }
+
+    fn open(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<dyn AssetReader>> {
+        let Some(hash) = self.content_hash(tracker, path) else {
+            return Err(std::io::ErrorKind::NotFound.into());
+        };
+        Ok(Box::new(std::fs::File::open(self.blob_path(hash))?))
+    }
+
+    fn get_children(&self, tracker: &Tracker, path: &str) -> std::io::Result<Vec<String>> {
+        self.track(tracker, path);
+        let index = self.inner.index.lock().unwrap();
+        list_children(index.keys().map(|key| key.as_str()), path)
+    }
+
+    fn is_dir(&self, tracker: &Tracker, path: &str) -> bool {
+        self.track(tracker, path);
+        let index = self.inner.index.lock().unwrap();
+        has_dir(index.keys().map(|key| key.as_str()), path)
+    }
+
+    fn track(&self, tracker: &Tracker, path: &str) {
+        use std::collections::hash_map::Entry::*;
+        let mut conditions = self.inner.conditions.lock().unwrap();
+        let token = match conditions.entry(path.to_owned()) {
+            Occupied(entry) => entry.get().token(),
+            Vacant(entry) => entry.insert(renege::Condition::new()).token(),
+        };
+        tracker.set(tracker.get() & token);
+    }
+
+    fn write_bytes(&self, path: &str, data: &[u8], options: &WriteOptions) -> std::io::Result<()> {
+        self.import_with(path, data, options)?;
+        Ok(())
+    }
+
+    fn content_hash(&self, tracker: &Tracker, path: &str) -> Option<ContentHash> {
+        self.track(tracker, path);
+        self.hash(path)
+    }
+}
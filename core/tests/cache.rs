@@ -0,0 +1,91 @@
+use assetman::{AssetCache, AssetLoadResult, AssetPath, Tracker};
+use assetman_test_util::TempDir;
+use std::cell::Cell;
+
+#[test]
+fn test_cache_eviction() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(env!("CARGO_MANIFEST_DIR")));
+    let tracker = Tracker::default();
+    let [a, b] = ["Cargo.toml", "src/lib.rs"].map(|s| root.relative(s));
+    let loads = Cell::new(0);
+    let load = |asset| load_counted(asset, &loads);
+    let a_len = a.load_bytes(&tracker).unwrap().len();
+    let cache = AssetCache::new(a_len);
+
+    // Repeated loads hit the cache
+    cache.get_or_load(&a, &tracker, load(&a)).unwrap();
+    cache.get_or_load(&a, &tracker, load(&a)).unwrap();
+    assert_eq!(loads.get(), 1);
+    assert_eq!(cache.size(), a_len);
+
+    // Loading another asset exceeds the budget and evicts the first
+    cache.get_or_load(&b, &tracker, load(&b)).unwrap();
+    cache.get_or_load(&a, &tracker, load(&a)).unwrap();
+    assert_eq!(loads.get(), 3);
+
+    // Assets with outstanding handles are not evicted
+    let handle = cache.get_or_load(&a, &tracker, load(&a)).unwrap();
+    let weak = handle.downgrade();
+    let b_handle = cache.get_or_load(&b, &tracker, load(&b)).unwrap();
+    cache.get_or_load(&a, &tracker, load(&a)).unwrap();
+    assert_eq!(loads.get(), 4);
+    assert_eq!(handle.get().len(), a_len);
+    assert_eq!(handle.generation(), 0);
+
+    // When the last handle for an asset is dropped, the cache is brought back within budget
+    assert_eq!(cache.size(), a_len + b_handle.get().len());
+    drop(b_handle);
+    assert_eq!(cache.size(), a_len);
+
+    // Once all handles are dropped, the asset can be evicted
+    drop(handle);
+    assert!(weak.upgrade().is_some());
+    cache.clear();
+    assert!(weak.upgrade().is_none());
+    assert_eq!(cache.size(), 0);
+}
+
+/// Loads the bytes for an asset, counting the number of loads.
+fn load_counted<'a>(
+    asset: &'a AssetPath,
+    loads: &'a Cell<u32>,
+) -> impl FnOnce(&Tracker) -> AssetLoadResult<Box<[u8]>> + 'a {
+    move |tracker| {
+        loads.set(loads.get() + 1);
+        asset.load_bytes(tracker)
+    }
+}
+
+#[test]
+fn test_cache_reload() {
+    let dir = TempDir::new("cache");
+    std::fs::write(dir.join("data.txt"), "first").unwrap();
+    std::fs::write(dir.join("other.txt"), "other").unwrap();
+    let root = AssetPath::new_root_fs(&dir);
+    let [asset, other] = ["data.txt", "other.txt"].map(|s| root.relative(s));
+    let cache = AssetCache::new(5);
+    let tracker = Tracker::default();
+    cache
+        .get_or_load(&other, &tracker, |tracker| other.load_bytes(tracker))
+        .unwrap();
+    let handle = cache
+        .get_or_load(&asset, &tracker, |tracker| asset.load_bytes(tracker))
+        .unwrap();
+    assert_eq!(&**handle.get(), b"first");
+
+    // Modify the asset and wait for the change to be noticed
+    std::fs::write(dir.join("data.txt"), "second").unwrap();
+    let start = std::time::Instant::now();
+    while tracker.get().is_valid() && start.elapsed() < std::time::Duration::from_secs(5) {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let tracker = Tracker::default();
+    cache
+        .get_or_load(&asset, &tracker, |tracker| asset.load_bytes(tracker))
+        .unwrap();
+    assert_eq!(handle.generation(), 1);
+    assert_eq!(&**handle.get(), b"second");
+
+    // Reloading a value with outstanding handles doesn't evict other values
+    assert_eq!(cache.size(), 11);
+}
@@ -0,0 +1,61 @@
+use assetman::{AssetPath, FsRootOptions, Tracker};
+use assetman_test_util::TempDir;
+
+#[test]
+fn test_windows_paths() {
+    let dir = TempDir::new("compat");
+    std::fs::create_dir_all(dir.join("textures")).unwrap();
+    std::fs::write(dir.join("textures/wood.png"), "wood").unwrap();
+    std::fs::write(dir.join("textures/Stone.png"), "stone 1").unwrap();
+    std::fs::write(dir.join("textures/stone.PNG"), "stone 2").unwrap();
+    let tracker = Tracker::default();
+
+    // Windows-style paths fail by default
+    let root = AssetPath::new_root_fs(&dir);
+    assert!(root
+        .relative("Textures\\Wood.PNG")
+        .load_bytes(&tracker)
+        .is_err());
+    let stone = root.relative("textures/stone.PNG");
+    assert_eq!(stone.extension().as_deref(), Some("PNG"));
+
+    // ...but can be resolved in compatibility mode
+    let root = AssetPath::new_root_fs_with(
+        &dir,
+        &FsRootOptions {
+            backslash_separators: true,
+            case_insensitive: true,
+            ..Default::default()
+        },
+    );
+    let wood = root.relative("Textures\\Wood.PNG");
+    assert_eq!(wood.to_string(), "\"textures/wood.png\"");
+    assert_eq!(wood, root.relative("textures/wood.png"));
+    assert_eq!(&*wood.load_bytes(&tracker).unwrap(), b"wood");
+    let missing = root.relative("Textures/Missing.PNG");
+    assert_eq!(missing.to_string(), "\"textures/Missing.PNG\"");
+    assert_eq!(missing.extension().as_deref(), Some("png"));
+    assert_eq!(
+        root.relative("TEXTURES")
+            .get_children(&tracker)
+            .unwrap()
+            .len(),
+        3
+    );
+
+    // Exact matches are preferred, and ambiguous matches are resolved consistently
+    let stone = root.relative("textures/stone.PNG");
+    assert_eq!(&*stone.load_bytes(&tracker).unwrap(), b"stone 2");
+    let stone = root.relative("textures/STONE.png");
+    assert_eq!(stone, root.relative("textures/Stone.png"));
+    assert_eq!(&*stone.load_bytes(&tracker).unwrap(), b"stone 1");
+
+    // Newly-created files are found
+    std::fs::write(dir.join("textures/metal.png"), "metal").unwrap();
+    let start = std::time::Instant::now();
+    let metal = root.relative("TEXTURES/Metal.png");
+    while metal.load_bytes(&tracker).is_err() && start.elapsed().as_secs() < 5 {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(&*metal.load_bytes(&tracker).unwrap(), b"metal");
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "compression")]
+use assetman::{AssetPath, Compression, Tracker, WriteOptions};
+use assetman_test_util::TempDir;
+use std::io::Read;
+
+#[test]
+fn test_compression() {
+    let dir = TempDir::new("compress");
+    let root = AssetPath::new_root_fs(&dir);
+    let data = "compressible ".repeat(100);
+    let tracker = Tracker::default();
+    for (name, compression) in [
+        ("level.json.gz", Compression::Gzip),
+        ("level.json.zst", Compression::Zstd),
+        ("level.json.lz4", Compression::Lz4),
+    ] {
+        let asset = root.relative(name);
+        assert_eq!(asset.compression(), Some(compression));
+        assert_eq!(asset.extension().as_deref(), Some("json"));
+
+        // Data is compressed when written and decompressed when loaded
+        asset
+            .write_bytes(data.as_bytes(), &WriteOptions::default())
+            .unwrap();
+        let stored = std::fs::read(dir.join(name)).unwrap();
+        assert!(stored.len() < data.len());
+        assert_eq!(&*compression.decompress(&stored).unwrap(), data.as_bytes());
+        assert_eq!(&*asset.load_bytes(&tracker).unwrap(), data.as_bytes());
+        assert_eq!(
+            &*asset.load_range(&tracker, 13, 12).unwrap(),
+            b"compressible"
+        );
+        let mut opened = String::new();
+        asset
+            .open(&tracker)
+            .unwrap()
+            .read_to_string(&mut opened)
+            .unwrap();
+        assert_eq!(opened, data);
+    }
+
+    // Corrupt data is reported as an error
+    std::fs::write(dir.join("bad.bin.gz"), b"not gzip").unwrap();
+    assert!(root.relative("bad.bin.gz").load_bytes(&tracker).is_err());
+    assert_eq!(root.relative("archive.gz").extension(), None);
+}
@@ -0,0 +1,113 @@
+use assetman::{AssetPath, FsRootOptions, RootEscapeError, SymlinkPolicy, Tracker};
+use assetman_test_util::TempDir;
+
+/// Creates a directory containing an asset root, `root`, and a file outside of it, `secret.txt`.
+fn setup(name: &str) -> TempDir {
+    let dir = TempDir::new(name);
+    std::fs::create_dir_all(dir.join("root/sub")).unwrap();
+    std::fs::write(dir.join("root/sub/data.txt"), "data").unwrap();
+    std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+    dir
+}
+
+#[test]
+fn test_parent_escape() {
+    let dir = setup("escape-parent");
+    let root = AssetPath::new_root_fs(&dir.join("root"));
+    let tracker = Tracker::default();
+    let asset = root.relative("sub/../../secret.txt");
+    assert_eq!(asset, root.relative("secret.txt"));
+    assert!(asset.load_bytes(&tracker).is_err());
+    assert_eq!(
+        &*root
+            .relative("sub")
+            .relative("../sub/./data.txt")
+            .load_bytes(&tracker)
+            .unwrap(),
+        b"data"
+    );
+}
+
+#[test]
+fn test_absolute_escape() {
+    let dir = setup("escape-absolute");
+    let root = AssetPath::new_root_fs(&dir.join("root"));
+    let tracker = Tracker::default();
+
+    // Absolute paths, as may appear in a glTF URI, are interpreted relative to the root
+    let secret = dir.join("secret.txt").canonicalize().unwrap();
+    let asset = root.relative(secret.to_str().unwrap());
+    assert!(asset.load_bytes(&tracker).is_err());
+    std::fs::create_dir_all(
+        dir.join("root")
+            .join(secret.parent().unwrap().strip_prefix("/").unwrap()),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("root").join(secret.strip_prefix("/").unwrap()),
+        "inside",
+    )
+    .unwrap();
+    assert_eq!(&*asset.load_bytes(&tracker).unwrap(), b"inside");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_escape() {
+    let dir = setup("escape-symlink");
+    std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("root/escape.txt")).unwrap();
+    std::os::unix::fs::symlink(dir.join("root/sub"), dir.join("root/alias")).unwrap();
+    let tracker = Tracker::default();
+
+    // By default, only symlinks within the root are followed
+    let root = AssetPath::new_root_fs(&dir.join("root"));
+    let err = root
+        .relative("escape.txt")
+        .load_bytes(&tracker)
+        .unwrap_err();
+    assert!(matches!(
+        err.inner.downcast_ref::<RootEscapeError>(),
+        Some(RootEscapeError::OutsideRoot(_))
+    ));
+    let alias = root.relative("alias/data.txt");
+    assert_eq!(&*alias.load_bytes(&tracker).unwrap(), b"data");
+
+    // Writes through escaping symlinks are also rejected
+    assert!(root
+        .relative("escape.txt")
+        .write_bytes(b"overwritten", &Default::default())
+        .is_err());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("secret.txt")).unwrap(),
+        "secret"
+    );
+
+    // Symlinks can be denied entirely
+    let root = AssetPath::new_root_fs_with(
+        &dir.join("root"),
+        &FsRootOptions {
+            symlinks: SymlinkPolicy::Deny,
+            ..Default::default()
+        },
+    );
+    let err = root
+        .relative("alias/data.txt")
+        .load_bytes(&tracker)
+        .unwrap_err();
+    assert!(matches!(
+        err.inner.downcast_ref::<RootEscapeError>(),
+        Some(RootEscapeError::Symlink)
+    ));
+    assert!(root.relative("alias").get_children(&tracker).is_err());
+
+    // ...or allowed entirely
+    let root = AssetPath::new_root_fs_with(
+        &dir.join("root"),
+        &FsRootOptions {
+            symlinks: SymlinkPolicy::Follow,
+            ..Default::default()
+        },
+    );
+    let secret = root.relative("escape.txt");
+    assert_eq!(&*secret.load_bytes(&tracker).unwrap(), b"secret");
+}
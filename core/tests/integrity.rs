@@ -0,0 +1,72 @@
+#![cfg(feature = "integrity")]
+use assetman::{AssetPath, FsRootOptions, IntegrityError, IntegrityManifest, SigningKey, Tracker};
+use assetman_test_util::TempDir;
+
+#[test]
+fn test_integrity() {
+    let dir = TempDir::new("integrity");
+    std::fs::create_dir_all(dir.join("data/sub")).unwrap();
+    std::fs::write(dir.join("data/a.txt"), "a").unwrap();
+    std::fs::write(dir.join("data/sub/b.txt"), "b").unwrap();
+    let fs_root = AssetPath::new_root_fs(&dir);
+    let data = fs_root.relative("data");
+
+    // Manifests can be saved and loaded with a signature
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let public_key = assetman::format_verifying_key(&key.verifying_key());
+    assert_eq!(public_key.len(), 64);
+    assert_eq!(
+        assetman::parse_verifying_key(&public_key),
+        Some(key.verifying_key())
+    );
+    let manifest = IntegrityManifest::generate(&data).unwrap();
+    assert_eq!(
+        manifest.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+        ["a.txt", "sub/b.txt"]
+    );
+    let manifest_asset = fs_root.relative("manifest.txt");
+    manifest.save(&manifest_asset, Some(&key)).unwrap();
+    let loaded = IntegrityManifest::load(&manifest_asset, Some(&key.verifying_key())).unwrap();
+    assert_eq!(loaded, manifest);
+    let other_key = SigningKey::from_bytes(&[8; 32]);
+    let err =
+        IntegrityManifest::load(&manifest_asset, Some(&other_key.verifying_key())).unwrap_err();
+    assert!(matches!(
+        err.inner.downcast_ref::<IntegrityError>(),
+        Some(IntegrityError::InvalidSignature)
+    ));
+
+    // Files are checked against the manifest when loaded
+    let root = AssetPath::new_root_verified(&data, loaded);
+    let tracker = Tracker::default();
+    assert_eq!(
+        &*root.relative("sub/b.txt").load_bytes(&tracker).unwrap(),
+        b"b"
+    );
+    let mut children = root.get_children(&tracker).unwrap();
+    children.sort();
+    assert_eq!(children, ["a.txt", "sub"]);
+    std::fs::write(dir.join("data/a.txt"), "tampered").unwrap();
+    std::fs::write(dir.join("data/c.txt"), "c").unwrap();
+    let err = root.relative("a.txt").open(&tracker).err().unwrap();
+    assert!(matches!(
+        err.inner.downcast_ref::<IntegrityError>(),
+        Some(IntegrityError::Mismatch { .. })
+    ));
+    let err = root.relative("c.txt").load_bytes(&tracker).unwrap_err();
+    assert!(matches!(
+        err.inner.downcast_ref::<IntegrityError>(),
+        Some(IntegrityError::Unlisted)
+    ));
+
+    // Paths are canonicalized by the inner root before they are checked
+    let options = FsRootOptions {
+        case_insensitive: true,
+        ..Default::default()
+    };
+    let data = AssetPath::new_root_fs_with(&dir, &options).relative("data");
+    let root = AssetPath::new_root_verified(&data, manifest);
+    let b = root.relative("SUB/B.txt");
+    assert_eq!(b, root.relative("sub/b.txt"));
+    assert_eq!(&*b.load_bytes(&tracker).unwrap(), b"b");
+}
@@ -0,0 +1,48 @@
+use assetman::{AssetPath, EmbeddedDir, Tracker};
+use std::io::{Read, Seek, SeekFrom};
+
+#[test]
+fn test_open_fs() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(env!("CARGO_MANIFEST_DIR")));
+    let tracker = Tracker::default();
+    let asset = root.relative("Cargo.toml");
+    let bytes = asset.load_bytes(&tracker).unwrap();
+    let mut reader = asset.open(&tracker).unwrap();
+    assert_eq!(reader.size_hint(), Some(bytes.len() as u64));
+    let mut header = [0u8; 9];
+    reader.read_exact(&mut header).unwrap();
+    assert_eq!(&header, b"[package]");
+    reader.seek(SeekFrom::End(-4)).unwrap();
+    let mut tail = Vec::new();
+    reader.read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, bytes[bytes.len() - 4..]);
+}
+
+#[test]
+fn test_open_embedded() {
+    static DIR: EmbeddedDir = EmbeddedDir::new("", &[("data.bin", b"0123456789")]);
+    let tracker = Tracker::default();
+    let mut reader = DIR.root().relative("data.bin").open(&tracker).unwrap();
+    assert_eq!(reader.size_hint(), Some(10));
+    reader.seek(SeekFrom::Start(6)).unwrap();
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "6789");
+    assert!(DIR.root().relative("missing.bin").open(&tracker).is_err());
+}
+
+#[test]
+fn test_load_range() {
+    static DIR: EmbeddedDir = EmbeddedDir::new("", &[("data.bin", b"0123456789")]);
+    let tracker = Tracker::default();
+    let asset = DIR.root().relative("data.bin");
+    assert_eq!(&*asset.load_range(&tracker, 3, 4).unwrap(), b"3456");
+    assert_eq!(&*asset.load_range(&tracker, 10, 0).unwrap(), b"");
+    assert!(asset.load_range(&tracker, 8, 4).is_err());
+
+    let root = AssetPath::new_root_fs(std::path::Path::new(env!("CARGO_MANIFEST_DIR")));
+    let asset = root.relative("Cargo.toml");
+    let bytes = asset.load_bytes(&tracker).unwrap();
+    assert_eq!(*asset.load_range(&tracker, 1, 7).unwrap(), bytes[1..8]);
+    assert!(asset.load_range(&tracker, bytes.len() as u64, 1).is_err());
+}
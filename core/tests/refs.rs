@@ -0,0 +1,25 @@
+use assetman::{AssetPath, ReferenceGraph};
+
+#[test]
+fn test_reference_graph() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(env!("CARGO_MANIFEST_DIR")));
+    let [a, b, c, d, e] =
+        ["a.json", "b.json", "c.json", "d.json", "e.json"].map(|s| root.relative(s));
+    let graph = ReferenceGraph::build([a.clone(), b.clone(), c.clone(), d.clone()], |asset| {
+        Ok(if *asset == a {
+            vec![b.clone(), e.clone()]
+        } else if *asset == b {
+            vec![c.clone()]
+        } else if *asset == c {
+            vec![b.clone()]
+        } else {
+            vec![]
+        })
+    });
+    assert_eq!(graph.missing(), vec![(a.clone(), e.clone())]);
+    assert_eq!(graph.orphans(&[a]), vec![d]);
+    let cycles = graph.cycles();
+    assert_eq!(cycles.len(), 1);
+    assert_eq!(cycles[0].len(), 2);
+    assert!(cycles[0].contains(&b) && cycles[0].contains(&c));
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "stats")]
+use assetman::stats::LoadScope;
+use assetman::{AssetPath, Tracker};
+
+#[test]
+fn test_load_stats() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(env!("CARGO_MANIFEST_DIR")));
+    let tracker = Tracker::default();
+    let manifest = root.relative("Cargo.toml");
+    let len = {
+        let scope = LoadScope::new(&manifest, "test");
+        scope.record_cache_hit(false);
+        manifest.load_bytes(&tracker).unwrap().len()
+    };
+    let report = assetman::stats::report();
+    let records = report.for_asset(&manifest).collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].kind, "test");
+    assert_eq!(records[0].cache_hit, Some(false));
+    assert_eq!(records[1].kind, "bytes");
+    assert_eq!(records[1].bytes_read, len as u64);
+    let outer = report
+        .records()
+        .iter()
+        .position(|r| r.kind == "test")
+        .unwrap();
+    assert_eq!(report.children(outer).count(), 1);
+
+    // Nested loads of the same asset aren't counted twice
+    let slowest = report.slowest();
+    let (_, total) = slowest
+        .iter()
+        .find(|(asset, _)| *asset == manifest.to_string())
+        .unwrap();
+    assert_eq!(*total, records[0].duration);
+    let mut trace = Vec::new();
+    report.write_chrome_trace(&mut trace).unwrap();
+    let trace = String::from_utf8(trace).unwrap();
+    assert!(trace.starts_with('[') && trace.contains("\"cat\":\"bytes\""));
+}
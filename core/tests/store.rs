@@ -0,0 +1,74 @@
+use assetman::{AssetCache, ContentStore, Handle, Tracker};
+use assetman_test_util::TempDir;
+
+#[test]
+fn test_content_store() {
+    let dir = TempDir::new("store");
+    let store = ContentStore::open(&dir).unwrap();
+    let a = store.import("textures/a.png", b"pixels").unwrap();
+    let b = store.import("models/b.png", b"pixels").unwrap();
+    store.import("models/c.txt", b"other").unwrap();
+
+    // Identical contents are only stored once
+    assert_eq!(a, b);
+    let num_blobs = std::fs::read_dir(dir.join("blobs"))
+        .unwrap()
+        .map(|entry| std::fs::read_dir(entry.unwrap().path()).unwrap().count())
+        .sum::<usize>();
+    assert_eq!(num_blobs, 2);
+
+    // Assets are accessible through the normal API
+    let root = store.root();
+    let tracker = Tracker::default();
+    assert_eq!(root.get_children(&tracker).unwrap(), ["models", "textures"]);
+    assert_eq!(
+        root.relative("models").get_children(&tracker).unwrap(),
+        ["b.png", "c.txt"]
+    );
+    assert!(root.relative("models").is_dir(&tracker));
+    assert!(!root.relative("models/b.png").is_dir(&tracker));
+    let [a, b] = ["textures/a.png", "models/b.png"].map(|s| root.relative(s));
+    assert_eq!(&*b.load_bytes(&tracker).unwrap(), b"pixels");
+    assert_eq!(a.content_hash(&tracker), b.content_hash(&tracker));
+
+    // The cache shares values between paths with the same contents
+    let cache = AssetCache::new(usize::MAX);
+    let a_value = cache
+        .get_or_load(&a, &tracker, |tracker| a.load_bytes(tracker))
+        .unwrap();
+    let b_value = cache
+        .get_or_load(&b, &tracker, |_| panic!("should be cached"))
+        .unwrap();
+    assert!(Handle::ptr_eq(&a_value, &b_value));
+
+    // ...but not between paths with different extensions, which may be loaded differently
+    let d = root.relative("models/d.txt");
+    store.import("models/d.txt", b"pixels").unwrap();
+    let d_value = cache
+        .get_or_load(&d, &tracker, |tracker| d.load_bytes(tracker))
+        .unwrap();
+    assert!(!Handle::ptr_eq(&a_value, &d_value));
+
+    // ...or when loading by path, since the load may depend on the path
+    let b_path_value = cache
+        .get_or_load_by_path(&b, &tracker, |tracker| b.load_bytes(tracker))
+        .unwrap();
+    assert!(!Handle::ptr_eq(&a_value, &b_path_value));
+
+    // Replacing an asset notifies trackers
+    let a_tracker = Tracker::default();
+    a.load_bytes(&a_tracker).unwrap();
+    store.import("textures/a.png", b"new pixels").unwrap();
+    assert!(!a_tracker.get().is_valid());
+
+    // The index persists
+    let store = ContentStore::open(&dir).unwrap();
+    let root = store.root();
+    assert_eq!(
+        &*root
+            .relative("textures/a.png")
+            .load_bytes(&tracker)
+            .unwrap(),
+        b"new pixels"
+    );
+}
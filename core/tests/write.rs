@@ -0,0 +1,87 @@
+use assetman::{AssetPath, Tracker, WriteOptions};
+use assetman_test_util::TempDir;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_write_bytes() {
+    let dir = TempDir::new("write");
+    let root = AssetPath::new_root_fs(&dir);
+    let asset = root.relative("level/data.txt");
+
+    // Writing creates the file and its directory, and notifies trackers of the listing
+    let list_tracker = Tracker::default();
+    root.get_children(&list_tracker).unwrap();
+    asset
+        .write_bytes(b"first", &WriteOptions::default())
+        .unwrap();
+    assert!(!list_tracker.get().is_valid());
+    let tracker = Tracker::default();
+    assert_eq!(&*asset.load_bytes(&tracker).unwrap(), b"first");
+    assert_eq!(
+        root.relative("level").get_children(&tracker).unwrap(),
+        ["data.txt"]
+    );
+
+    // Trackers are notified of writes
+    asset
+        .write_bytes(b"second", &WriteOptions::default())
+        .unwrap();
+    assert!(!tracker.get().is_valid());
+
+    // ...unless invalidation is suppressed
+    sync_watcher(&dir, &root);
+    let tracker = Tracker::default();
+    assert_eq!(&*asset.load_bytes(&tracker).unwrap(), b"second");
+    let options = WriteOptions {
+        suppress_invalidation: true,
+    };
+    asset.write_bytes(b"third", &options).unwrap();
+    sync_watcher(&dir, &root);
+    assert!(tracker.get().is_valid());
+    assert_eq!(&*asset.load_bytes(&tracker).unwrap(), b"third");
+
+    // Later changes by other programs are still detected
+    std::fs::write(dir.join("level/data.txt"), b"fourth").unwrap();
+    sync_watcher(&dir, &root);
+    assert!(!tracker.get().is_valid());
+}
+
+/// Waits until the watcher for the file system root at the given directory has handled the
+/// events for all changes made so far, by making a change of its own and waiting for a tracker to
+/// be notified of it.
+fn sync_watcher(dir: &std::path::Path, root: &AssetPath) {
+    let tracker = Tracker::default();
+    let _ = root.relative("sync.txt").load_bytes(&tracker);
+    std::fs::write(dir.join("sync.txt"), b"sync").unwrap();
+    let start = Instant::now();
+    while tracker.get().is_valid() {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "watcher missed a change"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn test_modified() {
+    let dir = TempDir::new("modified");
+    let root = AssetPath::new_root_fs(&dir);
+    let source = root.relative("level.json");
+    let cooked = source.with_extension("bin");
+    assert_eq!(cooked, root.relative("level.bin"));
+    assert_eq!(
+        root.relative("a.b/data").with_extension("bin"),
+        root.relative("a.b/data.bin")
+    );
+
+    // Missing files have no modification time, and trackers are notified when they are created
+    let tracker = Tracker::default();
+    assert_eq!(cooked.modified(&tracker), None);
+    source.write_bytes(b"{}", &WriteOptions::default()).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    cooked.write_bytes(b"", &WriteOptions::default()).unwrap();
+    assert!(!tracker.get().is_valid());
+    let tracker = Tracker::default();
+    assert!(cooked.modified(&tracker).unwrap() >= source.modified(&tracker).unwrap());
+}
@@ -0,0 +1,112 @@
+//! Provides the `embed_dir!` macro, re-exported by `assetman` when its `embed` feature is enabled.
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use std::path::{Path, PathBuf};
+
+/// Embeds the contents of a directory into the binary, producing an `assetman::EmbeddedDir`.
+///
+/// The path is given as a string literal, relative to the directory containing the manifest of
+/// the crate being compiled. The result can be used to initialize a `static` or `const`:
+///
+/// ```ignore
+/// static ASSETS: assetman::EmbeddedDir = assetman::embed_dir!("assets");
+/// ```
+///
+/// Changes to existing files cause a rebuild, but files added to the directory are only picked up
+/// when the crate is next rebuilt for some other reason.
+#[proc_macro]
+pub fn embed_dir(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output,
+        Err(message) => format!("::core::compile_error!({:?})", message)
+            .parse()
+            .unwrap(),
+    }
+}
+
+/// Expands an invocation of `embed_dir!`.
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let rel_path = parse_str_literal(input)?;
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| "CARGO_MANIFEST_DIR is not set".to_owned())?;
+    let dir = Path::new(&manifest_dir).join(rel_path);
+    let mut files = Vec::new();
+    collect_files(&dir, "", &mut files)
+        .map_err(|err| format!("failed to read {}: {}", dir.display(), err))?;
+    files.sort();
+    let mut entries = String::new();
+    for (name, path) in files {
+        let path = path
+            .to_str()
+            .ok_or_else(|| format!("path is not valid UTF-8: {}", path.display()))?;
+        entries.push_str(&format!(
+            "({:?}, ::core::include_bytes!({:?}) as &[u8]),",
+            name, path
+        ));
+    }
+    let dir = dir
+        .to_str()
+        .ok_or_else(|| format!("path is not valid UTF-8: {}", dir.display()))?;
+    let output = format!("::assetman::EmbeddedDir::new({:?}, &[{}])", dir, entries);
+    Ok(output.parse().unwrap())
+}
+
+/// Parses the input to `embed_dir!`, which should be a single string literal without escapes.
+fn parse_str_literal(input: TokenStream) -> Result<String, String> {
+    let mut tokens = input.into_iter().collect::<Vec<_>>();
+
+    // Literals passed through `macro_rules!` fragments are wrapped in an invisible group
+    while let [TokenTree::Group(group)] = tokens.as_slice() {
+        if group.delimiter() != Delimiter::None {
+            break;
+        }
+        tokens = group.stream().into_iter().collect();
+    }
+    let [TokenTree::Literal(lit)] = tokens.as_slice() else {
+        return Err("expected a string literal".to_owned());
+    };
+    let lit = lit.to_string();
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = &raw[hashes..raw.len() - hashes];
+        if let Some(inner) = inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            return Ok(inner.to_owned());
+        }
+    } else if let Some(inner) = lit.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        if inner.contains('\\') {
+            return Err("escapes are not supported in embed_dir! paths".to_owned());
+        }
+        return Ok(inner.to_owned());
+    }
+    Err("expected a string literal".to_owned())
+}
+
+/// Recursively collects the files in the given directory, along with their `/`-separated paths
+/// relative to the embedded root.
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut Vec<(String, PathBuf)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_str().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("file name is not valid UTF-8: {:?}", name),
+            )
+        })?;
+        let rel_name = if prefix.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, &rel_name, files)?;
+        } else {
+            files.push((rel_name, path));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use assetman::{EmbeddedDir, Tracker};
+
+static ASSETS: EmbeddedDir = assetman_embed::embed_dir!("tests/assets");
+
+#[test]
+fn test_embed_dir() {
+    let root = ASSETS.root();
+    let tracker = Tracker::default();
+    assert_eq!(root.get_children(&tracker).unwrap(), ["a.txt", "sub"]);
+    let sub = root.relative("sub");
+    assert_eq!(sub.get_children(&tracker).unwrap().len(), 2);
+    assert_eq!(
+        &*root.relative("sub/b.txt").load_bytes(&tracker).unwrap(),
+        b"world"
+    );
+    assert!(root.relative("missing.txt").load_bytes(&tracker).is_err());
+    assert!(tracker.get().is_valid());
+}
+
+#[test]
+fn test_embed_dir_dev() {
+    let tracker = Tracker::default();
+    let root = ASSETS.root_dev(true);
+    assert_eq!(
+        &*root.relative("a.txt").load_bytes(&tracker).unwrap(),
+        b"hello"
+    );
+}
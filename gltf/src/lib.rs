@@ -1,87 +1,194 @@
-use assetman::{AssetLoadError, AssetLoadResult, AssetPath, Tracker};
+use assetman::{AssetCache, AssetLoadError, AssetLoadResult, AssetPath, Handle, Tracker};
 use assetman_image::{AssetPathImageExt, DynamicImage};
 use assetman_json::AssetPathJsonExt;
-use serdere::{Deserialize, Utf8Reader};
+use serdere::Deserialize;
 use serdere_json::{JsonDeserializer, ValueExt};
 use std::cell::OnceCell;
-use std::io::{BufReader, Read};
+use std::io::{Read, Seek};
+use std::sync::Arc;
 
 /// Contains GLTF-loading extensions for [`AssetPath`].
 pub trait AssetPathGltfExt {
     /// Loads a GLTF or GLB file.
     fn load_gltf<'a>(&self, tracker: &'a Tracker) -> AssetLoadResult<Gltf<'a>>;
+
+    /// Loads a GLTF or GLB file, as with [`AssetPathGltfExt::load_gltf`], but loads external
+    /// buffers through the given [`AssetCache`]. This allows them to be shared between loads, and
+    /// their size counts towards the cache's budget. Such buffers are always loaded in full.
+    fn load_gltf_cached<'a>(
+        &self,
+        cache: &AssetCache,
+        tracker: &'a Tracker,
+    ) -> AssetLoadResult<Gltf<'a>>;
+
+    /// Loads the [`GltfInfo`] for a GLTF or GLB file, without loading any buffers or images.
+    fn load_gltf_info(&self, tracker: &Tracker) -> AssetLoadResult<GltfInfo>;
+
+    /// Enumerates the external buffers and images referenced by a GLTF or GLB file.
+    ///
+    /// Only the JSON content of the file is loaded. Embedded `data:` URIs are not included.
+    fn scan_gltf_references(&self, tracker: &Tracker) -> AssetLoadResult<Vec<AssetPath>>;
 }
 
 impl AssetPathGltfExt for AssetPath {
     fn load_gltf<'a>(&self, tracker: &'a Tracker) -> AssetLoadResult<Gltf<'a>> {
-        match self.extension() {
-            None | Some("gltf") => self.load_json_with(tracker, |value| {
-                let info: GltfInfo = value.get()?;
+        load_gltf(self, None, tracker)
+    }
+
+    fn load_gltf_cached<'a>(
+        &self,
+        cache: &AssetCache,
+        tracker: &'a Tracker,
+    ) -> AssetLoadResult<Gltf<'a>> {
+        load_gltf(self, Some(cache), tracker)
+    }
+
+    fn load_gltf_info(&self, tracker: &Tracker) -> AssetLoadResult<GltfInfo> {
+        let _scope = assetman::stats::LoadScope::new(self, "gltf-info");
+        match self.extension().as_deref() {
+            None | Some("gltf") => self.load_json_with(tracker, |value| value.get()),
+            Some("glb") => {
+                let mut reader = self.open(tracker)?;
+                assetman::with_asset(self, || read_glb_info(&mut reader))
+            }
+            _ => Err(AssetLoadError {
+                asset: self.clone(),
+                inner: UnsupportedExtensionError.into(),
+            }),
+        }
+    }
+
+    fn scan_gltf_references(&self, tracker: &Tracker) -> AssetLoadResult<Vec<AssetPath>> {
+        let info = self.load_gltf_info(tracker)?;
+        let dir = self.parent().unwrap();
+        let buffer_uris = info.buffers.iter().filter_map(|buffer| buffer.uri.as_ref());
+        let image_uris = info.images.iter().filter_map(|image| image.uri.as_ref());
+        Ok(buffer_uris
+            .chain(image_uris)
+            .filter(|uri| !uri.starts_with("data:"))
+            .map(|uri| resolve_uri(&dir, uri))
+            .collect())
+    }
+}
+
+/// Loads a GLTF or GLB file, loading external buffers through the given cache, if any.
+fn load_gltf<'a>(
+    asset: &AssetPath,
+    cache: Option<&AssetCache>,
+    tracker: &'a Tracker,
+) -> AssetLoadResult<Gltf<'a>> {
+    let _scope = assetman::stats::LoadScope::new(asset, "gltf");
+    match asset.extension().as_deref() {
+        None | Some("gltf") => asset.load_json_with(tracker, |value| {
+            let info: GltfInfo = value.get()?;
+            let num_buffers = info.buffers.len();
+            let num_buffer_views = info.buffer_views.len();
+            Ok(Gltf {
+                tracker,
+                cache: cache.cloned(),
+                asset: asset.clone(),
+                dir: asset.parent().unwrap(),
+                info,
+                bin_chunk: None,
+                buffer_cache: (0..num_buffers).map(|_| OnceCell::new()).collect(),
+                buffer_view_cache: (0..num_buffer_views).map(|_| OnceCell::new()).collect(),
+            })
+        }),
+        Some("glb") => {
+            let mut reader = asset.open(tracker)?;
+            assetman::with_asset(asset, || {
+                let info = read_glb_info(&mut reader)?;
                 let num_buffers = info.buffers.len();
-                Ok(Gltf {
+                let num_buffer_views = info.buffer_views.len();
+                let mut res = Gltf {
                     tracker,
-                    dir: self.parent().unwrap(),
+                    cache: cache.cloned(),
+                    asset: asset.clone(),
+                    dir: asset.parent().unwrap(),
                     info,
+                    bin_chunk: None,
                     buffer_cache: (0..num_buffers).map(|_| OnceCell::new()).collect(),
-                })
-            }),
-            Some("glb") => {
-                let mut file = self.open_file(tracker)?;
-                assetman::with_asset(self, || {
-                    let mut header = [0u8; 12];
-                    let Ok(()) = file.read_exact(&mut header) else {
-                        return Err(MalformedGlbError.into());
-                    };
-                    if u32::from_le_bytes(header[0..4].try_into().unwrap()) != 0x46546c67 {
-                        return Err(MalformedGlbError.into());
-                    }
-                    let mut chunk_header = [0u8; 8];
-                    let Ok(()) = file.read_exact(&mut chunk_header) else {
-                        return Err(MalformedGlbError.into());
-                    };
-                    if u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) != 0x4e4f534a {
+                    buffer_view_cache: (0..num_buffer_views).map(|_| OnceCell::new()).collect(),
+                };
+
+                // The binary chunk is only needed if it provides the first buffer. Only its location
+                // is read here, so that buffer views can be read from it on demand.
+                if num_buffers == 0 || res.info.buffers[0].uri.is_some() {
+                    return Ok(res);
+                }
+                let mut chunk_header = [0u8; 8];
+                if let Ok(()) = reader.read_exact(&mut chunk_header) {
+                    if u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) != 0x004e4942 {
                         return Err(MalformedGlbError.into());
                     }
                     let chunk_len = u32::from_le_bytes(chunk_header[0..4].try_into().unwrap());
-                    let mut take_file = file.take(chunk_len as u64);
-                    let json_reader =
-                        Utf8Reader::new(BufReader::<&mut dyn Read>::new(&mut take_file))?;
-                    let info: GltfInfo = serdere::Value::with(
-                        &mut serdere_json::TextDeserializer::new(
-                            serdere_json::TextDeserializerConfig::strict(),
-                            json_reader,
-                        )?,
-                        |value| value.get(),
-                    )?;
-                    let num_buffers = info.buffers.len();
-                    let res = Gltf {
-                        tracker,
-                        dir: self.parent().unwrap(),
-                        info,
-                        buffer_cache: (0..num_buffers).map(|_| OnceCell::new()).collect(),
-                    };
-                    let mut file = take_file.into_inner();
-                    if let Ok(()) = file.read_exact(&mut chunk_header) {
-                        if u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) != 0x004e4942
-                        {
-                            return Err(MalformedGlbError.into());
-                        }
-                        let chunk_len = u32::from_le_bytes(chunk_header[0..4].try_into().unwrap());
-                        let mut chunk_data = vec![0u8; chunk_len as usize].into_boxed_slice();
-                        file.read_exact(&mut chunk_data)?;
-                        if num_buffers > 0 && res.info.buffers[0].uri.is_none() {
-                            res.buffer_cache[0].set(chunk_data).unwrap();
-                        }
-                    }
-                    Ok(res)
-                })
+                    res.bin_chunk = Some((reader.stream_position()?, chunk_len as usize));
+                }
+                Ok(res)
+            })
+        }
+        _ => Err(AssetLoadError {
+            asset: asset.clone(),
+            inner: UnsupportedExtensionError.into(),
+        }),
+    }
+}
+
+/// Gets the asset referred to by a relative URI in a GLTF file in the given directory. URIs are
+/// percent-encoded, so `textures/wood%20grain.png` refers to `textures/wood grain.png`.
+fn resolve_uri(dir: &AssetPath, uri: &str) -> AssetPath {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut rest = uri.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        match escaped {
+            Some(escaped) if byte == b'%' => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
             }
-            _ => Err(AssetLoadError {
-                asset: self.clone(),
-                inner: UnsupportedExtensionError.into(),
-            }),
         }
     }
+    dir.relative(&String::from_utf8_lossy(&bytes))
+}
+
+/// Reads the header and JSON chunk of a GLB file, leaving `reader` positioned at the start of the
+/// next chunk.
+fn read_glb_info(reader: &mut impl Read) -> Result<GltfInfo, assetman::AssetLoadInnerError> {
+    let mut header = [0u8; 12];
+    let Ok(()) = reader.read_exact(&mut header) else {
+        return Err(MalformedGlbError.into());
+    };
+    if u32::from_le_bytes(header[0..4].try_into().unwrap()) != 0x46546c67 {
+        return Err(MalformedGlbError.into());
+    }
+    let mut chunk_header = [0u8; 8];
+    let Ok(()) = reader.read_exact(&mut chunk_header) else {
+        return Err(MalformedGlbError.into());
+    };
+    if u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) != 0x4e4f534a {
+        return Err(MalformedGlbError.into());
+    }
+    let chunk_len = u32::from_le_bytes(chunk_header[0..4].try_into().unwrap());
+    let mut chunk_data = Vec::new();
+    reader
+        .by_ref()
+        .take(chunk_len as u64)
+        .read_to_end(&mut chunk_data)?;
+    if chunk_data.len() != chunk_len as usize {
+        return Err(MalformedGlbError.into());
+    }
+    Ok(assetman_json::deserialize_json(
+        &chunk_data,
+        serdere_json::TextDeserializerConfig::strict(),
+        |value| value.get(),
+    )?)
 }
 
 /// The type of error produced when there is an attempt to load a GLTF content from an asset with
@@ -95,6 +202,32 @@ pub struct UnsupportedExtensionError;
 #[error("malformed GLB file")]
 pub struct MalformedGlbError;
 
+/// The type of error produced when a buffer in a GLTF file has no data, because it has no URI and
+/// isn't provided by the binary chunk of a GLB file.
+#[derive(Debug, thiserror::Error)]
+#[error("buffer has no URI or binary chunk")]
+pub struct MissingBufferError;
+
+/// The type of error produced when data for a [`Gltf`] fails to load. Since the same error is
+/// reported each time the data is requested, the original error is shared, and can be accessed
+/// using [`SharedLoadError::get`].
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(transparent)]
+pub struct SharedLoadError(Arc<dyn std::error::Error + Send + Sync>);
+
+impl SharedLoadError {
+    /// Gets the original error, e.g. for downcasting.
+    pub fn get(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        &*self.0
+    }
+}
+
+/// The type of error produced when a buffer view in a GLTF file refers to a range outside of its
+/// buffer.
+#[derive(Debug, thiserror::Error)]
+#[error("buffer view is out of range of its buffer")]
+pub struct BufferViewRangeError;
+
 /// Describes the contents of a GLTF file.
 #[derive(Debug, Deserialize, Clone)]
 pub struct GltfInfo {
@@ -554,9 +687,59 @@ pub struct ImageInfo {
 /// demand.
 pub struct Gltf<'a> {
     tracker: &'a Tracker,
+
+    /// The cache that external buffers are loaded through, if any.
+    cache: Option<AssetCache>,
+
+    /// The GLTF or GLB file itself.
+    asset: AssetPath,
     dir: AssetPath,
     info: GltfInfo,
-    buffer_cache: Box<[OnceCell<Box<[u8]>>]>,
+
+    /// The offset and length of the binary chunk of a GLB file, which provides the data for the
+    /// first buffer if it has no URI.
+    bin_chunk: Option<(u64, usize)>,
+    buffer_cache: Box<[OnceCell<LoadedData<BufferData>>]>,
+
+    /// The data for buffer views whose buffer was not loaded in full.
+    buffer_view_cache: Box<[OnceCell<LoadedData<Box<[u8]>>>]>,
+}
+
+/// The result of loading data for a [`Gltf`] on demand. Failures are kept, so that they are
+/// reported again when the data is requested again.
+type LoadedData<T> = Result<T, (AssetPath, SharedLoadError)>;
+
+/// Gets the data in the given cell, loading it using the given function if this hasn't been
+/// attempted yet.
+fn load_once<T>(
+    cell: &OnceCell<LoadedData<T>>,
+    load: impl FnOnce() -> AssetLoadResult<T>,
+) -> AssetLoadResult<&T> {
+    let res =
+        cell.get_or_init(|| load().map_err(|err| (err.asset, SharedLoadError(err.inner.into()))));
+    res.as_ref().map_err(|(asset, err)| AssetLoadError {
+        asset: asset.clone(),
+        inner: err.clone().into(),
+    })
+}
+
+/// The data for a buffer in a [`Gltf`].
+enum BufferData {
+    Owned(Box<[u8]>),
+
+    /// Data loaded through an [`AssetCache`]. The [`Handle`] prevents it from being evicted while
+    /// the [`Gltf`] exists.
+    Cached(Arc<Box<[u8]>>, Handle<Box<[u8]>>),
+}
+
+impl BufferData {
+    /// Gets the bytes for this buffer.
+    fn bytes(&self) -> &[u8] {
+        match self {
+            BufferData::Owned(data) => data,
+            BufferData::Cached(data, _) => data,
+        }
+    }
 }
 
 impl Gltf<'_> {
@@ -565,6 +748,12 @@ impl Gltf<'_> {
         &self.info
     }
 
+    /// Gets the asset referred to by a URI in this file, such as the `uri` of a buffer or image.
+    /// The URI is percent-decoded and resolved relative to the directory containing the file.
+    pub fn resolve_uri(&self, uri: &str) -> AssetPath {
+        resolve_uri(&self.dir, uri)
+    }
+
     /// Gets the default scene to display.
     pub fn scene(&self) -> Option<Scene> {
         let id = self.info.scene?;
@@ -606,34 +795,88 @@ impl Gltf<'_> {
         }
     }
 
+    /// Gets the asset which contains the data for the given buffer, along with the offset and
+    /// length of the data within it. The length is [`None`] if the data is the entire asset.
+    fn buffer_location(&self, id: BufferId) -> AssetLoadResult<(AssetPath, u64, Option<usize>)> {
+        match (&self.info.buffers[id as usize].uri, self.bin_chunk) {
+            (Some(uri), _) => Ok((self.resolve_uri(uri), 0, None)),
+            (None, Some((offset, len))) if id == 0 => Ok((self.asset.clone(), offset, Some(len))),
+            (None, _) => Err(AssetLoadError {
+                asset: self.asset.clone(),
+                inner: MissingBufferError.into(),
+            }),
+        }
+    }
+
     /// Gets the data for the given buffer.
     pub fn buffer(&self, id: BufferId) -> AssetLoadResult<&[u8]> {
-        let cache = &self.buffer_cache[id as usize];
-        let mut err = None;
-        let res = cache.get_or_init(|| {
-            let buffer_info = &self.info.buffers[id as usize];
-            let uri = buffer_info.uri.as_ref().expect("buffer has no URI");
-            match self.dir.relative(uri).load_bytes(self.tracker) {
-                Ok(data) => data,
-                Err(e) => {
-                    err = Some(e);
-                    Box::new([])
-                }
+        let data = load_once(&self.buffer_cache[id as usize], || {
+            let (path, offset, len) = self.buffer_location(id)?;
+            let _scope = assetman::stats::LoadScope::new(&path, "gltf-buffer");
+            match (len, &self.cache) {
+                (Some(len), _) => path
+                    .load_range(self.tracker, offset, len)
+                    .map(BufferData::Owned),
+                (None, Some(cache)) => cache
+                    .get_or_load(&path, self.tracker, |tracker| path.load_bytes(tracker))
+                    .map(|handle| BufferData::Cached(handle.get(), handle)),
+                (None, None) => path.load_bytes(self.tracker).map(BufferData::Owned),
             }
-        });
-        match err {
-            Some(e) => Err(e),
-            None => Ok(res.as_ref()),
-        }
+        })?;
+        Ok(data.bytes())
     }
 
     /// Gets the data and stride for the given buffer view.
+    ///
+    /// If the buffer hasn't already been loaded, and is stored uncompressed in an external file or
+    /// in the binary chunk of a GLB file, only the range referenced by the view is read. External
+    /// buffers of files loaded using [`AssetPathGltfExt::load_gltf_cached`] are always loaded in
+    /// full.
     pub fn buffer_view(&self, id: BufferViewId) -> AssetLoadResult<(&[u8], Option<usize>)> {
         let buffer_view = &self.info.buffer_views[id as usize];
-        let buffer_data = self.buffer(buffer_view.buffer)?;
-        let buffer_data = &buffer_data[buffer_view.byte_offset as usize..];
-        let buffer_data = &buffer_data[..buffer_view.byte_length as usize];
-        Ok((buffer_data, buffer_view.byte_stride.map(|s| s as usize)))
+        let stride = buffer_view.byte_stride.map(|s| s as usize);
+        let location = match self.buffer_cache[buffer_view.buffer as usize].get() {
+            None => Some(self.buffer_location(buffer_view.buffer)?)
+                .filter(|(_, _, len)| len.is_some() || self.cache.is_none()),
+            Some(_) => None,
+        };
+
+        // Compressed buffers can't be read partially, so they are loaded in full instead
+        let (path, offset, len) = match location {
+            Some(location) if location.0.compression().is_none() => location,
+            _ => {
+                let buffer_data = self.buffer(buffer_view.buffer)?;
+                let range = usize::try_from(buffer_view.byte_offset)
+                    .ok()
+                    .zip(usize::try_from(buffer_view.byte_length).ok())
+                    .and_then(|(start, len)| buffer_data.get(start..start.checked_add(len)?));
+                return match range {
+                    Some(buffer_data) => Ok((buffer_data, stride)),
+                    None => Err(AssetLoadError {
+                        asset: self.asset.clone(),
+                        inner: BufferViewRangeError.into(),
+                    }),
+                };
+            }
+        };
+        let data = load_once(&self.buffer_view_cache[id as usize], || {
+            let _scope = assetman::stats::LoadScope::new(&path, "gltf-buffer-view");
+            let end = buffer_view
+                .byte_offset
+                .saturating_add(buffer_view.byte_length);
+            if len.is_some_and(|len| end > len as u64) {
+                return Err(AssetLoadError {
+                    asset: self.asset.clone(),
+                    inner: BufferViewRangeError.into(),
+                });
+            }
+            path.load_range(
+                self.tracker,
+                offset + buffer_view.byte_offset,
+                buffer_view.byte_length as usize,
+            )
+        })?;
+        Ok((data, stride))
     }
 
     /// Iterates over the nodes in this GLTF file that have the given name.
@@ -1005,8 +1248,7 @@ impl Image<'_> {
             todo!()
         } else {
             self.gltf
-                .dir
-                .relative(self.info.uri.as_ref().unwrap())
+                .resolve_uri(self.info.uri.as_ref().unwrap())
                 .size_image(self.gltf.tracker)
         }
     }
@@ -1017,8 +1259,7 @@ impl Image<'_> {
             todo!()
         } else {
             self.gltf
-                .dir
-                .relative(self.info.uri.as_ref().unwrap())
+                .resolve_uri(self.info.uri.as_ref().unwrap())
                 .load_image(self.gltf.tracker)
         }
     }
@@ -1028,7 +1269,7 @@ impl Image<'_> {
         if let Some(buffer_view) = self.info.buffer_view {
             todo!()
         } else {
-            ImageSource::Asset(self.gltf.dir.relative(self.info.uri.as_ref().unwrap()))
+            ImageSource::Asset(self.gltf.resolve_uri(self.info.uri.as_ref().unwrap()))
         }
     }
 }
@@ -1,5 +1,5 @@
-use assetman::{AssetPath, Tracker};
-use assetman_gltf::AssetPathGltfExt;
+use assetman::{AssetCache, AssetPath, Tracker};
+use assetman_gltf::{AssetPathGltfExt, BufferViewRangeError, SharedLoadError};
 
 #[test]
 fn test_load_box() {
@@ -25,6 +25,83 @@ fn test_load_box() {
     }
 }
 
+#[test]
+fn test_load_cached() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let cache = AssetCache::new(usize::MAX);
+    let bin_len = root.relative("box.bin").load_bytes(&tracker).unwrap().len();
+
+    // Buffers count towards the cache budget, and are shared between loads
+    let first = root
+        .relative("box.gltf")
+        .load_gltf_cached(&cache, &tracker)
+        .unwrap();
+    assert_eq!(first.buffer(0).unwrap().len(), bin_len);
+    assert_eq!(cache.size(), bin_len);
+    let second = root
+        .relative("box.gltf")
+        .load_gltf_cached(&cache, &tracker)
+        .unwrap();
+    let view = second.buffer_view(0).unwrap().0;
+    assert!(first
+        .buffer(0)
+        .unwrap()
+        .as_ptr_range()
+        .contains(&view.as_ptr()));
+    assert_eq!(cache.size(), bin_len);
+}
+
+#[test]
+fn test_buffer_views() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let bin = root.relative("box.bin").load_bytes(&tracker).unwrap();
+
+    // Views of an external buffer are read as ranges, and match views of the embedded GLB buffer
+    for bx in ["box.gltf", "box.glb"].map(|s| root.relative(s).load_gltf(&tracker)) {
+        let gltf = bx.unwrap();
+        assert_eq!(gltf.buffer_view(0).unwrap(), (&bin[576..648], None));
+        assert_eq!(gltf.buffer_view(1).unwrap(), (&bin[..576], Some(12)));
+        assert_eq!(gltf.buffer(0).unwrap(), &*bin);
+    }
+
+    // Views outside of their buffer are reported as errors
+    let bad_view = root.relative("bad_view.gltf");
+    assert!(bad_view
+        .load_gltf(&tracker)
+        .unwrap()
+        .buffer_view(0)
+        .is_err());
+    let cache = AssetCache::new(usize::MAX);
+    let err = bad_view
+        .load_gltf_cached(&cache, &tracker)
+        .unwrap()
+        .buffer_view(0)
+        .unwrap_err();
+    assert_eq!(err.asset, bad_view);
+    assert!(err.inner.is::<BufferViewRangeError>());
+
+    // Failures are reported every time the data is requested
+    let gltf = root.relative("escaped.gltf").load_gltf(&tracker).unwrap();
+    for _ in 0..2 {
+        assert_eq!(
+            gltf.buffer_view(0).unwrap_err().asset,
+            root.relative("mesh data.bin")
+        );
+        let err = gltf.buffer(0).unwrap_err();
+        assert_eq!(err.asset, root.relative("mesh data.bin"));
+        let err = err.inner.downcast_ref::<SharedLoadError>().unwrap();
+        assert!(err.get().is::<std::io::Error>());
+    }
+}
+
 #[test]
 fn test_load_basket() {
     let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
@@ -40,3 +117,38 @@ fn test_load_basket() {
         .camera()
         .unwrap();
 }
+
+#[test]
+fn test_scan_references() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    assert_eq!(
+        root.relative("box.gltf")
+            .scan_gltf_references(&tracker)
+            .unwrap(),
+        vec![root.relative("box.bin")]
+    );
+    assert_eq!(
+        root.relative("box.glb")
+            .scan_gltf_references(&tracker)
+            .unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        root.relative("basket.gltf")
+            .scan_gltf_references(&tracker)
+            .unwrap(),
+        ["test.bin", "test_albedo.jpg", "test_orm.jpg"].map(|s| root.relative(s))
+    );
+
+    // URIs are percent-decoded
+    assert_eq!(
+        root.relative("escaped.gltf")
+            .scan_gltf_references(&tracker)
+            .unwrap(),
+        ["mesh data.bin", "textures/wood#grain.png"].map(|s| root.relative(s))
+    );
+}
@@ -1,5 +1,8 @@
-use assetman::{AssetLoadResult, AssetPath, Tracker};
-use std::io::BufReader;
+use assetman::{
+    AssetCache, AssetLoadResult, AssetPath, AssetSaveError, AssetSaveResult, Handle, Tracker,
+    WriteOptions,
+};
+use std::io::{BufReader, Cursor};
 
 pub use image::*;
 
@@ -8,27 +11,51 @@ pub trait AssetPathImageExt {
     /// Loads an image.
     fn load_image(&self, tracker: &Tracker) -> AssetLoadResult<DynamicImage>;
 
+    /// Loads an image, or gets it from the given [`AssetCache`] if it has already been loaded.
+    fn load_image_cached(
+        &self,
+        cache: &AssetCache,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<Handle<DynamicImage>>;
+
     /// Gets the size of an image at the given path.
     fn size_image(&self, tracker: &Tracker) -> AssetLoadResult<[u32; 2]>;
+
+    /// Saves an image, using the format implied by the asset's extension. See
+    /// [`AssetPath::write_bytes`].
+    fn save_image(&self, image: &DynamicImage, options: &WriteOptions) -> AssetSaveResult<()>;
 }
 
 impl AssetPathImageExt for AssetPath {
     fn load_image(&self, tracker: &Tracker) -> AssetLoadResult<DynamicImage> {
-        let file = self.open_file(tracker)?;
-        let reader = BufReader::new(file);
+        let _scope = assetman::stats::LoadScope::new(self, "image");
+        let reader = BufReader::new(self.open(tracker)?);
         assetman::with_asset(self, || {
             Ok(load(
                 reader,
-                image_format_from_extension(self.extension())?,
+                image_format_from_extension(self.extension().as_deref())?,
             )?)
         })
     }
 
+    fn load_image_cached(
+        &self,
+        cache: &AssetCache,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<Handle<DynamicImage>> {
+        cache.get_or_load_with_size(
+            self,
+            tracker,
+            |tracker| self.load_image(tracker),
+            image_cache_size,
+        )
+    }
+
     fn size_image(&self, tracker: &Tracker) -> AssetLoadResult<[u32; 2]> {
-        let file = self.open_file(tracker)?;
-        let reader = BufReader::new(file);
+        let _scope = assetman::stats::LoadScope::new(self, "image-size");
+        let reader = BufReader::new(self.open(tracker)?);
         assetman::with_asset(self, || {
-            let format = image_format_from_extension(self.extension())?;
+            let format = image_format_from_extension(self.extension().as_deref())?;
             let (width, height) = match format {
                 ImageFormat::Png => codecs::png::PngDecoder::new(reader)?.dimensions(),
                 _ => todo!(),
@@ -36,6 +63,23 @@ impl AssetPathImageExt for AssetPath {
             Ok([width, height])
         })
     }
+
+    fn save_image(&self, image: &DynamicImage, options: &WriteOptions) -> AssetSaveResult<()> {
+        let mut data = Cursor::new(Vec::new());
+        image_format_from_extension(self.extension().as_deref())
+            .and_then(|format| image.write_to(&mut data, format))
+            .map_err(|err| AssetSaveError {
+                asset: self.clone(),
+                inner: err.into(),
+            })?;
+        self.write_bytes(data.get_ref(), options)
+    }
+}
+
+/// Gets the approximate memory cost of an image, in bytes, for the purposes of an
+/// [`AssetCache`] budget.
+pub fn image_cache_size(image: &DynamicImage) -> usize {
+    image.width() as usize * image.height() as usize * image.color().bytes_per_pixel() as usize
 }
 
 /// Gets the [`ImageFormat`] for the given file extension, or returns an error if the format
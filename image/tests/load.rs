@@ -1,5 +1,6 @@
-use assetman::{AssetPath, Tracker};
+use assetman::{AssetCache, AssetPath, Tracker};
 use assetman_image::AssetPathImageExt;
+use assetman_test_util::TempDir;
 use image::GenericImageView;
 
 #[test]
@@ -16,3 +17,32 @@ fn test_load_ferris() {
     assert_eq!(ferris.height(), 200);
     assert_eq!(ferris.get_pixel(150, 100), image::Rgba([247, 76, 0, 255]));
 }
+
+#[test]
+fn test_load_ferris_cached() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let cache = AssetCache::new(usize::MAX);
+    let ferris = root.relative("ferris.png");
+    let first = ferris.load_image_cached(&cache, &tracker).unwrap();
+    let second = ferris.load_image_cached(&cache, &tracker).unwrap();
+    assert!(assetman::Handle::ptr_eq(&first, &second));
+    assert_eq!(first.get().width(), 300);
+    assert_eq!(cache.size(), 300 * 200 * 4);
+}
+
+#[test]
+fn test_save_image() {
+    let dir = TempDir::new("save-image");
+    let root = AssetPath::new_root_fs(&dir);
+    let tracker = Tracker::default();
+    let image = image::DynamicImage::new_rgba8(4, 3);
+    let asset = root.relative("out/image.png");
+    asset
+        .save_image(&image, &assetman::WriteOptions::default())
+        .unwrap();
+    assert_eq!(asset.size_image(&tracker).unwrap(), [4, 3]);
+}
@@ -0,0 +1,419 @@
+//! Provides `#[derive(JsonSchema)]` and `#[derive(ToJson)]`, re-exported by `assetman-json`
+//! alongside the traits they implement.
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Derives `assetman_json::JsonSchema` for a struct with named fields, a newtype struct, or an
+/// enum with only unit variants.
+///
+/// Doc comments on the type and its fields become schema descriptions. The `rename` and `default`
+/// options of `#[serde(...)]` attributes are respected, matching the deserializer. Enums whose
+/// variants all have explicit discriminants are described by their discriminant values, and other
+/// enums by their variant names.
+#[proc_macro_derive(JsonSchema, attributes(serde))]
+pub fn derive_json_schema(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output,
+        Err(message) => format!("::core::compile_error!({:?});", message)
+            .parse()
+            .unwrap(),
+    }
+}
+
+/// Derives `assetman_json::ToJson` for the same types as `#[derive(JsonSchema)]`, producing the
+/// JSON format those types are deserialized from.
+#[proc_macro_derive(ToJson, attributes(serde))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    match expand_to_json(input) {
+        Ok(output) => output,
+        Err(message) => format!("::core::compile_error!({:?});", message)
+            .parse()
+            .unwrap(),
+    }
+}
+
+/// Expands an invocation of `#[derive(JsonSchema)]`.
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let mut tokens = input.into_iter().peekable();
+    let attrs = parse_attrs(&mut tokens);
+    skip_visibility(&mut tokens);
+    let kind = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a struct or enum".to_owned()),
+    };
+    let Some(TokenTree::Ident(name)) = tokens.next() else {
+        return Err("expected a type name".to_owned());
+    };
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) => group,
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("JsonSchema can't be derived for generic types".to_owned())
+        }
+        _ => return Err("JsonSchema can't be derived for unit structs".to_owned()),
+    };
+    let description = display_option(attrs.description.as_deref());
+    let schema = match (kind.as_str(), body.delimiter()) {
+        ("struct", Delimiter::Brace) => {
+            let mut properties = String::new();
+            for field in parse_fields(body.stream())? {
+                properties.push_str(&format!(
+                    "::assetman_json::SchemaProperty::new::<{}>({:?}, {}, {}),",
+                    field.ty,
+                    field.attrs.rename.unwrap_or(field.name),
+                    display_option(field.attrs.description.as_deref()),
+                    field.attrs.default
+                ));
+            }
+            format!(
+                "::assetman_json::object_schema({}, ::std::vec![{}])",
+                description, properties
+            )
+        }
+        ("struct", Delimiter::Parenthesis) => {
+            let mut tokens = body.stream().into_iter().peekable();
+            parse_attrs(&mut tokens);
+            skip_visibility(&mut tokens);
+            let ty = parse_type(&mut tokens);
+            if ty.is_empty() || tokens.peek().is_some() {
+                return Err(
+                    "JsonSchema can only be derived for tuple structs with one field".into(),
+                );
+            }
+            format!("<{} as ::assetman_json::JsonSchema>::json_schema()", ty)
+        }
+        ("enum", Delimiter::Brace) => {
+            let variants = parse_variants(body.stream())?;
+            let mut values = String::new();
+            if variants
+                .iter()
+                .all(|variant| variant.discriminant.is_some())
+            {
+                for variant in variants {
+                    values.push_str(&format!(
+                        "::assetman_json::JsonValue::from(({}) as i64),",
+                        variant.discriminant.unwrap()
+                    ));
+                }
+            } else {
+                for variant in variants {
+                    values.push_str(&format!(
+                        "::assetman_json::JsonValue::String({:?}.to_owned()),",
+                        variant.attrs.rename.unwrap_or(variant.name)
+                    ));
+                }
+            }
+            format!(
+                "::assetman_json::enum_schema({}, ::std::vec![{}])",
+                description, values
+            )
+        }
+        _ => return Err("expected a struct or enum".to_owned()),
+    };
+    let output = format!(
+        "impl ::assetman_json::JsonSchema for {} {{ \
+            fn json_schema() -> ::assetman_json::JsonValue {{ {} }} \
+        }}",
+        name, schema
+    );
+    Ok(output.parse().unwrap())
+}
+
+/// Expands an invocation of `#[derive(ToJson)]`.
+fn expand_to_json(input: TokenStream) -> Result<TokenStream, String> {
+    let mut tokens = input.into_iter().peekable();
+    parse_attrs(&mut tokens);
+    skip_visibility(&mut tokens);
+    let kind = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a struct or enum".to_owned()),
+    };
+    let Some(TokenTree::Ident(name)) = tokens.next() else {
+        return Err("expected a type name".to_owned());
+    };
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) => group,
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("ToJson can't be derived for generic types".to_owned())
+        }
+        _ => return Err("ToJson can't be derived for unit structs".to_owned()),
+    };
+    let value = match (kind.as_str(), body.delimiter()) {
+        ("struct", Delimiter::Brace) => {
+            let mut entries = String::new();
+            for field in parse_fields(body.stream())? {
+                entries.push_str(&format!(
+                    "(::std::string::String::from({:?}), \
+                        ::assetman_json::ToJson::to_json(&self.r#{})),",
+                    field.attrs.rename.unwrap_or_else(|| field.name.clone()),
+                    field.name
+                ));
+            }
+            format!(
+                "::assetman_json::JsonValue::Object(::std::vec![{}])",
+                entries
+            )
+        }
+        ("struct", Delimiter::Parenthesis) => {
+            let mut tokens = body.stream().into_iter().peekable();
+            parse_attrs(&mut tokens);
+            skip_visibility(&mut tokens);
+            if parse_type(&mut tokens).is_empty() || tokens.peek().is_some() {
+                return Err("ToJson can only be derived for tuple structs with one field".into());
+            }
+            "::assetman_json::ToJson::to_json(&self.0)".to_owned()
+        }
+        ("enum", Delimiter::Brace) => {
+            let variants = parse_variants(body.stream())?;
+            let numbered = variants
+                .iter()
+                .all(|variant| variant.discriminant.is_some());
+            let mut arms = String::new();
+            for variant in variants {
+                let value = match variant.discriminant {
+                    Some(discriminant) if numbered => {
+                        format!(
+                            "::assetman_json::ToJson::to_json(&(({}) as i64))",
+                            discriminant
+                        )
+                    }
+                    _ => format!(
+                        "::assetman_json::JsonValue::String(::std::string::String::from({:?}))",
+                        variant.attrs.rename.unwrap_or_else(|| variant.name.clone())
+                    ),
+                };
+                arms.push_str(&format!("Self::{} => {},", variant.name, value));
+            }
+            format!("match self {{ {} }}", arms)
+        }
+        _ => return Err("expected a struct or enum".to_owned()),
+    };
+    let output = format!(
+        "impl ::assetman_json::ToJson for {} {{ \
+            fn to_json(&self) -> ::assetman_json::JsonValue {{ {} }} \
+        }}",
+        name, value
+    );
+    Ok(output.parse().unwrap())
+}
+
+/// The information gathered from the attributes of an item.
+#[derive(Default)]
+struct Attrs {
+    /// The text of the doc comments for the item.
+    description: Option<String>,
+
+    /// The name given by a `#[serde(rename = "...")]` attribute.
+    rename: Option<String>,
+
+    /// Indicates whether the item has a `#[serde(default)]` attribute.
+    default: bool,
+}
+
+/// A named field of a struct.
+struct Field {
+    attrs: Attrs,
+    name: String,
+    ty: String,
+}
+
+/// A variant of an enum.
+struct Variant {
+    attrs: Attrs,
+    name: String,
+    discriminant: Option<String>,
+}
+
+/// Parses the outer attributes at the start of the given tokens.
+fn parse_attrs(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) -> Attrs {
+    let mut attrs = Attrs::default();
+    let mut doc_lines = Vec::new();
+    while matches!(tokens.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '#') {
+        tokens.next();
+        let Some(TokenTree::Group(group)) = tokens.next() else {
+            break;
+        };
+        let inner = group.stream().into_iter().collect::<Vec<_>>();
+        match inner.as_slice() {
+            [TokenTree::Ident(ident), TokenTree::Punct(eq), TokenTree::Literal(lit)]
+                if ident.to_string() == "doc" && eq.as_char() == '=' =>
+            {
+                if let Some(line) = parse_str_literal(&lit.to_string()) {
+                    doc_lines.push(line);
+                }
+            }
+            [TokenTree::Ident(ident), TokenTree::Group(args)] if ident.to_string() == "serde" => {
+                let args = args.stream().into_iter().collect::<Vec<_>>();
+                for arg in args.split(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == ',')) {
+                    match arg {
+                        [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(lit)]
+                            if key.to_string() == "rename" && eq.as_char() == '=' =>
+                        {
+                            attrs.rename = parse_str_literal(&lit.to_string());
+                        }
+                        [TokenTree::Ident(key), ..] if key.to_string() == "default" => {
+                            attrs.default = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Lines within a paragraph of a doc comment are joined, since they are only wrapped for width
+    let mut description = String::new();
+    for line in doc_lines.iter().map(|line| line.trim()) {
+        if line.is_empty() {
+            if !description.is_empty() && !description.ends_with("\n\n") {
+                description.push_str("\n\n");
+            }
+        } else {
+            if !description.is_empty() && !description.ends_with('\n') {
+                description.push(' ');
+            }
+            description.push_str(line);
+        }
+    }
+    let description = description.trim_end();
+    if !description.is_empty() {
+        attrs.description = Some(description.to_owned());
+    }
+    attrs
+}
+
+/// Skips a visibility qualifier, such as `pub` or `pub(crate)`, at the start of the given tokens.
+fn skip_visibility(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) {
+    if matches!(tokens.peek(), Some(TokenTree::Ident(ident)) if ident.to_string() == "pub") {
+        tokens.next();
+        if let Some(TokenTree::Group(group)) = tokens.peek() {
+            if group.delimiter() == Delimiter::Parenthesis {
+                tokens.next();
+            }
+        }
+    }
+}
+
+/// Parses the tokens of a type, up to the next top-level comma, which is consumed.
+fn parse_type(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) -> String {
+    let mut ty = TokenStream::new();
+    let mut depth = 0usize;
+    let mut prev_joint = false;
+    for token in tokens.by_ref() {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                ',' if depth == 0 => break,
+                '<' => depth += 1,
+                // The `>` of `->` doesn't close an angle bracket
+                '>' if !prev_joint => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            prev_joint = punct.spacing() == Spacing::Joint && punct.as_char() == '-';
+        } else {
+            prev_joint = false;
+        }
+        ty.extend([token]);
+    }
+    ty.to_string()
+}
+
+/// Parses the named fields in the body of a struct.
+fn parse_fields(body: TokenStream) -> Result<Vec<Field>, String> {
+    let mut tokens = body.into_iter().peekable();
+    let mut fields = Vec::new();
+    while tokens.peek().is_some() {
+        let attrs = parse_attrs(&mut tokens);
+        skip_visibility(&mut tokens);
+        let Some(TokenTree::Ident(name)) = tokens.next() else {
+            return Err("expected a field name".to_owned());
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {}
+            _ => return Err("expected `:` after field name".to_owned()),
+        }
+        let name = name.to_string();
+        fields.push(Field {
+            attrs,
+            name: name.strip_prefix("r#").unwrap_or(&name).to_owned(),
+            ty: parse_type(&mut tokens),
+        });
+    }
+    Ok(fields)
+}
+
+/// Parses the variants in the body of an enum.
+fn parse_variants(body: TokenStream) -> Result<Vec<Variant>, String> {
+    let mut tokens = body.into_iter().peekable();
+    let mut variants = Vec::new();
+    while tokens.peek().is_some() {
+        let attrs = parse_attrs(&mut tokens);
+        let Some(TokenTree::Ident(name)) = tokens.next() else {
+            return Err("expected a variant name".to_owned());
+        };
+        let discriminant = match tokens.next() {
+            None => None,
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => None,
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {
+                Some(parse_type(&mut tokens))
+            }
+            Some(_) => {
+                return Err("JsonSchema can only be derived for enums with unit variants".into())
+            }
+        };
+        variants.push(Variant {
+            attrs,
+            name: name.to_string(),
+            discriminant,
+        });
+    }
+    Ok(variants)
+}
+
+/// Parses the contents of a string literal, returning [`None`] if it isn't one.
+fn parse_str_literal(lit: &str) -> Option<String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = &raw[hashes..raw.len() - hashes];
+        return Some(inner.strip_prefix('"')?.strip_suffix('"')?.to_owned());
+    }
+    let inner = lit.strip_prefix('"')?.strip_suffix('"')?;
+    let mut res = String::new();
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            res.push(ch);
+            continue;
+        }
+        match chars.next()? {
+            'n' => res.push('\n'),
+            'r' => res.push('\r'),
+            't' => res.push('\t'),
+            '0' => res.push('\0'),
+            'x' => {
+                let code = chars.by_ref().take(2).collect::<String>();
+                res.push(char::from(u8::from_str_radix(&code, 16).ok()?));
+            }
+            'u' => {
+                let code = chars
+                    .by_ref()
+                    .skip(1)
+                    .take_while(|&ch| ch != '}')
+                    .collect::<String>();
+                res.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            '\n' => {
+                // A line continuation, which skips leading whitespace on the next line
+                let rest = chars.as_str().trim_start();
+                chars = rest.chars();
+            }
+            ch => res.push(ch),
+        }
+    }
+    Some(res)
+}
+
+/// Formats an optional string as a Rust expression of type `Option<&str>`.
+fn display_option(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("::core::option::Option::Some({:?})", value),
+        None => "::core::option::Option::None".to_owned(),
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{AssetPathJsonExt, JsonDeserializer, JsonDeserializerError};
+use crate::{JsonLoadOptions, JsonValue};
+use assetman::{AssetLoadInnerError, AssetLoadResult, AssetPath, Tracker};
+use serdere::{Deserialize, Value};
+use std::borrow::Cow;
+
+/// Contains extensions for [`AssetPath`] which load configuration file assets in whichever
+/// [`ConfigFormat`] their file extension indicates.
+///
+/// Every format is converted to a [`JsonValue`] before deserialization, so the same types can be
+/// loaded from a `.json`, `.json5`, `.jsonc`, `.toml` or `.ron` file.
+pub trait AssetPathConfigExt {
+    /// Loads a configuration file asset using a deserializer interface.
+    ///
+    /// JSON files are loaded as by [`AssetPathJsonExt::load_json_with`]. Files in other formats
+    /// are converted to a [`JsonValue`], which is deserialized directly, and `$include` and `$ref`
+    /// nodes are not resolved. Errors are still reported as a [`JsonError`](crate::JsonError):
+    /// its pointer identifies the offending value, and its line and column refer to the value
+    /// formatted as by [`AssetPathJsonExt::save_json`].
+    fn load_config_with<R>(
+        &self,
+        tracker: &Tracker,
+        f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+    ) -> AssetLoadResult<R>;
+
+    /// Loads a configuration file asset, deserializing it into a value of type `T`.
+    fn load_config<T: for<'a> Deserialize<JsonDeserializer<'a>>>(
+        &self,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<T> {
+        self.load_config_with(tracker, |value| value.get())
+    }
+
+    /// Loads a configuration file asset as a generic [`JsonValue`].
+    fn load_config_value(&self, tracker: &Tracker) -> AssetLoadResult<JsonValue>;
+}
+
+impl AssetPathConfigExt for AssetPath {
+    fn load_config_with<R>(
+        &self,
+        tracker: &Tracker,
+        f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+    ) -> AssetLoadResult<R> {
+        if ConfigFormat::of(self) == Some(ConfigFormat::Json) {
+            return self.load_json_with(tracker, f);
+        }
+        let value = self.load_config_value(tracker)?;
+        let config = crate::deserializer_config(&JsonLoadOptions::default_for(self));
+        assetman::with_asset(self, || {
+            Ok(crate::source::deserialize_value(&value, config, f)?)
+        })
+    }
+
+    fn load_config_value(&self, tracker: &Tracker) -> AssetLoadResult<JsonValue> {
+        let format = ConfigFormat::of(self);
+        if format == Some(ConfigFormat::Json) {
+            return self.load_json_value(tracker);
+        }
+        let Some(format) = format else {
+            return Err(assetman::AssetLoadError {
+                asset: self.clone(),
+                inner: UnsupportedConfigFormatError(self.extension().map(Cow::into_owned)).into(),
+            });
+        };
+        let _scope = assetman::stats::LoadScope::new(self, "config");
+        let bytes = self.load_bytes(tracker)?;
+        assetman::with_asset(self, || format.parse(std::str::from_utf8(&bytes)?))
+    }
+}
+
+/// A file format which configuration file assets can be loaded from using
+/// [`AssetPathConfigExt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    /// JSON, with the extension `.json`.
+    Json,
+
+    /// [JSON5](https://json5.org/), with the extension `.json5`, or JSON with comments, with the
+    /// extension `.jsonc`. `Infinity` and `NaN` become `null`. This requires the `json5` feature.
+    Json5,
+
+    /// [TOML](https://toml.io/), with the extension `.toml`. Dates and times become strings.
+    /// This requires the `toml` feature.
+    Toml,
+
+    /// [RON](https://github.com/ron-rs/ron), with the extension `.ron`. Structs and maps become
+    /// objects, tuples and tuple structs become arrays, `Some(x)` becomes `x`, and `None`, `()`
+    /// and unit structs and enum variants become `null`, so their names are lost. Map keys which
+    /// aren't strings are formatted as JSON. This requires the `ron` feature.
+    Ron,
+}
+
+impl ConfigFormat {
+    /// Gets the format of a file with the given extension, if it is supported.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "json" => Some(Self::Json),
+            "json5" | "jsonc" if cfg!(feature = "json5") => Some(Self::Json5),
+            "toml" if cfg!(feature = "toml") => Some(Self::Toml),
+            "ron" if cfg!(feature = "ron") => Some(Self::Ron),
+            _ => None,
+        }
+    }
+
+    /// Gets the format of the given file asset, based on its extension.
+    pub fn of(asset: &AssetPath) -> Option<Self> {
+        asset.extension().as_deref().and_then(Self::from_extension)
+    }
+
+    /// Parses a [`JsonValue`] from text in this format.
+    pub fn parse(self, text: &str) -> Result<JsonValue, AssetLoadInnerError> {
+        Ok(match self {
+            Self::Json => JsonValue::parse(text)?,
+            Self::Json5 => parse_json5(text)?,
+            Self::Toml => parse_toml(text)?,
+            Self::Ron => parse_ron(text)?,
+        })
+    }
+}
+
+/// Parses a [`JsonValue`] from TOML text.
+#[cfg(feature = "toml")]
+fn parse_toml(text: &str) -> Result<JsonValue, AssetLoadInnerError> {
+    /// Converts a TOML value into the equivalent [`JsonValue`].
+    fn convert(value: toml::Value) -> JsonValue {
+        match value {
+            toml::Value::String(value) => JsonValue::String(value),
+            toml::Value::Integer(value) => JsonValue::from(value),
+            toml::Value::Float(value) => JsonValue::from(value),
+            toml::Value::Boolean(value) => JsonValue::Bool(value),
+            toml::Value::Datetime(value) => JsonValue::String(value.to_string()),
+            toml::Value::Array(items) => JsonValue::Array(items.into_iter().map(convert).collect()),
+            toml::Value::Table(table) => convert_table(table),
+        }
+    }
+
+    /// Converts a TOML table into the equivalent [`JsonValue`] object.
+    fn convert_table(table: toml::Table) -> JsonValue {
+        JsonValue::Object(table.into_iter().map(|(k, v)| (k, convert(v))).collect())
+    }
+    Ok(convert_table(text.parse::<toml::Table>()?))
+}
+
+/// Parses a [`JsonValue`] from TOML text, which isn't supported without the `toml` feature.
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_: &str) -> Result<JsonValue, AssetLoadInnerError> {
+    Err(UnsupportedConfigFormatError(Some("toml".to_owned())).into())
+}
+
+/// Parses a [`JsonValue`] from JSON5 text.
+#[cfg(feature = "json5")]
+fn parse_json5(text: &str) -> Result<JsonValue, AssetLoadInnerError> {
+    Ok(json5::from_str::<SerdeValue>(text)?.0)
+}
+
+/// Parses a [`JsonValue`] from JSON5 text, which isn't supported without the `json5` feature.
+#[cfg(not(feature = "json5"))]
+fn parse_json5(_: &str) -> Result<JsonValue, AssetLoadInnerError> {
+    Err(UnsupportedConfigFormatError(Some("json5".to_owned())).into())
+}
+
+/// Parses a [`JsonValue`] from RON text.
+#[cfg(feature = "ron")]
+fn parse_ron(text: &str) -> Result<JsonValue, AssetLoadInnerError> {
+    Ok(ron::from_str::<SerdeValue>(text)?.0)
+}
+
+/// Parses a [`JsonValue`] from RON text, which isn't supported without the `ron` feature.
+#[cfg(not(feature = "ron"))]
+fn parse_ron(_: &str) -> Result<JsonValue, AssetLoadInnerError> {
+    Err(UnsupportedConfigFormatError(Some("ron".to_owned())).into())
+}
+
+/// A [`JsonValue`] which can be deserialized from any self-describing `serde` format.
+#[cfg(any(feature = "json5", feature = "ron"))]
+struct SerdeValue(JsonValue);
+
+#[cfg(any(feature = "json5", feature = "ron"))]
+impl<'de> serde::Deserialize<'de> for SerdeValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(SerdeValueVisitor)
+            .map(SerdeValue)
+    }
+}
+
+/// The visitor which converts a `serde` value into the equivalent [`JsonValue`].
+#[cfg(any(feature = "json5", feature = "ron"))]
+struct SerdeValueVisitor;
+
+#[cfg(any(feature = "json5", feature = "ron"))]
+impl<'de> serde::de::Visitor<'de> for SerdeValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_f32<E>(self, value: f32) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_char<E>(self, value: char) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value.to_string()))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<JsonValue, D::Error> {
+        d.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+        self,
+        d: D,
+    ) -> Result<JsonValue, D::Error> {
+        d.deserialize_any(self)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(SerdeValue(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut entries = Vec::new();
+        while let Some((SerdeValue(key), SerdeValue(value))) = map.next_entry()? {
+            let key = match key {
+                JsonValue::String(key) => key,
+                key => key.to_string(),
+            };
+            entries.push((key, value));
+        }
+        Ok(JsonValue::Object(entries))
+    }
+}
+
+/// The type of error produced when loading a configuration file asset whose extension doesn't
+/// correspond to a supported [`ConfigFormat`].
+#[derive(thiserror::Error, Debug)]
+#[error("unsupported config format: {}", display_extension(.0))]
+pub struct UnsupportedConfigFormatError(pub Option<String>);
+
+/// Formats the extension of a file with an unsupported format for display.
+fn display_extension(ext: &Option<String>) -> String {
+    match ext {
+        Some(ext) => format!(".{}", ext),
+        None => "no file extension".to_owned(),
+    }
+}
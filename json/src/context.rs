@@ -0,0 +1,170 @@
+use crate::{AssetPathJsonExt, JsonDeserializer};
+use assetman::{
+    AssetCache, AssetLoadError, AssetLoadResult, AssetPath, CacheSize, Handle, Tracker,
+};
+use serdere::{Deserialize, Outliner, Value};
+use std::cell::RefCell;
+
+/// A deserialization context for a JSON file asset which allows [`AssetPath`]s to be
+/// deserialized directly from string values, such as `"../img/wood.png"`. Paths are resolved
+/// relative to the directory containing the JSON file, or for values which were included from
+/// another file, the directory containing that file.
+///
+/// [`Handle`]s can be deserialized in the same way for any type which implements [`LoadHandle`],
+/// loading the asset that the path refers to through the context's [`AssetCache`]. Loads are
+/// tracked using the context's [`Tracker`]. When loaded using
+/// [`AssetPathJsonExt::load_json_relative`] or [`AssetPathJsonExt::load_json_relative_cached`],
+/// load failures are reported as the [`JsonError`](crate::JsonError) for the path.
+pub struct JsonAssetContext {
+    /// The directory that paths are resolved relative to.
+    pub dir: AssetPath,
+
+    /// The cache that [`Handle`]s are loaded through.
+    pub cache: AssetCache,
+
+    /// The tracker for the assets loaded through [`Handle`]s.
+    pub tracker: Tracker,
+
+    /// The failure to load a [`Handle`] which the most recent deserialization error was returned
+    /// for, if any.
+    failure: Option<AssetLoadError>,
+}
+
+impl JsonAssetContext {
+    /// Constructs a [`JsonAssetContext`] for deserializing the given JSON file asset. [`Handle`]s
+    /// are loaded through a new [`AssetCache`], which only holds values while they have handles.
+    pub fn new(asset: &AssetPath) -> Self {
+        Self::with_cache(asset, AssetCache::new(0))
+    }
+
+    /// Constructs a [`JsonAssetContext`] for deserializing the given JSON file asset, which loads
+    /// [`Handle`]s through the given [`AssetCache`].
+    pub fn with_cache(asset: &AssetPath, cache: AssetCache) -> Self {
+        Self {
+            dir: asset.parent().unwrap(),
+            cache,
+            tracker: Tracker::default(),
+            failure: None,
+        }
+    }
+
+    /// Loads a JSON file asset through the context's [`AssetCache`], deserializing it into a value
+    /// of type `T` using a [`JsonAssetContext`] for that asset. This can be used to implement
+    /// [`LoadHandle`] for types which are loaded from JSON.
+    ///
+    /// Since paths in the file are resolved relative to it, the value is cached by path (see
+    /// [`AssetCache::get_or_load_by_path`]).
+    pub fn load_json_handle<T>(&self, asset: &AssetPath) -> AssetLoadResult<Handle<T>>
+    where
+        T: for<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext>,
+        T: CacheSize + Send + Sync + 'static,
+    {
+        self.cache
+            .get_or_load_by_path(asset, &self.tracker, |tracker| {
+                asset.load_json_relative_cached(&self.cache, tracker)
+            })
+    }
+
+    /// Takes the failure which the most recent deserialization error was returned for, if any.
+    pub(crate) fn take_failure(&mut self) -> Option<AssetLoadError> {
+        self.failure.take()
+    }
+
+    /// Resolves a path relative to the file which the value being deserialized was read from.
+    fn resolve(&mut self, path: &str) -> AssetPath {
+        // Deserialization has continued, so any earlier error was recovered from
+        self.failure = None;
+        INCLUDE_DIR.with(|dir| match &*dir.borrow() {
+            Some(dir) => dir.relative(path),
+            None => self.dir.relative(path),
+        })
+    }
+}
+
+impl Clone for JsonAssetContext {
+    fn clone(&self) -> Self {
+        Self {
+            dir: self.dir.clone(),
+            cache: self.cache.clone(),
+            tracker: self.tracker.clone(),
+            failure: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for JsonAssetContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonAssetContext")
+            .field("dir", &self.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A type which [`Handle`]s can be deserialized for using a [`JsonAssetContext`], by loading the
+/// asset that the path refers to.
+///
+/// For types which are loaded from JSON, this can be implemented using
+/// [`JsonAssetContext::load_json_handle`].
+pub trait LoadHandle: Send + Sync + Sized + 'static {
+    /// Loads the given asset through the context's [`AssetCache`], tracking it using the
+    /// context's [`Tracker`].
+    fn load_handle(asset: &AssetPath, ctx: &JsonAssetContext) -> AssetLoadResult<Handle<Self>>;
+}
+
+#[cfg(feature = "image")]
+impl LoadHandle for assetman_image::DynamicImage {
+    fn load_handle(asset: &AssetPath, ctx: &JsonAssetContext) -> AssetLoadResult<Handle<Self>> {
+        use assetman_image::AssetPathImageExt;
+        asset.load_image_cached(&ctx.cache, &ctx.tracker)
+    }
+}
+
+impl<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext> for AssetPath {
+    const NULLABLE: bool = false;
+    fn deserialize(
+        value: Value<JsonDeserializer<'a>>,
+        ctx: &mut JsonAssetContext,
+    ) -> Result<Self, <JsonDeserializer<'a> as Outliner>::Error> {
+        let path: String = value.get()?;
+        Ok(ctx.resolve(&path))
+    }
+}
+
+impl<'a, T: LoadHandle> Deserialize<JsonDeserializer<'a>, JsonAssetContext> for Handle<T> {
+    const NULLABLE: bool = false;
+    fn deserialize(
+        value: Value<JsonDeserializer<'a>>,
+        ctx: &mut JsonAssetContext,
+    ) -> Result<Self, <JsonDeserializer<'a> as Outliner>::Error> {
+        let asset: AssetPath = value.get_using(ctx)?;
+        T::load_handle(&asset, ctx).map_err(|failure| {
+            // The deserializer can't carry the failure, so it is kept in the context to be
+            // reported in place of the error
+            ctx.failure = Some(failure);
+            crate::source::abort_error()
+        })
+    }
+}
+
+thread_local! {
+    /// The directory containing the included file that the deserializer on this thread last read
+    /// from, or [`None`] if it last read from the file being loaded.
+    static INCLUDE_DIR: RefCell<Option<AssetPath>> = const { RefCell::new(None) };
+}
+
+/// Starts a deserialization on this thread, returning the include directory of the
+/// deserialization it is nested in, to be restored by [`exit`].
+pub(crate) fn enter() -> Option<AssetPath> {
+    INCLUDE_DIR.with(|dir| dir.replace(None))
+}
+
+/// Ends a deserialization on this thread, restoring the include directory returned by [`enter`].
+pub(crate) fn exit(outer: Option<AssetPath>) {
+    INCLUDE_DIR.with(|dir| *dir.borrow_mut() = outer);
+}
+
+/// Sets the directory that paths in the text the deserializer is reading are relative to, or
+/// [`None`] for the directory given by the [`JsonAssetContext`].
+pub(crate) fn set_include_dir(dir: Option<AssetPath>) {
+    INCLUDE_DIR.with(|include_dir| *include_dir.borrow_mut() = dir);
+}
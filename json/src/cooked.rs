@@ -0,0 +1,793 @@
+use crate::error::error_offset;
+use crate::value::write_string;
+use crate::{JsonError, JsonNumber, JsonValue};
+use assetman::{AssetLoadInnerError, AssetLoadResult, AssetPath, Tracker};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// The bytes at the start of every cooked JSON file.
+const MAGIC: &[u8; 4] = b"AMJB";
+
+/// The version of the cooked JSON format produced by [`encode_cooked_json`].
+const VERSION: u8 = 2;
+
+/// The tags which identify the kind of each value in cooked JSON.
+mod tag {
+    pub const NULL: u8 = 0;
+    pub const FALSE: u8 = 1;
+    pub const TRUE: u8 = 2;
+    pub const INT: u8 = 3;
+    pub const NUMBER: u8 = 4;
+    pub const STRING: u8 = 5;
+    pub const ARRAY: u8 = 6;
+    pub const OBJECT: u8 = 7;
+    pub const INCLUDE: u8 = 8;
+}
+
+/// Gets the path of the cooked sibling of the given JSON file asset, e.g. `level.bin` for
+/// `level.json`.
+pub fn cooked_json_path(asset: &AssetPath) -> AssetPath {
+    asset.with_extension("bin")
+}
+
+/// Encodes a [`JsonValue`] in the compact binary "cooked" format, which can be decoded using
+/// [`decode_cooked_json`] much faster than JSON text can be parsed.
+///
+/// Object keys are stored once in a table at the start of the file and referred to by index,
+/// integers are stored as variable-length integers, and other numbers are stored as written.
+pub fn encode_cooked_json(value: &JsonValue) -> Vec<u8> {
+    encode_cooked(value, &[], &HashMap::new())
+}
+
+/// Encodes a [`JsonValue`] in the cooked format, along with the paths of the files it includes,
+/// relative to the directory containing the JSON file. `includes` gives the index path (the
+/// index of each array item or object entry on the way) of each value which was included from a
+/// different file than the value containing it, along with the index of that file in `deps`,
+/// plus one, or `0` for the JSON file itself.
+pub(crate) fn encode_cooked(
+    value: &JsonValue,
+    deps: &[&str],
+    includes: &HashMap<Vec<usize>, usize>,
+) -> Vec<u8> {
+    let mut writer = Writer {
+        out: MAGIC.to_vec(),
+        keys: HashMap::new(),
+        includes,
+        path: Vec::new(),
+    };
+    writer.out.push(VERSION);
+    write_varint(&mut writer.out, deps.len() as u64);
+    for dep in deps {
+        write_str(&mut writer.out, dep);
+    }
+    let mut key_table = Vec::new();
+    collect_keys(value, &mut writer.keys, &mut key_table);
+    write_varint(&mut writer.out, key_table.len() as u64);
+    for key in key_table {
+        write_str(&mut writer.out, key);
+    }
+    writer.write_value(value);
+    writer.out
+}
+
+/// Adds the object keys in the given value to the key table, in the order they first appear.
+fn collect_keys<'a>(
+    value: &'a JsonValue,
+    keys: &mut HashMap<&'a str, u64>,
+    key_table: &mut Vec<&'a str>,
+) {
+    match value {
+        JsonValue::Array(items) => {
+            for item in items {
+                collect_keys(item, keys, key_table);
+            }
+        }
+        JsonValue::Object(entries) => {
+            for (key, value) in entries {
+                keys.entry(key.as_str()).or_insert_with(|| {
+                    key_table.push(key);
+                    key_table.len() as u64 - 1
+                });
+                collect_keys(value, keys, key_table);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Writes values in the cooked format.
+struct Writer<'a> {
+    out: Vec<u8>,
+
+    /// The indices of the object keys in the key table.
+    keys: HashMap<&'a str, u64>,
+
+    /// The values which were included from other files. See [`encode_cooked`].
+    includes: &'a HashMap<Vec<usize>, usize>,
+
+    /// The index path of the value being written.
+    path: Vec<usize>,
+}
+
+impl Writer<'_> {
+    /// Writes a value in the cooked format.
+    fn write_value(&mut self, value: &JsonValue) {
+        let out = &mut self.out;
+        if let Some(&file) = self.includes.get(&self.path) {
+            out.push(tag::INCLUDE);
+            write_varint(out, file as u64);
+        }
+        match value {
+            JsonValue::Null => out.push(tag::NULL),
+            JsonValue::Bool(false) => out.push(tag::FALSE),
+            JsonValue::Bool(true) => out.push(tag::TRUE),
+            JsonValue::Number(value) => match value.as_i64() {
+                Some(value) => {
+                    // Zigzag encoding keeps small negative integers small
+                    out.push(tag::INT);
+                    write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+                }
+                None => {
+                    out.push(tag::NUMBER);
+                    write_str(out, value.as_str());
+                }
+            },
+            JsonValue::String(value) => {
+                out.push(tag::STRING);
+                write_str(out, value);
+            }
+            JsonValue::Array(items) => {
+                out.push(tag::ARRAY);
+                write_varint(out, items.len() as u64);
+                for (i, item) in items.iter().enumerate() {
+                    self.path.push(i);
+                    self.write_value(item);
+                    self.path.pop();
+                }
+            }
+            JsonValue::Object(entries) => {
+                out.push(tag::OBJECT);
+                write_varint(out, entries.len() as u64);
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write_varint(&mut self.out, self.keys[key.as_str()]);
+                    self.path.push(i);
+                    self.write_value(value);
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Writes an unsigned LEB128 variable-length integer.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Writes a length-prefixed string.
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Decodes a [`JsonValue`] encoded by [`encode_cooked_json`].
+pub fn decode_cooked_json(bytes: &[u8]) -> Result<JsonValue, CookedJsonError> {
+    let (mut reader, _) = Reader::new(bytes)?;
+    let value = reader.read_value(0)?;
+    reader.finish()?;
+    Ok(value)
+}
+
+/// The maximum nesting depth of arrays and objects accepted by [`decode_cooked_json`], which
+/// protects against stack overflows when decoding corrupt files.
+const MAX_DEPTH: usize = 1024;
+
+/// Reads values from cooked JSON.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    keys: Vec<&'a str>,
+
+    /// The number of files that values may be included from, including the JSON file itself.
+    num_files: usize,
+}
+
+/// A value, or the start of an array or object, read from cooked JSON.
+enum Token<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Number(&'a str),
+    String(&'a str),
+
+    /// The start of an array with the given number of items.
+    Array(usize),
+
+    /// The start of an object with the given number of entries, each of which is a key followed
+    /// by a value.
+    Object(usize),
+
+    /// The start of a value included from the file with the given index.
+    Include(usize),
+}
+
+impl<'a> Reader<'a> {
+    /// Constructs a reader for the given cooked JSON, positioned at its value, returning it along
+    /// with the paths of the files the value includes.
+    fn new(bytes: &'a [u8]) -> Result<(Self, Vec<&'a str>), CookedJsonError> {
+        let Some(rest) = bytes.strip_prefix(MAGIC) else {
+            return Err(CookedJsonError::NotCooked);
+        };
+        match rest.first() {
+            Some(&VERSION) => {}
+            Some(&version) => return Err(CookedJsonError::UnsupportedVersion(version)),
+            None => return Err(CookedJsonError::Truncated),
+        }
+        let mut reader = Reader {
+            bytes,
+            pos: MAGIC.len() + 1,
+            keys: Vec::new(),
+            num_files: 1,
+        };
+        let num_deps = reader.read_len()?;
+        let mut deps = Vec::with_capacity(num_deps);
+        for _ in 0..num_deps {
+            deps.push(reader.read_str()?);
+        }
+        reader.num_files += num_deps;
+        let num_keys = reader.read_len()?;
+        for _ in 0..num_keys {
+            let key = reader.read_str()?;
+            reader.keys.push(key);
+        }
+        Ok((reader, deps))
+    }
+
+    /// Checks that the whole of the cooked JSON has been read.
+    fn finish(&self) -> Result<(), CookedJsonError> {
+        if self.pos < self.bytes.len() {
+            return Err(CookedJsonError::Malformed(self.pos));
+        }
+        Ok(())
+    }
+
+    /// Reads a single byte.
+    fn read_byte(&mut self) -> Result<u8, CookedJsonError> {
+        let byte = *self.bytes.get(self.pos).ok_or(CookedJsonError::Truncated)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Reads the given number of bytes.
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], CookedJsonError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or(CookedJsonError::Truncated)?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(CookedJsonError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned LEB128 variable-length integer.
+    fn read_varint(&mut self) -> Result<u64, CookedJsonError> {
+        let start = self.pos;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CookedJsonError::Malformed(start))
+    }
+
+    /// Reads the length of a string, array or object, checking that it is plausible given the
+    /// number of remaining bytes, so that corrupt files don't cause huge allocations.
+    fn read_len(&mut self) -> Result<usize, CookedJsonError> {
+        let len = self.read_varint()?;
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err(CookedJsonError::Truncated);
+        }
+        Ok(len as usize)
+    }
+
+    /// Reads a length-prefixed string.
+    fn read_str(&mut self) -> Result<&'a str, CookedJsonError> {
+        let len = self.read_len()?;
+        let start = self.pos;
+        let bytes = self.read_bytes(len)?;
+        std::str::from_utf8(bytes).map_err(|_| CookedJsonError::Malformed(start))
+    }
+
+    /// Reads the key of an object entry.
+    fn read_key(&mut self) -> Result<&'a str, CookedJsonError> {
+        let start = self.pos;
+        let index = self.read_varint()?;
+        match self.keys.get(index as usize) {
+            Some(key) => Ok(key),
+            None => Err(CookedJsonError::Malformed(start)),
+        }
+    }
+
+    /// Reads the next token.
+    fn read_token(&mut self) -> Result<Token<'a>, CookedJsonError> {
+        let start = self.pos;
+        Ok(match self.read_byte()? {
+            tag::NULL => Token::Null,
+            tag::FALSE => Token::Bool(false),
+            tag::TRUE => Token::Bool(true),
+            tag::INT => {
+                let value = self.read_varint()?;
+                Token::Int((value >> 1) as i64 ^ -((value & 1) as i64))
+            }
+            tag::NUMBER => {
+                let text = self.read_str()?;
+                if JsonNumber::parse(text).is_none() {
+                    return Err(CookedJsonError::Malformed(start));
+                }
+                Token::Number(text)
+            }
+            tag::STRING => Token::String(self.read_str()?),
+            tag::ARRAY => Token::Array(self.read_len()?),
+            tag::OBJECT => Token::Object(self.read_len()?),
+            tag::INCLUDE => match self.read_varint()? {
+                file if file < self.num_files as u64 => Token::Include(file as usize),
+                _ => return Err(CookedJsonError::Malformed(start)),
+            },
+            _ => return Err(CookedJsonError::Malformed(start)),
+        })
+    }
+
+    /// Reads a value at the given nesting depth.
+    fn read_value(&mut self, depth: usize) -> Result<JsonValue, CookedJsonError> {
+        let start = self.pos;
+        Ok(match self.read_token()? {
+            Token::Null => JsonValue::Null,
+            Token::Bool(value) => JsonValue::Bool(value),
+            Token::Int(value) => JsonValue::from(value),
+            Token::Number(text) => JsonValue::Number(JsonNumber::parse(text).unwrap()),
+            Token::String(value) => JsonValue::String(value.to_owned()),
+            Token::Array(len) if depth < MAX_DEPTH => {
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(self.read_value(depth + 1)?);
+                }
+                JsonValue::Array(items)
+            }
+            Token::Object(len) if depth < MAX_DEPTH => {
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let key = self.read_key()?.to_owned();
+                    entries.push((key, self.read_value(depth + 1)?));
+                }
+                JsonValue::Object(entries)
+            }
+            Token::Include(_) if depth < MAX_DEPTH => self.read_value(depth + 1)?,
+            _ => return Err(CookedJsonError::Malformed(start)),
+        })
+    }
+}
+
+/// Determines whether the given asset is cooked JSON, in any version of the cooked format, by
+/// checking the bytes at its start. Only the start of the asset is loaded.
+pub fn is_cooked_json(asset: &AssetPath, tracker: &Tracker) -> bool {
+    asset
+        .load_range(tracker, 0, MAGIC.len())
+        .is_ok_and(|bytes| *bytes == *MAGIC)
+}
+
+/// Determines whether the given JSON file asset has an up-to-date cooked sibling (see
+/// [`cooked_json_path`]), which would be loaded in its place when
+/// [`JsonLoadOptions::use_cooked`](crate::JsonLoadOptions::use_cooked) is set: one in the current
+/// cooked format which was modified more recently than the asset and every file it includes.
+pub fn is_cooked_json_current(asset: &AssetPath, tracker: &Tracker) -> bool {
+    matches!(load_cooked(asset, tracker), Ok(Some(_)))
+}
+
+/// Loads the cooked sibling of the given JSON file asset (see [`cooked_json_path`]), if it
+/// exists and was modified more recently than the asset itself and every file it includes.
+/// Siblings which aren't cooked JSON, such as the buffer of a glTF file, or which are in another
+/// version of the cooked format, are ignored.
+///
+/// Only the header of the cooked data is checked here. The value is checked as it is read.
+///
+/// All of the files are tracked using the given [`Tracker`].
+pub(crate) fn load_cooked(
+    asset: &AssetPath,
+    tracker: &Tracker,
+) -> AssetLoadResult<Option<CookedJson>> {
+    let cooked = cooked_json_path(asset);
+    let Some(cooked_modified) = cooked.modified(tracker) else {
+        return Ok(None);
+    };
+    let is_current = |asset: &AssetPath| {
+        asset
+            .modified(tracker)
+            .is_some_and(|modified| modified < cooked_modified)
+    };
+    if !is_current(asset) || !is_cooked_json(&cooked, tracker) {
+        return Ok(None);
+    }
+    let _scope = assetman::stats::LoadScope::new(&cooked, "json-cooked");
+    let bytes = cooked.load_bytes(tracker)?;
+    let deps = assetman::with_asset(&cooked, || match Reader::new(&bytes) {
+        Ok((_, deps)) => Ok(Some(deps)),
+        Err(CookedJsonError::NotCooked | CookedJsonError::UnsupportedVersion(_)) => Ok(None),
+        Err(err) => Err(err.into()),
+    })?;
+    let Some(deps) = deps else {
+        return Ok(None);
+    };
+    let dir = asset.parent().unwrap();
+    let mut dirs = vec![None];
+    for dep in deps {
+        let dep = dir.relative(dep);
+        if !is_current(&dep) {
+            return Ok(None);
+        }
+        dirs.push(Some(dep.parent().unwrap()));
+    }
+    Ok(Some(CookedJson {
+        asset: cooked,
+        bytes,
+        dirs,
+    }))
+}
+
+/// The cooked sibling of a JSON file asset, which has been checked to be up to date by
+/// [`load_cooked`].
+pub(crate) struct CookedJson {
+    /// The cooked sibling itself.
+    asset: AssetPath,
+    bytes: Box<[u8]>,
+
+    /// The directory containing each file that values may be included from, or [`None`] for the
+    /// JSON file itself.
+    dirs: Vec<Option<AssetPath>>,
+}
+
+impl CookedJson {
+    /// Gets the path of the cooked sibling, which errors in the cooked data are attributed to.
+    pub(crate) fn asset(&self) -> &AssetPath {
+        &self.asset
+    }
+
+    /// Decodes the cooked value.
+    pub(crate) fn value(&self) -> Result<JsonValue, CookedJsonError> {
+        decode_cooked_json(&self.bytes)
+    }
+
+    /// Gets the text of the cooked value, generated as it is read. The progress of the text is
+    /// recorded in the given [`CookedProgress`].
+    pub(crate) fn text<'a>(&'a self, progress: &'a CookedProgress<'a>) -> CookedText<'a> {
+        let (reader, _) = Reader::new(&self.bytes).expect("cooked JSON header was checked");
+        CookedText {
+            reader,
+            file_dirs: &self.dirs,
+            dirs: vec![(0, None)],
+            chunk: String::new(),
+            pos: 0,
+            generated: 0,
+            stack: vec![Pending::Value(0, 0)],
+            progress,
+        }
+    }
+}
+
+/// Generates JSON text for the value in [`CookedJson`] a piece at a time, directly from the
+/// cooked tokens, so that it can be read by a [`JsonDeserializer`](crate::JsonDeserializer)
+/// without decoding the value or formatting it.
+///
+/// The text has no whitespace, other than a line break before each array item and object entry,
+/// so that errors can be located by line while only keeping the text of the current line. If the
+/// cooked data is malformed, reading fails with an [`std::io::Error`], and the
+/// [`CookedJsonError`] is recorded in the [`CookedProgress`].
+pub(crate) struct CookedText<'a> {
+    reader: Reader<'a>,
+
+    /// The directory containing each file that values may be included from. See [`CookedJson`].
+    file_dirs: &'a [Option<AssetPath>],
+
+    /// The offsets in the text generated so far where the file that values were included from
+    /// changes, along with the directory containing that file.
+    dirs: Vec<(usize, Option<AssetPath>)>,
+
+    /// The most recently generated piece of text.
+    chunk: String,
+
+    /// The number of bytes of `chunk` which have been consumed.
+    pos: usize,
+
+    /// The number of bytes of text generated before `chunk`.
+    generated: usize,
+
+    /// The parts of the value whose text has yet to be generated, innermost last.
+    stack: Vec<Pending>,
+    progress: &'a CookedProgress<'a>,
+}
+
+/// Part of the value in [`CookedJson`] whose text has yet to be generated by a [`CookedText`].
+/// Each has the nesting depth of the value, or of the items of the array or object, and the
+/// index of the file it was included from. Arrays and objects also have the number of items
+/// remaining and the index of the next item.
+enum Pending {
+    Value(usize, usize),
+    Items(usize, usize, usize, usize),
+    Entries(usize, usize, usize, usize),
+
+    /// The end of a value included from another file than the one containing it, which was
+    /// included from the given file.
+    Exit(usize),
+}
+
+impl CookedText<'_> {
+    /// Gets the offsets in the text where the file that values were included from changes, along
+    /// with the directory containing that file, as known from the text generated so far.
+    pub(crate) fn dirs(&self) -> &[(usize, Option<AssetPath>)] {
+        &self.dirs
+    }
+
+    /// Gets the text which hasn't been consumed yet, generating the next piece if all of the
+    /// current piece has been consumed. This is empty at the end of the text.
+    pub(crate) fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pos == self.chunk.len() {
+            self.generated += self.chunk.len();
+            self.chunk.clear();
+            self.pos = 0;
+            while self.chunk.is_empty() {
+                let Some(pending) = self.stack.pop() else {
+                    break;
+                };
+                let res = self
+                    .generate(pending)
+                    .and_then(|()| match self.stack.is_empty() {
+                        true => self.reader.finish(),
+                        false => Ok(()),
+                    });
+                if let Err(err) = res {
+                    self.stack.clear();
+                    self.chunk.clear();
+                    self.progress.error.set(Some(err.clone()));
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err));
+                }
+            }
+            self.progress.record(self.generated, &self.chunk);
+        }
+        Ok(&self.chunk.as_bytes()[self.pos..])
+    }
+
+    /// Marks the given number of bytes of the text as consumed.
+    pub(crate) fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+
+    /// Generates the next piece of text for the given pending part of the value.
+    fn generate(&mut self, pending: Pending) -> Result<(), CookedJsonError> {
+        let chunk = &mut self.chunk;
+        let mut state = self.progress.state.borrow_mut();
+        match pending {
+            Pending::Value(depth, file) => {
+                let start = self.reader.pos;
+                let token = self.reader.read_token()?;
+                let is_scalar = !matches!(
+                    token,
+                    Token::Array(_) | Token::Object(_) | Token::Include(_)
+                );
+                if !is_scalar && depth >= MAX_DEPTH {
+                    return Err(CookedJsonError::Malformed(start));
+                }
+                match token {
+                    Token::Null => chunk.push_str("null"),
+                    Token::Bool(value) => chunk.push_str(if value { "true" } else { "false" }),
+                    Token::Int(value) => chunk.push_str(&value.to_string()),
+                    Token::Number(text) => chunk.push_str(text),
+                    Token::String(value) => write_string(chunk, value).unwrap(),
+                    Token::Array(len) => {
+                        chunk.push('[');
+                        self.stack.push(Pending::Items(len, 0, depth + 1, file));
+                    }
+                    Token::Object(len) => {
+                        chunk.push('{');
+                        self.stack.push(Pending::Entries(len, 0, depth + 1, file));
+                    }
+                    Token::Include(inner) => {
+                        let offset = self.generated + chunk.len();
+                        self.dirs.push((offset, self.file_dirs[inner].clone()));
+                        self.stack.push(Pending::Exit(file));
+                        self.stack.push(Pending::Value(depth + 1, inner));
+                    }
+                }
+                if is_scalar {
+                    state.scalar = Some((self.generated, self.generated + chunk.len()));
+                }
+            }
+            Pending::Items(len, index, depth, file) => {
+                if len > 0 {
+                    state.next_item(Segment::Index(index), index == 0);
+                    chunk.push_str(if index > 0 { ",\n" } else { "\n" });
+                    self.stack
+                        .push(Pending::Items(len - 1, index + 1, depth, file));
+                    self.stack.push(Pending::Value(depth, file));
+                } else {
+                    state.end(index);
+                    chunk.push(']');
+                }
+            }
+            Pending::Entries(len, index, depth, file) => {
+                if len > 0 {
+                    let key = self.reader.read_key()?;
+                    state.next_item(Segment::Key(key), index == 0);
+                    chunk.push_str(if index > 0 { ",\n" } else { "\n" });
+                    write_string(chunk, key).unwrap();
+                    chunk.push(':');
+                    self.stack
+                        .push(Pending::Entries(len - 1, index + 1, depth, file));
+                    self.stack.push(Pending::Value(depth, file));
+                } else {
+                    state.end(index);
+                    chunk.push('}');
+                }
+            }
+            Pending::Exit(file) => {
+                let offset = self.generated + chunk.len();
+                self.dirs.push((offset, self.file_dirs[file].clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Records the progress of a [`CookedText`], so that errors which occur while it is being read
+/// can be located without generating all of the text.
+#[derive(Default)]
+pub(crate) struct CookedProgress<'a> {
+    /// The error in the cooked data which stopped the text, if any.
+    error: Cell<Option<CookedJsonError>>,
+    state: RefCell<ProgressState<'a>>,
+}
+
+/// The state of a [`CookedProgress`].
+#[derive(Default)]
+struct ProgressState<'a> {
+    /// The keys and indices leading to the array item or object entry whose text was generated
+    /// most recently.
+    path: Vec<Segment<'a>>,
+
+    /// The number of line breaks in the text generated so far.
+    line: usize,
+
+    /// The offset of the start of the current line.
+    line_start: usize,
+
+    /// The text of the current line generated so far.
+    line_text: String,
+
+    /// If the most recently generated piece of text started a new line, the text of the previous
+    /// line, along with the last component of the path for the item on it, if it wasn't the
+    /// first item in its container.
+    prev: Option<(String, Option<Segment<'a>>)>,
+
+    /// The component which was replaced by the item the most recent piece of text started, for
+    /// filling in `prev`.
+    replaced: Option<Option<Segment<'a>>>,
+
+    /// The range of the most recently generated string, number or literal.
+    scalar: Option<(usize, usize)>,
+}
+
+/// A component of the path to a value in a [`ProgressState`].
+#[derive(Clone, Copy)]
+enum Segment<'a> {
+    Index(usize),
+    Key(&'a str),
+}
+
+impl<'a> ProgressState<'a> {
+    /// Records that the text for the next item of an array or object is starting, given the
+    /// component of the path for it and whether it is the first item.
+    fn next_item(&mut self, segment: Segment<'a>, first: bool) {
+        let replaced = if first { None } else { self.path.pop() };
+        self.replaced = Some(replaced);
+        self.path.push(segment);
+    }
+
+    /// Records that the text for the end of an array or object with the given number of items
+    /// is starting.
+    fn end(&mut self, len: usize) {
+        if len > 0 {
+            self.path.pop();
+        }
+    }
+}
+
+impl CookedProgress<'_> {
+    /// Takes the error in the cooked data which stopped the text, if any.
+    pub(crate) fn take_error(&self) -> Option<CookedJsonError> {
+        self.error.take()
+    }
+
+    /// Records a piece of text generated at the given offset.
+    fn record(&self, offset: usize, chunk: &str) {
+        let state = &mut *self.state.borrow_mut();
+        let replaced = state.replaced.take();
+        match chunk.rfind('\n') {
+            Some(pos) => {
+                let prev = std::mem::take(&mut state.line_text) + &chunk[..pos];
+                state.prev = Some((prev, replaced.flatten()));
+                state.line += chunk.matches('\n').count();
+                state.line_start = offset + pos + 1;
+                state.line_text.push_str(&chunk[pos + 1..]);
+            }
+            None => {
+                state.prev = None;
+                state.line_text.push_str(chunk);
+            }
+        }
+    }
+
+    /// Constructs a [`JsonError`] for an error which occurred after the deserializer consumed the
+    /// given number of bytes of the text.
+    pub(crate) fn json_error(&self, consumed: usize, inner: AssetLoadInnerError) -> JsonError {
+        let state = self.state.borrow();
+        let (text, start) = match &state.prev {
+            Some((prev, _)) => (
+                format!("{}\n{}", prev, state.line_text),
+                state.line_start - prev.len() - 1,
+            ),
+            None => (state.line_text.clone(), state.line_start),
+        };
+        let mut offset = start + error_offset(&text, consumed.saturating_sub(start));
+        if let Some((scalar_start, scalar_end)) = state.scalar {
+            if (scalar_start..scalar_end).contains(&offset) {
+                offset = scalar_start;
+            }
+        }
+        let mut path = state.path.clone();
+        let (line, line_start, line_text) = match &state.prev {
+            Some((prev, replaced)) if offset < state.line_start => {
+                path.pop();
+                path.extend(*replaced);
+                (state.line, start, prev.as_str())
+            }
+            _ => (state.line + 1, state.line_start, state.line_text.as_str()),
+        };
+        let offset = (offset - line_start).min(line_text.len());
+        let mut pointer = String::new();
+        for segment in path {
+            pointer.push('/');
+            match segment {
+                Segment::Index(index) => pointer.push_str(&index.to_string()),
+                Segment::Key(key) => pointer.push_str(&key.replace('~', "~0").replace('/', "~1")),
+            }
+        }
+        JsonError {
+            line,
+            column: line_text[..offset].chars().count() + 1,
+            pointer,
+            line_text: line_text.to_owned(),
+            inner,
+        }
+    }
+}
+
+/// The type of error produced when decoding cooked JSON using [`decode_cooked_json`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CookedJsonError {
+    #[error("not a cooked JSON file")]
+    NotCooked,
+    #[error("unsupported cooked JSON version {0}")]
+    UnsupportedVersion(u8),
+    #[error("cooked JSON is truncated")]
+    Truncated,
+    #[error("malformed cooked JSON at byte {0}")]
+    Malformed(usize),
+}
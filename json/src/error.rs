@@ -0,0 +1,122 @@
+use crate::value::locate_json;
+use assetman::AssetLoadInnerError;
+
+/// Describes an error that occurred while deserializing JSON text, along with where in the text it
+/// occurred.
+///
+/// When displayed, this includes a snippet of the offending line.
+#[derive(thiserror::Error, Debug)]
+#[error("{inner}\n  at {}\n{}", self.location(), self.snippet())]
+pub struct JsonError {
+    /// The line where the error occurred, starting at 1.
+    pub line: usize,
+
+    /// The column where the error occurred, starting at 1.
+    pub column: usize,
+
+    /// The [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) for the innermost value
+    /// containing the error, e.g. `/enemies/4/health`. This is empty for the root value.
+    pub pointer: String,
+
+    /// The text of the line where the error occurred.
+    pub line_text: String,
+
+    /// Describes the error.
+    #[source]
+    pub inner: AssetLoadInnerError,
+}
+
+impl JsonError {
+    /// Constructs a [`JsonError`] for an error which occurred after the deserializer consumed the
+    /// given number of bytes of the given source text.
+    pub fn new(source: &[u8], consumed: usize, inner: AssetLoadInnerError) -> Self {
+        let text = match std::str::from_utf8(source) {
+            Ok(text) => text,
+            Err(err) => std::str::from_utf8(&source[..err.valid_up_to()]).unwrap(),
+        };
+        Self::at(text, error_offset(text, consumed), inner)
+    }
+
+    /// Constructs a [`JsonError`] for an error in the token at the given byte offset in the given
+    /// text.
+    pub(crate) fn at(text: &str, offset: usize, inner: AssetLoadInnerError) -> Self {
+        let (pointer, offset) = locate_json(text, offset);
+        let line_start = text[..offset].rfind('\n').map_or(0, |pos| pos + 1);
+        let line_end = text[offset..]
+            .find('\n')
+            .map_or(text.len(), |pos| offset + pos);
+        Self {
+            line: text[..line_start].matches('\n').count() + 1,
+            column: text[line_start..offset].chars().count() + 1,
+            pointer,
+            line_text: text[line_start..line_end].trim_end_matches('\r').to_owned(),
+            inner,
+        }
+    }
+
+    /// Describes where the error occurred.
+    fn location(&self) -> String {
+        if self.pointer.is_empty() {
+            format!("line {}, column {}", self.line, self.column)
+        } else {
+            format!(
+                "{} (line {}, column {})",
+                self.pointer, self.line, self.column
+            )
+        }
+    }
+
+    /// Renders the offending line, with a marker under the column where the error occurred. Long
+    /// lines, such as those in minified JSON, are cut down to the area around the error.
+    fn snippet(&self) -> String {
+        let chars = self.line_text.chars().collect::<Vec<_>>();
+        let index = (self.column - 1).min(chars.len());
+        let start = index.saturating_sub(SNIPPET_CONTEXT);
+        let end = (index + SNIPPET_CONTEXT).min(chars.len());
+        let mut text = String::new();
+        let mut marker = String::new();
+        if start > 0 {
+            text.push_str("...");
+            marker.push_str("   ");
+        }
+        for (i, &ch) in chars[start..end].iter().enumerate() {
+            text.push(ch);
+            if start + i < index {
+                // Preserve tabs so the marker lines up regardless of tab width
+                marker.push(if ch == '\t' { '\t' } else { ' ' });
+            }
+        }
+        if end < chars.len() {
+            text.push_str("...");
+        }
+        let gutter = self.line.to_string();
+        format!(
+            "{} | {}\n{} | {}^",
+            gutter,
+            text,
+            " ".repeat(gutter.len()),
+            marker
+        )
+    }
+}
+
+/// Gets the offset of the token which an error is attributed to, given the number of bytes of the
+/// text the deserializer consumed before the error occurred.
+pub(crate) fn error_offset(text: &str, consumed: usize) -> usize {
+    let mut consumed = consumed.min(text.len());
+    while !text.is_char_boundary(consumed) {
+        consumed -= 1;
+    }
+
+    // If the deserializer stopped just after a token, the error is attributed to that token.
+    // Otherwise, it is attributed to the next token, which may only have been peeked at.
+    match text[..consumed].chars().next_back() {
+        Some(ch) if !ch.is_whitespace() && !matches!(ch, ',' | ':' | '[' | '{') => {
+            consumed - ch.len_utf8()
+        }
+        _ => text.len() - text[consumed..].trim_start().len(),
+    }
+}
+
+/// The maximum number of characters shown on either side of an error in a [`JsonError`] snippet.
+const SNIPPET_CONTEXT: usize = 60;
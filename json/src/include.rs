@@ -0,0 +1,500 @@
+use crate::source::deserialize_text;
+use crate::{JsonDeserializer, JsonDeserializerError};
+use crate::{JsonError, JsonLoadOptions, JsonParseError, JsonValue, Parser};
+use assetman::{AssetLoadError, AssetLoadInnerError, AssetLoadResult, AssetPath, Tracker};
+use serdere::Value;
+use serdere_json::TextDeserializerConfig;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Determines whether the given JSON text may contain `$include` or `$ref` nodes, and so needs to
+/// be loaded as a [`Spliced`].
+pub(crate) fn may_include(source: &[u8]) -> bool {
+    [&b"\"$include\""[..], &b"\"$ref\""[..]]
+        .iter()
+        .any(|key| source.windows(key.len()).any(|window| window == *key))
+}
+
+/// The text of a JSON file asset, with each `$include` and `$ref` node replaced by the text of the
+/// value it refers to, recursively.
+///
+/// The text of every file is copied as written, so that it can be deserialized without
+/// reformatting, and errors can be located in the file which contains them.
+pub(crate) struct Spliced {
+    /// The spliced text.
+    text: String,
+
+    /// The pieces which make up the text, in order.
+    parts: Vec<Part>,
+
+    /// The files which the pieces are copied from, starting with the file that was loaded.
+    files: Vec<SplicedFile>,
+}
+
+/// A piece of the text of a [`Spliced`], copied from one of its files.
+struct Part {
+    /// The offset of the piece in the spliced text.
+    start: usize,
+
+    /// The index of the file the piece is copied from.
+    file: usize,
+
+    /// The offset of the piece in the text of the file.
+    offset: usize,
+}
+
+/// A file whose text is part of a [`Spliced`].
+struct SplicedFile {
+    asset: AssetPath,
+
+    /// The path of the file relative to the directory containing the file that was loaded, or
+    /// empty for the file that was loaded.
+    path: String,
+    text: Rc<str>,
+}
+
+impl Spliced {
+    /// Splices the values referred to by the includes in the given JSON file asset into its text,
+    /// which has already been loaded and checked against the given options. Included files are
+    /// tracked using the given [`Tracker`], and checked against the same options.
+    pub(crate) fn load(
+        asset: &AssetPath,
+        text: &str,
+        tracker: &Tracker,
+        options: &JsonLoadOptions,
+    ) -> AssetLoadResult<Self> {
+        let mut splicer = Splicer {
+            tracker,
+            options,
+            res: Spliced {
+                text: String::with_capacity(text.len()),
+                parts: Vec::new(),
+                files: Vec::new(),
+            },
+            indices: HashMap::new(),
+            stack: Vec::new(),
+        };
+        splicer.add_file(asset, String::new(), text.into());
+        splicer.splice(0, 0, text.len())?;
+        Ok(splicer.res)
+    }
+
+    /// Deserializes the spliced text using a deserializer interface. Errors are attributed to the
+    /// file containing them, and paths deserialized through a
+    /// [`JsonAssetContext`](crate::JsonAssetContext) are relative to the file containing them.
+    pub(crate) fn deserialize<R>(
+        &self,
+        config: TextDeserializerConfig,
+        f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+    ) -> AssetLoadResult<R> {
+        let dirs = self
+            .parts
+            .iter()
+            .map(|part| {
+                let dir = (part.file > 0).then(|| self.files[part.file].asset.parent().unwrap());
+                (part.start, dir)
+            })
+            .collect::<Vec<_>>();
+        deserialize_text(self.text.as_bytes(), &dirs, config, f)
+            .map_err(|(consumed, inner)| self.error(consumed, inner))
+    }
+
+    /// Parses the spliced text as a generic [`JsonValue`].
+    pub(crate) fn value(&self) -> AssetLoadResult<JsonValue> {
+        // Every piece was checked when its file was loaded, and the depth limit applies to each
+        // file separately
+        let options = JsonLoadOptions {
+            max_depth: None,
+            ..JsonLoadOptions::permissive()
+        };
+        assetman::with_asset(&self.files[0].asset, || {
+            Ok(JsonValue::parse_with(&self.text, &options)?)
+        })
+    }
+
+    /// Encodes the spliced value in the cooked format (see
+    /// [`encode_cooked_json`](crate::encode_cooked_json)), along with the files it includes, so
+    /// that the cooked data can be checked against them, and the file each included value came
+    /// from, so that paths within it can be resolved relative to that file.
+    pub(crate) fn cook(&self) -> AssetLoadResult<Vec<u8>> {
+        let value = self.value()?;
+        let mut parser = Parser::new(&self.text, JsonLoadOptions::permissive());
+        let mut includes = HashMap::new();
+        assetman::with_asset(&self.files[0].asset, || {
+            Ok(self.find_files(&mut parser, &mut Vec::new(), 0, &mut includes)?)
+        })?;
+        let deps = self.files[1..]
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect::<Vec<_>>();
+        Ok(crate::cooked::encode_cooked(&value, &deps, &includes))
+    }
+
+    /// Skips the value at the parser's position, which was copied from the given file, adding
+    /// the index path of each value within it which was copied from a different file than the
+    /// value containing it to `includes`, along with the index of that file.
+    fn find_files(
+        &self,
+        parser: &mut Parser,
+        path: &mut Vec<usize>,
+        file: usize,
+        includes: &mut HashMap<Vec<usize>, usize>,
+    ) -> Result<(), JsonParseError> {
+        parser.skip_whitespace()?;
+        let index = self.parts.partition_point(|part| part.start <= parser.pos);
+        let inner = self.parts[index.max(1) - 1].file;
+        if inner != file {
+            includes.insert(path.clone(), inner);
+        }
+        let close = match parser.peek() {
+            Some('{') => '}',
+            Some('[') => ']',
+            _ => {
+                parser.value()?;
+                return Ok(());
+            }
+        };
+        parser.next();
+        for i in 0.. {
+            parser.skip_whitespace()?;
+            if parser.peek() == Some(close) {
+                break;
+            }
+            if close == '}' {
+                parser.string()?;
+                parser.skip_whitespace()?;
+                parser.expect(':')?;
+            }
+            path.push(i);
+            self.find_files(parser, path, inner, includes)?;
+            path.pop();
+            parser.skip_whitespace()?;
+            if parser.next() != Some(',') {
+                return Ok(());
+            }
+        }
+        parser.next();
+        Ok(())
+    }
+
+    /// Describes an error which occurred after a deserializer consumed the given number of bytes
+    /// of the spliced text, locating it in the file which contains it.
+    fn error(&self, consumed: usize, inner: AssetLoadInnerError) -> AssetLoadError {
+        let offset = crate::error::error_offset(&self.text, consumed);
+        let index = self.parts.partition_point(|part| part.start <= offset);
+        let part = &self.parts[index.max(1) - 1];
+        let file = &self.files[part.file];
+        AssetLoadError {
+            asset: file.asset.clone(),
+            inner: JsonError::at(&file.text, part.offset + offset - part.start, inner).into(),
+        }
+    }
+}
+
+/// The maximum size of the text of a [`Spliced`], in bytes, which protects against files that
+/// include the same values many times over. The size of each file is only limited by
+/// [`JsonLoadOptions::max_size`].
+const MAX_SPLICED_SIZE: usize = 64 << 20;
+
+/// Builds a [`Spliced`].
+struct Splicer<'a> {
+    tracker: &'a Tracker,
+    options: &'a JsonLoadOptions,
+    res: Spliced,
+
+    /// The indices of the files in `res`, by asset.
+    indices: HashMap<AssetPath, usize>,
+
+    /// The includes being followed, as the index of the file containing each and its offset in
+    /// the text of the file, used to detect include cycles. Includes are identified by where they
+    /// are rather than by the file they refer to, so that a file may include other values from
+    /// itself.
+    stack: Vec<(usize, usize)>,
+}
+
+impl Splicer<'_> {
+    /// Adds a file to the result, returning its index.
+    fn add_file(&mut self, asset: &AssetPath, path: String, text: Rc<str>) -> usize {
+        let index = self.res.files.len();
+        self.res.files.push(SplicedFile {
+            asset: asset.clone(),
+            path,
+            text,
+        });
+        self.indices.insert(asset.clone(), index);
+        index
+    }
+
+    /// Gets the index of the file for the given JSON file asset, loading it and checking it
+    /// against the options if it hasn't been loaded yet. `path` is the path of the file relative
+    /// to the directory containing the file that was loaded.
+    fn file(&mut self, asset: &AssetPath, path: String) -> AssetLoadResult<usize> {
+        if let Some(&index) = self.indices.get(asset) {
+            return Ok(index);
+        }
+        let _scope = assetman::stats::LoadScope::new(asset, "json");
+        let bytes = asset.load_bytes(self.tracker)?;
+        let text = assetman::with_asset(asset, || crate::check_text(&bytes, self.options))?;
+        Ok(self.add_file(asset, path, text.into()))
+    }
+
+    /// Runs `f` with a [`Parser`] for the text of the given file, attributing errors to the file.
+    fn parse<R>(
+        &self,
+        file: usize,
+        f: impl FnOnce(&mut Parser) -> Result<R, JsonParseError>,
+    ) -> AssetLoadResult<R> {
+        let file = &self.res.files[file];
+        let options = JsonLoadOptions {
+            max_depth: None,
+            ..JsonLoadOptions::permissive()
+        };
+        let mut parser = Parser::new(&file.text, options);
+        assetman::with_asset(&file.asset, || Ok(f(&mut parser)?))
+    }
+
+    /// Starts following the include at the given offset in the text of the given file, checking
+    /// that it isn't already being followed.
+    fn enter(&mut self, file: usize, offset: usize) -> AssetLoadResult<()> {
+        if let Some(pos) = self
+            .stack
+            .iter()
+            .position(|&include| include == (file, offset))
+        {
+            let mut cycle = self.stack[pos..]
+                .iter()
+                .map(|&(file, _)| self.res.files[file].asset.clone())
+                .collect::<Vec<_>>();
+            cycle.push(self.res.files[file].asset.clone());
+            return Err(AssetLoadError {
+                asset: self.res.files[file].asset.clone(),
+                inner: JsonIncludeError::Cycle(cycle).into(),
+            });
+        }
+        self.stack.push((file, offset));
+        Ok(())
+    }
+
+    /// Appends the text of the given file between the given offsets, checking that the spliced
+    /// text doesn't exceed [`MAX_SPLICED_SIZE`].
+    fn push(&mut self, file: usize, start: usize, end: usize) -> AssetLoadResult<()> {
+        if start >= end {
+            return Ok(());
+        }
+        if self.res.text.len() + (end - start) > MAX_SPLICED_SIZE {
+            return Err(AssetLoadError {
+                asset: self.res.files[0].asset.clone(),
+                inner: JsonIncludeError::TooLarge(MAX_SPLICED_SIZE).into(),
+            });
+        }
+        self.res.parts.push(Part {
+            start: self.res.text.len(),
+            file,
+            offset: start,
+        });
+        self.res
+            .text
+            .push_str(&self.res.files[file].text[start..end]);
+        Ok(())
+    }
+
+    /// Appends the value between the given offsets in the text of the given file, with the values
+    /// referred to by the includes within it spliced in.
+    fn splice(&mut self, file: usize, start: usize, end: usize) -> AssetLoadResult<()> {
+        let includes = self.parse(file, |parser| {
+            parser.pos = start;
+            let mut includes = Vec::new();
+            find_includes(parser, &mut includes)?;
+            Ok(includes)
+        })?;
+        let mut pos = start;
+        for include in includes {
+            self.push(file, pos, include.start)?;
+            let depth = self.stack.len();
+            self.enter(file, include.start)?;
+            let (target, target_start, target_end) = self.resolve(file, &include.target)?;
+            self.splice(target, target_start, target_end)?;
+            self.stack.truncate(depth);
+            pos = include.end;
+        }
+        self.push(file, pos, end)
+    }
+
+    /// Finds the value referred to by an include target in the given file, returning the file
+    /// containing it along with the offsets of its start and end. Includes on the path to the
+    /// value are followed, and left on the stack.
+    fn resolve(&mut self, file: usize, target: &str) -> AssetLoadResult<(usize, usize, usize)> {
+        let (path, pointer) = target.split_once('#').unwrap_or((target, ""));
+        let asset = self.res.files[file].asset.parent().unwrap().relative(path);
+        let base = &self.res.files[file].path;
+        let path = format!(
+            "{}{}",
+            &base[..base.rfind('/').map_or(0, |pos| pos + 1)],
+            path
+        );
+        let index = self.file(&asset, path)?;
+        let found = self.parse(index, |parser| {
+            let mut rest = pointer;
+            while !rest.is_empty() {
+                let Some(tail) = rest.strip_prefix('/') else {
+                    return Ok(Found::Missing);
+                };
+                parser.skip_whitespace()?;
+                if let Some(include) = include_at(parser)? {
+                    return Ok(Found::Include(include, rest));
+                }
+                let (component, next) = tail.split_at(tail.find('/').unwrap_or(tail.len()));
+                let component = component.replace("~1", "/").replace("~0", "~");
+                if !parser.enter_child(&component)? {
+                    return Ok(Found::Missing);
+                }
+                rest = next;
+            }
+            parser.skip_whitespace()?;
+            let start = parser.pos;
+            parser.value()?;
+            Ok(Found::Value(start, parser.pos))
+        })?;
+        match found {
+            Found::Value(start, end) => Ok((index, start, end)),
+            Found::Include(include, rest) => {
+                // The pointer continues into the value of an include on the way
+                self.enter(index, include.start)?;
+                let target = include.target.as_str();
+                let (path, pointer) = target.split_once('#').unwrap_or((target, ""));
+                self.resolve(index, &format!("{}#{}{}", path, pointer, rest))
+            }
+            Found::Missing => Err(AssetLoadError {
+                asset,
+                inner: JsonIncludeError::MissingTarget(pointer.to_owned()).into(),
+            }),
+        }
+    }
+}
+
+/// The result of searching a file for the value at a JSON pointer.
+enum Found<'a> {
+    /// The value was found between the given offsets.
+    Value(usize, usize),
+
+    /// An include was found on the path to the value, with the given part of the pointer
+    /// remaining.
+    Include(Include, &'a str),
+    Missing,
+}
+
+/// An `$include` or `$ref` node in JSON text.
+struct Include {
+    /// The offset of the start of the node.
+    start: usize,
+
+    /// The offset of the end of the node.
+    end: usize,
+
+    /// The file, and optionally the JSON pointer, that the node refers to.
+    target: String,
+}
+
+/// Skips the value at the parser's position, adding the `$include` and `$ref` nodes within it to
+/// `includes`, in order.
+fn find_includes(parser: &mut Parser, includes: &mut Vec<Include>) -> Result<(), JsonParseError> {
+    parser.skip_whitespace()?;
+    if let Some(include) = include_at(parser)? {
+        includes.push(include);
+        return Ok(());
+    }
+    let close = match parser.peek() {
+        Some('{') => '}',
+        Some('[') => ']',
+        _ => {
+            parser.value()?;
+            return Ok(());
+        }
+    };
+    parser.next();
+    loop {
+        parser.skip_whitespace()?;
+        if parser.peek() == Some(close) {
+            break;
+        }
+        if close == '}' {
+            parser.string()?;
+            parser.skip_whitespace()?;
+            parser.expect(':')?;
+        }
+        find_includes(parser, includes)?;
+        parser.skip_whitespace()?;
+        if parser.next() != Some(',') {
+            return Ok(());
+        }
+    }
+    parser.next();
+    Ok(())
+}
+
+/// Parses the `$include` or `$ref` node at the parser's position, if there is one. Otherwise, the
+/// parser is left where it was.
+///
+/// Only nodes which refer to a file are includes. Nodes which only have a fragment, such as
+/// `{"$ref": "#/definitions/item"}`, are left for the deserializer, e.g. to be interpreted as
+/// part of a JSON Schema.
+fn include_at(parser: &mut Parser) -> Result<Option<Include>, JsonParseError> {
+    let state = (parser.pos, parser.line, parser.line_start);
+    let include = parse_include(parser)?;
+    if include.is_none() {
+        (parser.pos, parser.line, parser.line_start) = state;
+    }
+    Ok(include)
+}
+
+/// Parses the `$include` or `$ref` node at the parser's position, if there is one, leaving the
+/// parser at an unspecified position otherwise.
+fn parse_include(parser: &mut Parser) -> Result<Option<Include>, JsonParseError> {
+    let start = parser.pos;
+    if parser.peek() != Some('{') {
+        return Ok(None);
+    }
+    parser.next();
+    parser.skip_whitespace()?;
+    if parser.peek() != Some('"') || !matches!(parser.string()?.as_str(), "$include" | "$ref") {
+        return Ok(None);
+    }
+    parser.skip_whitespace()?;
+    parser.expect(':')?;
+    parser.skip_whitespace()?;
+    if parser.peek() != Some('"') {
+        return Ok(None);
+    }
+    let target = parser.string()?;
+    parser.skip_whitespace()?;
+    if parser.peek() == Some(',') {
+        parser.next();
+        parser.skip_whitespace()?;
+    }
+    if parser.peek() != Some('}') || target.starts_with('#') || target.is_empty() {
+        return Ok(None);
+    }
+    parser.next();
+    Ok(Some(Include {
+        start,
+        end: parser.pos,
+        target,
+    }))
+}
+
+/// The type of error produced when an `$include` or `$ref` node in a JSON file can't be resolved.
+#[derive(thiserror::Error, Debug)]
+pub enum JsonIncludeError {
+    #[error("include cycle: {}", display_cycle(.0))]
+    Cycle(Vec<AssetPath>),
+    #[error("included file has no value at {0:?}")]
+    MissingTarget(String),
+    #[error("text with includes spliced in exceeds the maximum size of {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Formats the assets in an include cycle for display.
+fn display_cycle(cycle: &[AssetPath]) -> String {
+    let names = cycle.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    names.join(" -> ")
+}
@@ -1,17 +1,83 @@
-use assetman::{AssetLoadResult, AssetPath, Tracker};
+use assetman::{AssetCache, AssetLoadResult, AssetPath, AssetSaveResult, Tracker, WriteOptions};
 use serdere::{Deserialize, Outliner, Utf8Reader, Value};
 use serdere_json::{TextDeserializer, TextDeserializerConfig};
-use std::io::BufReader;
+
+mod config;
+mod context;
+mod cooked;
+mod error;
+mod include;
+mod merge;
+mod options;
+mod patch;
+mod schema;
+mod source;
+mod stream;
+mod to_json;
+mod value;
+
+pub use assetman_json_derive::{JsonSchema, ToJson};
+pub use config::*;
+pub use context::*;
+pub use cooked::{
+    cooked_json_path, decode_cooked_json, encode_cooked_json, is_cooked_json,
+    is_cooked_json_current, CookedJsonError,
+};
+pub use error::*;
+pub use include::JsonIncludeError;
+pub use merge::*;
+pub use options::*;
+pub use patch::*;
+pub use schema::*;
+pub use source::JsonSource;
+pub use stream::{JsonArrayStream, JsonElementError};
+pub use to_json::*;
+pub use value::*;
 
 /// Contains JSON-loading extensions for [`AssetPath`].
 pub trait AssetPathJsonExt {
     /// Loads a JSON file asset using a deserializer interface.
+    ///
+    /// Objects of the form `{"$include": "other.json"}` or `{"$ref": "other.json"}` are replaced
+    /// by the contents of the referenced file, relative to the file containing them. A
+    /// [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) fragment may be given to select a
+    /// value within the file, as in `"other.json#/enemies/0"`. Included files are tracked using
+    /// the same [`Tracker`]. Include cycles, and files which are larger than 64 MiB once their
+    /// includes are spliced in, are reported as a [`JsonIncludeError`].
+    ///
+    /// Only objects naming another file are replaced. References within the same file, such as
+    /// `{"$ref": "#/definitions/item"}`, are left as they are.
+    ///
+    /// Errors are reported as a [`JsonError`], which gives the location of the error. Errors in
+    /// the value of an include are attributed to, and located within, the included file.
+    ///
+    /// The text is checked using the default [`JsonLoadOptions`] for the asset's root (see
+    /// [`JsonLoadOptions::default_for`]).
+    ///
+    /// If [`JsonLoadOptions::use_cooked`] is set and the file has a cooked sibling (see
+    /// [`cooked_json_path`]) which was modified more recently than the file itself and every file
+    /// it includes, the cooked data is deserialized instead, skipping these checks. Errors are
+    /// then located in the text generated for it, which has each array item and object entry on
+    /// its own line. A sibling which isn't cooked JSON, such as the buffer of a glTF file, is
+    /// ignored. See [`is_cooked_json_current`].
     fn load_json_with<R>(
         &self,
         tracker: &Tracker,
         f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
     ) -> AssetLoadResult<R>;
 
+    /// Loads a JSON file asset using a deserializer interface, as with
+    /// [`AssetPathJsonExt::load_json_with`], but using the given options rather than the default
+    /// for the asset's root. Included files are loaded with the same options.
+    ///
+    /// Syntax that isn't allowed by the options is reported as a [`JsonParseError`].
+    fn load_json_with_options<R>(
+        &self,
+        tracker: &Tracker,
+        options: &JsonLoadOptions,
+        f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+    ) -> AssetLoadResult<R>;
+
     /// Loads a JSON file asset, deserializing it into a value of type `T`.
     fn load_json<T: for<'a> Deserialize<JsonDeserializer<'a>>>(
         &self,
@@ -29,6 +95,85 @@ pub trait AssetPathJsonExt {
     ) -> AssetLoadResult<T> {
         self.load_json_with(tracker, |de| de.get_using(context))
     }
+
+    /// Loads a JSON file asset, deserializing it into a value of type `T` using a
+    /// [`JsonAssetContext`] for the asset. This allows [`AssetPath`] fields to be deserialized
+    /// from strings relative to the directory containing the JSON file.
+    fn load_json_relative<T: for<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext>>(
+        &self,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<T>;
+
+    /// Loads a JSON file asset, deserializing it into a value of type `T` using a
+    /// [`JsonAssetContext`] for the asset, as with [`AssetPathJsonExt::load_json_relative`], but
+    /// loading [`Handle`](assetman::Handle) fields through the given [`AssetCache`].
+    fn load_json_relative_cached<T: for<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext>>(
+        &self,
+        cache: &AssetCache,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<T>;
+
+    /// Loads a JSON file asset, checking it against the [`JsonSchema`] for `T` before
+    /// deserializing it. Unlike [`AssetPathJsonExt::load_json`], this reports every schema
+    /// violation in the file at once, as a [`JsonSchemaError`].
+    fn load_json_validated<T: JsonSchema + for<'a> Deserialize<JsonDeserializer<'a>>>(
+        &self,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<T> {
+        self.check_json_schema(tracker, &T::json_schema())?;
+        self.load_json(tracker)
+    }
+
+    /// Checks a JSON file asset, with `$include` and `$ref` nodes resolved, against the given
+    /// [JSON Schema](https://json-schema.org/), reporting all violations as a [`JsonSchemaError`].
+    /// See [`validate_json`] for the supported keywords.
+    fn check_json_schema(&self, tracker: &Tracker, schema: &JsonValue) -> AssetLoadResult<()>;
+
+    /// Opens a JSON file asset whose root value is an array, or which has an array at the given
+    /// [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) such as `/strings`, returning an
+    /// iterator which reads and deserializes the elements of the array one at a time. Only one
+    /// element is held in memory at a time, regardless of the size of the array.
+    ///
+    /// Errors for an element are reported as a [`JsonElementError`], which gives its index.
+    /// Unlike [`AssetPathJsonExt::load_json`], `$include` and `$ref` nodes aren't resolved, cooked
+    /// siblings aren't used, and only the `allow_comments` and `allow_trailing_commas`
+    /// [`JsonLoadOptions`] are checked.
+    fn stream_json_array<T: for<'a> Deserialize<JsonDeserializer<'a>>>(
+        &self,
+        tracker: &Tracker,
+        pointer: &str,
+    ) -> AssetLoadResult<JsonArrayStream<T>>;
+
+    /// Loads a JSON file asset as a generic [`JsonValue`], using the default [`JsonLoadOptions`]
+    /// for the asset's root.
+    fn load_json_value(&self, tracker: &Tracker) -> AssetLoadResult<JsonValue>;
+
+    /// Loads a JSON file asset as a generic [`JsonValue`], resolving `$include` and `$ref` nodes
+    /// as described for [`AssetPathJsonExt::load_json_with`].
+    fn load_json_value_resolved(&self, tracker: &Tracker) -> AssetLoadResult<JsonValue>;
+
+    /// Loads a JSON file asset, resolving `$include` and `$ref` nodes, and encodes it using
+    /// [`encode_cooked_json`], ready to be written to its cooked sibling (see
+    /// [`cooked_json_path`]). Any existing cooked sibling is ignored. The cooked data records the
+    /// files that were included, so that it is ignored once any of them change, and paths in
+    /// included values are still resolved relative to the file they came from.
+    fn cook_json(&self, tracker: &Tracker) -> AssetLoadResult<Vec<u8>>;
+
+    /// Enumerates the assets referenced by a JSON file asset.
+    ///
+    /// Since JSON has no dedicated syntax for references, every string value which looks like a
+    /// relative file path (see [`is_asset_reference`]) is interpreted as a reference to an asset
+    /// relative to the directory containing the JSON file.
+    fn scan_json_references(&self, tracker: &Tracker) -> AssetLoadResult<Vec<AssetPath>>;
+
+    /// Saves a value as an indented JSON file asset, in the format it would be loaded from by
+    /// [`AssetPathJsonExt::load_json`]. This accepts a [`JsonValue`], or any other type which
+    /// implements [`ToJson`]. See [`AssetPath::write_bytes`].
+    fn save_json<T: ToJson + ?Sized>(
+        &self,
+        value: &T,
+        options: &WriteOptions,
+    ) -> AssetSaveResult<()>;
 }
 
 impl AssetPathJsonExt for AssetPath {
@@ -37,20 +182,256 @@ impl AssetPathJsonExt for AssetPath {
         tracker: &Tracker,
         f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
     ) -> AssetLoadResult<R> {
-        let mut file = self.open_file(tracker)?;
-        assetman::with_asset(self, || {
-            let reader = Utf8Reader::new(BufReader::<&mut dyn std::io::Read>::new(&mut file))?;
-            Ok(
-                TextDeserializer::new(TextDeserializerConfig::permissive(), reader)
-                    .and_then(|mut deserializer| Value::with(&mut deserializer, f))?,
-            )
-        })
+        self.load_json_with_options(tracker, &JsonLoadOptions::default_for(self), f)
+    }
+
+    fn load_json_with_options<R>(
+        &self,
+        tracker: &Tracker,
+        options: &JsonLoadOptions,
+        f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+    ) -> AssetLoadResult<R> {
+        let _scope = assetman::stats::LoadScope::new(self, "json");
+        let config = deserializer_config(options);
+        if options.use_cooked {
+            if let Some(cooked) = cooked::load_cooked(self, tracker)? {
+                // Cooked data was checked, and had its includes resolved, when it was cooked
+                return source::deserialize_cooked(self, &cooked, config, f);
+            }
+        }
+        let bytes = self.load_bytes(tracker)?;
+        if include::may_include(&bytes) {
+            let text = assetman::with_asset(self, || check_text(&bytes, options))?;
+            return include::Spliced::load(self, text, tracker, options)?.deserialize(config, f);
+        }
+        if !options.is_lenient() {
+            assetman::with_asset(self, || check_text(&bytes, options))?;
+        }
+        assetman::with_asset(self, || Ok(deserialize_json(&bytes, config, f)?))
+    }
+
+    fn load_json_relative<T: for<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext>>(
+        &self,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<T> {
+        load_json_in(self, tracker, JsonAssetContext::new(self))
+    }
+
+    fn load_json_relative_cached<T: for<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext>>(
+        &self,
+        cache: &AssetCache,
+        tracker: &Tracker,
+    ) -> AssetLoadResult<T> {
+        load_json_in(
+            self,
+            tracker,
+            JsonAssetContext::with_cache(self, cache.clone()),
+        )
+    }
+
+    fn stream_json_array<T: for<'a> Deserialize<JsonDeserializer<'a>>>(
+        &self,
+        tracker: &Tracker,
+        pointer: &str,
+    ) -> AssetLoadResult<JsonArrayStream<T>> {
+        let _scope = assetman::stats::LoadScope::new(self, "json-stream");
+        let reader = self.open(tracker)?;
+        JsonArrayStream::open(self, reader, JsonLoadOptions::default_for(self), pointer)
+    }
+
+    fn load_json_value(&self, tracker: &Tracker) -> AssetLoadResult<JsonValue> {
+        load_value(self, tracker, &JsonLoadOptions::default_for(self))
+    }
+
+    fn load_json_value_resolved(&self, tracker: &Tracker) -> AssetLoadResult<JsonValue> {
+        let options = JsonLoadOptions::default_for(self);
+        if options.use_cooked {
+            if let Some(cooked) = cooked::load_cooked(self, tracker)? {
+                return assetman::with_asset(cooked.asset(), || Ok(cooked.value()?));
+            }
+        }
+        load_value_resolved(self, tracker, &options)
     }
+
+    fn cook_json(&self, tracker: &Tracker) -> AssetLoadResult<Vec<u8>> {
+        let _scope = assetman::stats::LoadScope::new(self, "json");
+        let options = JsonLoadOptions::default_for(self);
+        let bytes = self.load_bytes(tracker)?;
+        if !include::may_include(&bytes) {
+            let value = assetman::with_asset(self, || parse_value(&bytes, &options))?;
+            return Ok(encode_cooked_json(&value));
+        }
+        let text = assetman::with_asset(self, || check_text(&bytes, &options))?;
+        include::Spliced::load(self, text, tracker, &options)?.cook()
+    }
+
+    fn check_json_schema(&self, tracker: &Tracker, schema: &JsonValue) -> AssetLoadResult<()> {
+        let value = self.load_json_value_resolved(tracker)?;
+        let violations = schema::validate_json(schema, &value);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(assetman::AssetLoadError {
+                asset: self.clone(),
+                inner: JsonSchemaError(violations).into(),
+            })
+        }
+    }
+
+    fn scan_json_references(&self, tracker: &Tracker) -> AssetLoadResult<Vec<AssetPath>> {
+        let value = self.load_json_value(tracker)?;
+        let dir = self.parent().unwrap();
+        let mut references = Vec::new();
+        let mut stack = vec![&value];
+        while let Some(value) = stack.pop() {
+            match value {
+                JsonValue::String(text) if is_asset_reference(text) => {
+                    references.push(dir.relative(text));
+                }
+                JsonValue::Array(items) => stack.extend(items.iter().rev()),
+                JsonValue::Object(entries) => stack.extend(entries.iter().rev().map(|(_, v)| v)),
+                _ => {}
+            }
+        }
+        Ok(references)
+    }
+
+    fn save_json<T: ToJson + ?Sized>(
+        &self,
+        value: &T,
+        options: &WriteOptions,
+    ) -> AssetSaveResult<()> {
+        self.write_bytes(format!("{:#}\n", value.to_json()).as_bytes(), options)
+    }
+}
+
+/// Loads a JSON file asset, deserializing it into a value of type `T` using the given context. The
+/// assets loaded through the context are tracked using the given [`Tracker`].
+fn load_json_in<T: for<'a> Deserialize<JsonDeserializer<'a>, JsonAssetContext>>(
+    asset: &AssetPath,
+    tracker: &Tracker,
+    mut ctx: JsonAssetContext,
+) -> AssetLoadResult<T> {
+    let res = asset.load_json_using(tracker, &mut ctx);
+    tracker.set(tracker.get() & ctx.tracker.get());
+    res.map_err(|mut err| {
+        // Failures to load handles are reported in place of the error they stopped
+        // deserialization with, at the same location
+        if let Some(failure) = ctx.take_failure() {
+            match err.inner.downcast_mut::<JsonError>() {
+                Some(json_err) => json_err.inner = failure.into(),
+                None => err.inner = failure.into(),
+            }
+        }
+        err
+    })
+}
+
+/// Gets the configuration for the deserializer used to load JSON with the given options.
+fn deserializer_config(options: &JsonLoadOptions) -> TextDeserializerConfig {
+    match options.strict {
+        true => TextDeserializerConfig::strict(),
+        false => TextDeserializerConfig::permissive(),
+    }
+}
+
+/// Loads a JSON file asset as a generic [`JsonValue`], using the given options.
+fn load_value(
+    asset: &AssetPath,
+    tracker: &Tracker,
+    options: &JsonLoadOptions,
+) -> AssetLoadResult<JsonValue> {
+    let _scope = assetman::stats::LoadScope::new(asset, "json");
+    let bytes = asset.load_bytes(tracker)?;
+    assetman::with_asset(asset, || parse_value(&bytes, options))
+}
+
+/// Loads a JSON file asset as a generic [`JsonValue`], using the given options, and resolves its
+/// includes.
+fn load_value_resolved(
+    asset: &AssetPath,
+    tracker: &Tracker,
+    options: &JsonLoadOptions,
+) -> AssetLoadResult<JsonValue> {
+    let _scope = assetman::stats::LoadScope::new(asset, "json");
+    let bytes = asset.load_bytes(tracker)?;
+    if !include::may_include(&bytes) {
+        return assetman::with_asset(asset, || parse_value(&bytes, options));
+    }
+    let text = assetman::with_asset(asset, || check_text(&bytes, options))?;
+    include::Spliced::load(asset, text, tracker, options)?.value()
+}
+
+/// Checks that the contents of a JSON file are accepted by the given options, returning its text.
+fn check_text<'a>(
+    bytes: &'a [u8],
+    options: &JsonLoadOptions,
+) -> Result<&'a str, assetman::AssetLoadInnerError> {
+    let text = std::str::from_utf8(bytes)?;
+    JsonValue::parse_with(text, options)?;
+    Ok(text)
+}
+
+/// Parses a [`JsonValue`] from the contents of a JSON file.
+fn parse_value(
+    bytes: &[u8],
+    options: &JsonLoadOptions,
+) -> Result<JsonValue, assetman::AssetLoadInnerError> {
+    Ok(JsonValue::parse_with(std::str::from_utf8(bytes)?, options)?)
+}
+
+/// Determines whether a JSON string value should be interpreted as a reference to another asset.
+///
+/// This accepts strings such as `"../img/wood.png"` or `"level.json"`: strings without whitespace
+/// or a URI scheme whose last path component has a file extension containing at least one
+/// letter. Unless the string contains a path separator, the extension must also be one of
+/// [`ASSET_EXTENSIONS`], so that strings such as `"Hello.World"` or `"1.0b"` aren't accepted.
+pub fn is_asset_reference(text: &str) -> bool {
+    if text.is_empty() || text.contains("://") || text.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let (dir, name) = match text.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, text),
+    };
+    match name.rfind('.') {
+        Some(pos) if pos > 0 => {
+            let ext = &name[pos + 1..];
+            match dir {
+                Some(_) => {
+                    !ext.is_empty()
+                        && ext.chars().all(|ch| ch.is_ascii_alphanumeric())
+                        && ext.chars().any(|ch| ch.is_ascii_alphabetic())
+                }
+                None => ASSET_EXTENSIONS
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(ext)),
+            }
+        }
+        _ => false,
+    }
+}
+
+/// The file extensions of the asset formats loaded by the `assetman` crates, which are recognized
+/// by [`is_asset_reference`] in strings without a path separator.
+pub const ASSET_EXTENSIONS: &[&str] = &[
+    "json", "json5", "ron", "toml", "gltf", "glb", "bin", "png", "jpg", "jpeg", "gif", "bmp",
+    "tga", "tiff", "webp", "wgsl", "gz", "zst", "lz4",
+];
+
+/// Deserializes JSON text using a deserializer interface, reporting the location of any error
+/// that occurs.
+pub fn deserialize_json<R>(
+    source: &[u8],
+    config: TextDeserializerConfig,
+    f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+) -> Result<R, JsonError> {
+    source::deserialize_text(source, &[], config, f)
+        .map_err(|(consumed, inner)| JsonError::new(source, consumed, inner))
 }
 
-/// The type of JSON deserializer provided by an [`AssetLoader`].
-pub type JsonDeserializer<'a> =
-    TextDeserializer<Utf8Reader<std::io::BufReader<&'a mut dyn std::io::Read>>>;
+/// The type of JSON deserializer provided by [`AssetPathJsonExt::load_json_with`].
+pub type JsonDeserializer<'a> = TextDeserializer<Utf8Reader<JsonSource<'a>>>;
 
 /// The type of error produced by a [`JsonDeserializer`].
 pub type JsonDeserializerError = <JsonDeserializer<'static> as Outliner>::Error;
@@ -0,0 +1,80 @@
+use crate::source::deserialize_value;
+use crate::{AssetPathJsonExt, JsonDeserializer, JsonLoadOptions, JsonValue};
+use assetman::{AssetLoadResult, AssetPath, Tracker};
+use serdere::Deserialize;
+
+/// Determines how an array in one JSON layer is combined with an array in a lower layer by
+/// [`merge_json`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayMergePolicy {
+    /// The array in the higher layer replaces the array in the lower layer.
+    #[default]
+    Replace,
+
+    /// The items of the array in the higher layer are appended to the array in the lower layer.
+    Append,
+}
+
+/// Deep-merges a higher-priority JSON layer into a lower-priority one.
+///
+/// Objects are merged key by key, with an explicit `null` in `overlay` deleting the corresponding
+/// entry from `base`. Arrays are combined according to the given policy. All other values in
+/// `overlay` replace the corresponding values in `base`.
+///
+/// If `overlay` itself is `null`, `base` is left unchanged, so an empty layer can be written as
+/// `null`.
+pub fn merge_json(base: &mut JsonValue, overlay: JsonValue, arrays: ArrayMergePolicy) {
+    match (base, overlay) {
+        (_, JsonValue::Null) => {}
+        (JsonValue::Object(base_entries), JsonValue::Object(overlay_entries)) => {
+            for (key, value) in overlay_entries {
+                let existing = base_entries.iter().position(|(k, _)| *k == key);
+                match (existing, value) {
+                    (Some(index), JsonValue::Null) => {
+                        base_entries.remove(index);
+                    }
+                    (None, JsonValue::Null) => {}
+                    (Some(index), value) => merge_json(&mut base_entries[index].1, value, arrays),
+                    (None, value) => base_entries.push((key, value)),
+                }
+            }
+        }
+        (JsonValue::Array(base_items), JsonValue::Array(overlay_items))
+            if arrays == ArrayMergePolicy::Append =>
+        {
+            base_items.extend(overlay_items);
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Loads an ordered list of JSON file asset layers, such as `base.json`, `platform/linux.json`
+/// and `user.json`, deep-merges them using [`merge_json`] with later layers taking priority, and
+/// deserializes the result into a value of type `T`.
+///
+/// Every layer is tracked using the given [`Tracker`], so a change to any of them invalidates the
+/// result. Each layer is loaded as by [`AssetPathJsonExt::load_json_value_resolved`], so layers
+/// may contain `$include` and `$ref` nodes. The merged value is deserialized directly, and
+/// deserialization errors are attributed to the last layer, with a location in the merged value
+/// as formatted by [`AssetPathJsonExt::save_json`].
+///
+/// # Panics
+///
+/// Panics if `layers` is empty.
+pub fn load_json_layers<T: for<'a> Deserialize<JsonDeserializer<'a>>>(
+    layers: &[AssetPath],
+    tracker: &Tracker,
+    arrays: ArrayMergePolicy,
+) -> AssetLoadResult<T> {
+    let top = layers.last().expect("no JSON layers given");
+    let _scope = assetman::stats::LoadScope::new(top, "json-layers");
+    let mut merged = JsonValue::Null;
+    for layer in layers {
+        let value = layer.load_json_value_resolved(tracker)?;
+        merge_json(&mut merged, value, arrays);
+    }
+    let config = crate::deserializer_config(&JsonLoadOptions::default_for(top));
+    assetman::with_asset(top, || {
+        Ok(deserialize_value(&merged, config, |value| value.get())?)
+    })
+}
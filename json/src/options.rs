@@ -0,0 +1,141 @@
+use assetman::{AssetPath, WeakAssetRoot};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, RwLock};
+
+/// Options which determine what JSON text is accepted when loading a JSON file asset.
+///
+/// The [`Default`] options are permissive, which suits hand-edited files during development. Use
+/// [`JsonLoadOptions::strict`] to reject sloppy JSON, e.g. in shipping builds, either for a
+/// single load using [`AssetPathJsonExt::load_json_with_options`] or for every load from a root
+/// using [`JsonLoadOptions::set_root_default`].
+///
+/// [`AssetPathJsonExt::load_json_with_options`]: crate::AssetPathJsonExt::load_json_with_options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonLoadOptions {
+    /// If `true`, the deserializer uses `TextDeserializerConfig::strict()` rather than
+    /// `TextDeserializerConfig::permissive()`, which may reject syntax beyond that covered by the
+    /// other options. Numbers with leading zeros and strings containing unescaped control
+    /// characters are rejected.
+    pub strict: bool,
+
+    /// If `true`, `//` and `/* */` comments are allowed.
+    pub allow_comments: bool,
+
+    /// If `true`, a comma is allowed after the last item of an array or object.
+    pub allow_trailing_commas: bool,
+
+    /// Determines how objects with more than one entry for the same key are handled.
+    pub duplicate_keys: DuplicateKeyPolicy,
+
+    /// The maximum nesting depth of arrays and objects, or [`None`] for no limit. This defaults
+    /// to [`JsonLoadOptions::DEFAULT_MAX_DEPTH`], which protects against stack overflows when
+    /// deeply nested text is parsed into a [`JsonValue`](crate::JsonValue).
+    pub max_depth: Option<usize>,
+
+    /// The maximum size of a JSON file, in bytes, or [`None`] for no limit.
+    pub max_size: Option<usize>,
+
+    /// If `true`, a JSON file with an up-to-date cooked sibling (see
+    /// [`cooked_json_path`](crate::cooked_json_path)) is loaded from the sibling instead. This is
+    /// off by default, so that loads only look for cooked siblings where they are expected, e.g.
+    /// for the root of a shipping build (see [`JsonLoadOptions::set_root_default`]).
+    pub use_cooked: bool,
+}
+
+/// Determines how objects with more than one entry for the same key are handled when loading
+/// JSON. See [`JsonLoadOptions::duplicate_keys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Duplicate keys are passed through to the deserializer.
+    #[default]
+    Allow,
+
+    /// Duplicate keys are reported as an error.
+    Reject,
+}
+
+impl Default for JsonLoadOptions {
+    fn default() -> Self {
+        Self::permissive()
+    }
+}
+
+impl JsonLoadOptions {
+    /// The default value of [`JsonLoadOptions::max_depth`].
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Gets options which accept comments, trailing commas, duplicate keys and number syntax
+    /// such as leading zeros, limited only by the default maximum depth.
+    pub const fn permissive() -> Self {
+        Self {
+            strict: false,
+            allow_comments: true,
+            allow_trailing_commas: true,
+            duplicate_keys: DuplicateKeyPolicy::Allow,
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+            max_size: None,
+            use_cooked: false,
+        }
+    }
+
+    /// Gets options which accept only standard JSON, without duplicate keys, limited by the
+    /// default maximum depth.
+    pub const fn strict() -> Self {
+        Self {
+            strict: true,
+            allow_comments: false,
+            allow_trailing_commas: false,
+            duplicate_keys: DuplicateKeyPolicy::Reject,
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+            max_size: None,
+            use_cooked: false,
+        }
+    }
+
+    /// Determines whether these options accept all of the syntax that the permissive
+    /// deserializer does, in which case the text doesn't need to be checked before
+    /// deserialization.
+    ///
+    /// Deserializing text directly into a type doesn't build a [`JsonValue`](crate::JsonValue),
+    /// so a depth limit at least as high as the default doesn't need to be checked.
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.allow_comments
+            && self.allow_trailing_commas
+            && self.duplicate_keys == DuplicateKeyPolicy::Allow
+            && self
+                .max_depth
+                .is_none_or(|max| max >= Self::DEFAULT_MAX_DEPTH)
+            && self.max_size.is_none()
+    }
+
+    /// Sets the default options for loading JSON file assets derived from the given root, used by
+    /// [`AssetPathJsonExt::load_json`](crate::AssetPathJsonExt::load_json) and related methods.
+    /// The options are forgotten once the root is dropped.
+    pub fn set_root_default(root: &AssetPath, options: JsonLoadOptions) {
+        let mut defaults = ROOT_DEFAULTS.write().unwrap();
+        defaults.retain(|root, _| root.upgrade().is_some());
+        defaults.insert(root.downgrade_root(), options);
+        HAS_ROOT_DEFAULTS.store(true, Ordering::Release);
+    }
+
+    /// Gets the default options for loading the given JSON file asset: those set for its root
+    /// using [`JsonLoadOptions::set_root_default`], or permissive options otherwise.
+    pub fn default_for(asset: &AssetPath) -> JsonLoadOptions {
+        // Most programs never set root defaults, so loads don't need to take the lock
+        if !HAS_ROOT_DEFAULTS.load(Ordering::Acquire) {
+            return Self::permissive();
+        }
+        let defaults = ROOT_DEFAULTS.read().unwrap();
+        defaults
+            .get(&asset.downgrade_root())
+            .map_or_else(Self::permissive, Clone::clone)
+    }
+}
+
+/// The default [`JsonLoadOptions`] for roots, as set by [`JsonLoadOptions::set_root_default`].
+static ROOT_DEFAULTS: LazyLock<RwLock<HashMap<WeakAssetRoot, JsonLoadOptions>>> =
+    LazyLock::new(Default::default);
+
+/// Whether [`ROOT_DEFAULTS`] may have any entries.
+static HAS_ROOT_DEFAULTS: AtomicBool = AtomicBool::new(false);
@@ -0,0 +1,306 @@
+use crate::schema::with_component;
+use crate::source::deserialize_value;
+use crate::{AssetPathJsonExt, JsonDeserializer, JsonLoadOptions, JsonValue};
+use assetman::{AssetLoadResult, AssetPath, Tracker};
+use serdere::Deserialize;
+
+/// An operation which changes part of a JSON document, identified by a
+/// [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901), as in
+/// [JSON Patch](https://www.rfc-editor.org/rfc/rfc6902). Operations which remove or replace a
+/// value also keep the previous value, for logging.
+///
+/// When displayed, this gives a short description of the change, such as
+/// `/weapons/3/damage 10 -> 12`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonPatchOp {
+    /// Adds an entry to an object, or inserts an element into an array at the given index, or at
+    /// the end if the last component of the path is `-`.
+    Add { path: String, value: JsonValue },
+
+    /// Removes an entry from an object or an element from an array.
+    Remove { path: String, old: JsonValue },
+
+    /// Replaces an existing value.
+    Replace {
+        path: String,
+        old: JsonValue,
+        value: JsonValue,
+    },
+}
+
+impl JsonPatchOp {
+    /// Gets the JSON pointer for the value changed by this operation.
+    pub fn path(&self) -> &str {
+        match self {
+            JsonPatchOp::Add { path, .. } => path,
+            JsonPatchOp::Remove { path, .. } => path,
+            JsonPatchOp::Replace { path, .. } => path,
+        }
+    }
+
+    /// Converts this operation into its JSON Patch representation, such as
+    /// `{"op": "replace", "path": "/weapons/3/damage", "value": 12}`.
+    pub fn to_json(&self) -> JsonValue {
+        let (op, value) = match self {
+            JsonPatchOp::Add { value, .. } => ("add", Some(value)),
+            JsonPatchOp::Remove { .. } => ("remove", None),
+            JsonPatchOp::Replace { value, .. } => ("replace", Some(value)),
+        };
+        let mut entries = vec![
+            ("op".to_owned(), JsonValue::String(op.to_owned())),
+            ("path".to_owned(), JsonValue::String(self.path().to_owned())),
+        ];
+        if let Some(value) = value {
+            entries.push(("value".to_owned(), value.clone()));
+        }
+        JsonValue::Object(entries)
+    }
+}
+
+impl std::fmt::Display for JsonPatchOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonPatchOp::Add { path, value } => write!(f, "{} added {}", path, value),
+            JsonPatchOp::Remove { path, old } => write!(f, "{} removed {}", path, old),
+            JsonPatchOp::Replace { path, old, value } => write!(f, "{} {} -> {}", path, old, value),
+        }
+    }
+}
+
+/// Computes the operations which transform `old` into `new` when applied in order using
+/// [`apply_json_patch`].
+///
+/// Objects are compared key by key, ignoring the order of their entries, and arrays are compared
+/// index by index, with elements added or removed at the end. All other values are replaced
+/// whole if they differ.
+pub fn diff_json(old: &JsonValue, new: &JsonValue) -> Vec<JsonPatchOp> {
+    let mut ops = Vec::new();
+    diff_value(&mut String::new(), old, new, &mut ops);
+    ops
+}
+
+/// Appends the operations which transform `old` into `new`, for values at the given pointer.
+fn diff_value(pointer: &mut String, old: &JsonValue, new: &JsonValue, ops: &mut Vec<JsonPatchOp>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (JsonValue::Object(old_entries), JsonValue::Object(new_entries)) => {
+            for (key, old_value) in old_entries {
+                with_component(pointer, key, |pointer| match new.get(key) {
+                    Some(new_value) => diff_value(pointer, old_value, new_value, ops),
+                    None => ops.push(JsonPatchOp::Remove {
+                        path: pointer.clone(),
+                        old: old_value.clone(),
+                    }),
+                });
+            }
+            for (key, new_value) in new_entries {
+                if old.get(key).is_none() {
+                    with_component(pointer, key, |pointer| {
+                        ops.push(JsonPatchOp::Add {
+                            path: pointer.clone(),
+                            value: new_value.clone(),
+                        })
+                    });
+                }
+            }
+        }
+        (JsonValue::Array(old_items), JsonValue::Array(new_items)) => {
+            for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                with_component(pointer, &index.to_string(), |pointer| {
+                    diff_value(pointer, old_item, new_item, ops)
+                });
+            }
+            for (index, new_item) in new_items.iter().enumerate().skip(old_items.len()) {
+                with_component(pointer, &index.to_string(), |pointer| {
+                    ops.push(JsonPatchOp::Add {
+                        path: pointer.clone(),
+                        value: new_item.clone(),
+                    })
+                });
+            }
+
+            // Elements are removed from the end, so that the indices of the others don't change
+            for (index, old_item) in old_items.iter().enumerate().skip(new_items.len()).rev() {
+                with_component(pointer, &index.to_string(), |pointer| {
+                    ops.push(JsonPatchOp::Remove {
+                        path: pointer.clone(),
+                        old: old_item.clone(),
+                    })
+                });
+            }
+        }
+        _ => ops.push(JsonPatchOp::Replace {
+            path: pointer.clone(),
+            old: old.clone(),
+            value: new.clone(),
+        }),
+    }
+}
+
+/// Applies the given operations to a JSON value, in order.
+///
+/// If an operation fails, the operations before it remain applied.
+pub fn apply_json_patch(value: &mut JsonValue, ops: &[JsonPatchOp]) -> Result<(), JsonPatchError> {
+    for op in ops {
+        apply_op(value, op).ok_or_else(|| JsonPatchError {
+            path: op.path().to_owned(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Applies a single operation to a JSON value, returning [`None`] if its path is invalid.
+fn apply_op(root: &mut JsonValue, op: &JsonPatchOp) -> Option<()> {
+    let path = op.path();
+    let Some((parent, last)) = path.rsplit_once('/') else {
+        // The path refers to the root value
+        if !path.is_empty() {
+            return None;
+        }
+        match op {
+            JsonPatchOp::Add { value, .. } | JsonPatchOp::Replace { value, .. } => {
+                *root = value.clone();
+                return Some(());
+            }
+            JsonPatchOp::Remove { .. } => return None,
+        }
+    };
+    let last = last.replace("~1", "/").replace("~0", "~");
+    let mut target = root;
+    if !parent.is_empty() {
+        for component in parent.strip_prefix('/')?.split('/') {
+            let component = component.replace("~1", "/").replace("~0", "~");
+            target = match target {
+                JsonValue::Object(entries) => {
+                    let (_, value) = entries.iter_mut().find(|(key, _)| *key == component)?;
+                    value
+                }
+                JsonValue::Array(items) => items.get_mut(component.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+    }
+    match (target, op) {
+        (JsonValue::Object(entries), op) => {
+            let existing = entries.iter().position(|(key, _)| *key == last);
+            match (existing, op) {
+                (Some(index), JsonPatchOp::Add { value, .. })
+                | (Some(index), JsonPatchOp::Replace { value, .. }) => {
+                    entries[index].1 = value.clone();
+                }
+                (None, JsonPatchOp::Add { value, .. }) => entries.push((last, value.clone())),
+                (Some(index), JsonPatchOp::Remove { .. }) => {
+                    entries.remove(index);
+                }
+                (None, _) => return None,
+            }
+        }
+        (JsonValue::Array(items), JsonPatchOp::Add { value, .. }) => {
+            let index = match last.as_str() {
+                "-" => items.len(),
+                index => index.parse::<usize>().ok().filter(|&i| i <= items.len())?,
+            };
+            items.insert(index, value.clone());
+        }
+        (JsonValue::Array(items), JsonPatchOp::Remove { .. }) => {
+            let index = last.parse::<usize>().ok().filter(|&i| i < items.len())?;
+            items.remove(index);
+        }
+        (JsonValue::Array(items), JsonPatchOp::Replace { value, .. }) => {
+            *items.get_mut(last.parse::<usize>().ok()?)? = value.clone();
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// The type of error produced when a [`JsonPatchOp`] can't be applied by [`apply_json_patch`]
+/// because its path doesn't exist.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("can't apply patch at {path:?}: no such value")]
+pub struct JsonPatchError {
+    /// The path of the operation that failed.
+    pub path: String,
+}
+
+/// Keeps the most recently loaded version of a JSON file asset, deserialized into a value of type
+/// `T`, and reports what changed whenever it is reloaded, so that changes can be applied
+/// incrementally or logged, e.g. `balance change: /weapons/3/damage 10 -> 12`.
+pub struct JsonWatcher<T> {
+    asset: AssetPath,
+    value: JsonValue,
+    typed: T,
+    tracker: Tracker,
+}
+
+impl<T: for<'a> Deserialize<JsonDeserializer<'a>>> JsonWatcher<T> {
+    /// Loads the given JSON file asset, as with [`AssetPathJsonExt::load_json_value_resolved`],
+    /// and deserializes it into a value of type `T`.
+    pub fn load(asset: &AssetPath) -> AssetLoadResult<Self> {
+        let tracker = Tracker::default();
+        let value = asset.load_json_value_resolved(&tracker)?;
+        let typed = deserialize(asset, &value)?;
+        Ok(Self {
+            asset: asset.clone(),
+            value,
+            typed,
+            tracker,
+        })
+    }
+
+    /// Gets the asset this watcher loads.
+    pub fn asset(&self) -> &AssetPath {
+        &self.asset
+    }
+
+    /// Gets the most recently loaded version of the asset.
+    pub fn value(&self) -> &JsonValue {
+        &self.value
+    }
+
+    /// Gets the most recently loaded version of the asset, deserialized into a value of type `T`.
+    pub fn get(&self) -> &T {
+        &self.typed
+    }
+
+    /// Determines whether the asset, or a file it includes, has changed since it was last
+    /// loaded.
+    pub fn is_outdated(&self) -> bool {
+        !self.tracker.get().is_valid()
+    }
+
+    /// Reloads the asset if it has changed since it was last loaded, returning the operations
+    /// which transform the previous version into the new one, as computed by [`diff_json`].
+    /// This is empty if the asset hasn't changed, or if only its formatting has changed.
+    ///
+    /// If the asset fails to load or deserialize, the previous version is kept, and it won't be
+    /// reloaded until it changes again.
+    pub fn reload(&mut self) -> AssetLoadResult<Vec<JsonPatchOp>> {
+        if !self.is_outdated() {
+            return Ok(Vec::new());
+        }
+        let tracker = Tracker::default();
+        let res = self.asset.load_json_value_resolved(&tracker);
+        self.tracker = tracker;
+        let value = res?;
+        let ops = diff_json(&self.value, &value);
+        if !ops.is_empty() {
+            self.typed = deserialize(&self.asset, &value)?;
+            self.value = value;
+        }
+        Ok(ops)
+    }
+}
+
+/// Deserializes a version of the given JSON file asset into a value of type `T`.
+fn deserialize<T: for<'a> Deserialize<JsonDeserializer<'a>>>(
+    asset: &AssetPath,
+    value: &JsonValue,
+) -> AssetLoadResult<T> {
+    let config = crate::deserializer_config(&JsonLoadOptions::default_for(asset));
+    assetman::with_asset(asset, || {
+        Ok(deserialize_value(value, config, |value| value.get())?)
+    })
+}
@@ -0,0 +1,572 @@
+use crate::{AssetPathJsonExt, JsonDeserializer, JsonNumber, JsonValue};
+use assetman::{AssetLoadResult, AssetPath, Tracker};
+use serdere::Deserialize;
+
+/// A type which can describe the JSON it is deserialized from using a
+/// [JSON Schema](https://json-schema.org/). This can be derived for structs with named fields and
+/// enums with unit variants, in which case doc comments become descriptions and
+/// `#[serde(rename = "...")]` and `#[serde(default)]` attributes are respected.
+///
+/// Schemas can be given to editors to provide autocompletion, and can be checked before
+/// deserialization using [`validate_json`] to report every problem with a file at once.
+pub trait JsonSchema {
+    /// Indicates whether a property of this type may be omitted from an object.
+    const OPTIONAL: bool = false;
+
+    /// Gets the schema for values of this type.
+    fn json_schema() -> JsonValue;
+}
+
+/// Describes a property of an object schema constructed using [`object_schema`].
+pub struct SchemaProperty {
+    /// The key for the property.
+    pub name: String,
+
+    /// The schema for the value of the property.
+    pub schema: JsonValue,
+
+    /// Indicates whether the property must be present in the object.
+    pub required: bool,
+}
+
+impl SchemaProperty {
+    /// Constructs a [`SchemaProperty`] for a property of type `T`. The property is required unless
+    /// `T` is [optional](JsonSchema::OPTIONAL) or the property has a default value.
+    pub fn new<T: JsonSchema + ?Sized>(
+        name: &str,
+        description: Option<&str>,
+        has_default: bool,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            schema: with_description(T::json_schema(), description),
+            required: !(T::OPTIONAL || has_default),
+        }
+    }
+}
+
+/// Constructs a schema for an object with the given properties.
+pub fn object_schema(description: Option<&str>, properties: Vec<SchemaProperty>) -> JsonValue {
+    let required = properties
+        .iter()
+        .filter(|property| property.required)
+        .map(|property| JsonValue::String(property.name.clone()))
+        .collect::<Vec<_>>();
+    let properties = properties
+        .into_iter()
+        .map(|property| (property.name, property.schema))
+        .collect();
+    let mut entries = vec![
+        ("type".to_owned(), JsonValue::String("object".to_owned())),
+        ("properties".to_owned(), JsonValue::Object(properties)),
+    ];
+    if !required.is_empty() {
+        entries.push(("required".to_owned(), JsonValue::Array(required)));
+    }
+    with_description(JsonValue::Object(entries), description)
+}
+
+/// Constructs a schema which accepts only the given values.
+pub fn enum_schema(description: Option<&str>, values: Vec<JsonValue>) -> JsonValue {
+    let schema = JsonValue::Object(vec![("enum".to_owned(), JsonValue::Array(values))]);
+    with_description(schema, description)
+}
+
+/// Adds a `description` to a schema, if one is given and the schema is an object.
+fn with_description(mut schema: JsonValue, description: Option<&str>) -> JsonValue {
+    if let (JsonValue::Object(entries), Some(description)) = (&mut schema, description) {
+        entries.retain(|(key, _)| key != "description");
+        entries.insert(
+            0,
+            (
+                "description".to_owned(),
+                JsonValue::String(description.to_owned()),
+            ),
+        );
+    }
+    schema
+}
+
+/// Constructs a schema with only a `type` keyword.
+fn type_schema(ty: &str) -> JsonValue {
+    JsonValue::Object(vec![("type".to_owned(), JsonValue::String(ty.to_owned()))])
+}
+
+/// Constructs a schema for an array with items of type `T`.
+fn array_schema<T: JsonSchema + ?Sized>() -> JsonValue {
+    JsonValue::Object(vec![
+        ("type".to_owned(), JsonValue::String("array".to_owned())),
+        ("items".to_owned(), T::json_schema()),
+    ])
+}
+
+impl JsonSchema for bool {
+    fn json_schema() -> JsonValue {
+        type_schema("boolean")
+    }
+}
+
+macro_rules! impl_int_schema {
+    ($($ty:ty),*) => {
+        $(impl JsonSchema for $ty {
+            fn json_schema() -> JsonValue {
+                JsonValue::Object(vec![
+                    ("type".to_owned(), JsonValue::String("integer".to_owned())),
+                    ("minimum".to_owned(), JsonValue::from(<$ty>::MIN)),
+                    ("maximum".to_owned(), JsonValue::from(<$ty>::MAX)),
+                ])
+            }
+        })*
+    };
+}
+
+impl_int_schema!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl JsonSchema for f32 {
+    fn json_schema() -> JsonValue {
+        type_schema("number")
+    }
+}
+
+impl JsonSchema for f64 {
+    fn json_schema() -> JsonValue {
+        type_schema("number")
+    }
+}
+
+impl JsonSchema for str {
+    fn json_schema() -> JsonValue {
+        type_schema("string")
+    }
+}
+
+impl JsonSchema for String {
+    fn json_schema() -> JsonValue {
+        type_schema("string")
+    }
+}
+
+impl JsonSchema for AssetPath {
+    fn json_schema() -> JsonValue {
+        with_description(
+            type_schema("string"),
+            Some("A path to an asset, relative to the directory containing this file."),
+        )
+    }
+}
+
+impl JsonSchema for JsonValue {
+    fn json_schema() -> JsonValue {
+        JsonValue::Object(Vec::new())
+    }
+}
+
+impl<T: JsonSchema + ?Sized> JsonSchema for Box<T> {
+    const OPTIONAL: bool = T::OPTIONAL;
+    fn json_schema() -> JsonValue {
+        T::json_schema()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    const OPTIONAL: bool = true;
+    fn json_schema() -> JsonValue {
+        JsonValue::Object(vec![(
+            "anyOf".to_owned(),
+            JsonValue::Array(vec![T::json_schema(), type_schema("null")]),
+        )])
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for [T] {
+    fn json_schema() -> JsonValue {
+        array_schema::<T>()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> JsonValue {
+        array_schema::<T>()
+    }
+}
+
+impl<T: JsonSchema, const N: usize> JsonSchema for [T; N] {
+    fn json_schema() -> JsonValue {
+        let JsonValue::Object(mut entries) = array_schema::<T>() else {
+            unreachable!()
+        };
+        entries.push(("minItems".to_owned(), JsonValue::from(N)));
+        entries.push(("maxItems".to_owned(), JsonValue::from(N)));
+        JsonValue::Object(entries)
+    }
+}
+
+/// Describes a way in which a [`JsonValue`] does not conform to a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// The [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) for the offending value. This
+    /// is empty for the root value.
+    pub pointer: String,
+
+    /// Describes the problem with the value.
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pointer.as_str() {
+            "" => write!(f, "(root): {}", self.message),
+            pointer => write!(f, "{}: {}", pointer, self.message),
+        }
+    }
+}
+
+/// The type of error produced when a JSON file does not conform to a schema.
+#[derive(thiserror::Error, Debug)]
+#[error("JSON does not conform to schema:{}", display_violations(.0))]
+pub struct JsonSchemaError(pub Vec<SchemaViolation>);
+
+/// Formats the violations in a [`JsonSchemaError`] for display, one per line.
+fn display_violations(violations: &[SchemaViolation]) -> String {
+    let mut res = String::new();
+    for violation in violations {
+        res.push_str(&format!("\n  {}", violation));
+    }
+    res
+}
+
+/// Checks a [`JsonValue`] against a [JSON Schema](https://json-schema.org/), returning every
+/// violation found.
+///
+/// This supports the `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+/// `items`, `minItems`, `maxItems`, `minimum`, `maximum`, `minLength`, `maxLength`, `anyOf` and
+/// `allOf` keywords, which includes every keyword used by derived [`JsonSchema`]s. Other keywords
+/// are ignored.
+pub fn validate_json(schema: &JsonValue, value: &JsonValue) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    validate_at(schema, value, &mut String::new(), &mut violations);
+    violations
+}
+
+/// Checks a [`JsonValue`] at the given pointer against a schema, appending any violations found.
+fn validate_at(
+    schema: &JsonValue,
+    value: &JsonValue,
+    pointer: &mut String,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let JsonValue::Object(_) = schema else {
+        if let JsonValue::Bool(false) = schema {
+            violate(violations, pointer, "no value is allowed here".to_owned());
+        }
+        return;
+    };
+    if !type_matches(schema, value) {
+        let ty = schema.get("type").unwrap();
+        violate(
+            violations,
+            pointer,
+            format!("expected {}, found {}", display_types(ty), type_name(value)),
+        );
+        return;
+    }
+    if let Some(JsonValue::Array(values)) = schema.get("enum") {
+        if !values.contains(value) {
+            let values = values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+            violate(
+                violations,
+                pointer,
+                format!("expected one of {}, found {}", values.join(", "), value),
+            );
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            violate(
+                violations,
+                pointer,
+                format!("expected {}, found {}", expected, value),
+            );
+        }
+    }
+    match value {
+        JsonValue::Number(num) => {
+            if let Some(JsonValue::Number(min)) = schema.get("minimum") {
+                if num < min {
+                    violate(violations, pointer, format!("{} is less than {}", num, min));
+                }
+            }
+            if let Some(JsonValue::Number(max)) = schema.get("maximum") {
+                if num > max {
+                    violate(
+                        violations,
+                        pointer,
+                        format!("{} is greater than {}", num, max),
+                    );
+                }
+            }
+        }
+        JsonValue::String(text) => {
+            let len = text.chars().count();
+            check_len(
+                schema,
+                "minLength",
+                "maxLength",
+                len,
+                "characters",
+                pointer,
+                violations,
+            );
+        }
+        JsonValue::Array(items) => {
+            check_len(
+                schema,
+                "minItems",
+                "maxItems",
+                items.len(),
+                "items",
+                pointer,
+                violations,
+            );
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    with_component(pointer, &i.to_string(), |pointer| {
+                        validate_at(item_schema, item, pointer, violations)
+                    });
+                }
+            }
+        }
+        JsonValue::Object(entries) => {
+            if let Some(JsonValue::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(JsonValue::as_str) {
+                    if value.get(name).is_none() {
+                        violate(
+                            violations,
+                            pointer,
+                            format!("missing required property {:?}", name),
+                        );
+                    }
+                }
+            }
+            let properties = schema.get("properties");
+            let additional = schema.get("additionalProperties");
+            for (key, item) in entries {
+                let item_schema = match properties.and_then(|p| p.get(key)) {
+                    Some(item_schema) => item_schema,
+                    None => match additional {
+                        Some(item_schema) => item_schema,
+                        None => continue,
+                    },
+                };
+                with_component(pointer, key, |pointer| {
+                    if let JsonValue::Bool(false) = item_schema {
+                        violate(violations, pointer, "unexpected property".to_owned());
+                    } else {
+                        validate_at(item_schema, item, pointer, violations)
+                    }
+                });
+            }
+        }
+        JsonValue::Null | JsonValue::Bool(_) => {}
+    }
+    if let Some(JsonValue::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            validate_at(schema, value, pointer, violations);
+        }
+    }
+    if let Some(JsonValue::Array(schemas)) = schema.get("anyOf") {
+        let mut plausible = Vec::new();
+        let mut matched = false;
+        for schema in schemas {
+            let mut inner = Vec::new();
+            validate_at(schema, value, pointer, &mut inner);
+            if inner.is_empty() {
+                matched = true;
+                break;
+            }
+            if type_matches(schema, value) {
+                plausible.push(inner);
+            }
+        }
+        if !matched {
+            match <[_; 1]>::try_from(plausible) {
+                // Only one alternative accepts this type of value, so its violations are the most
+                // helpful
+                Ok([inner]) => violations.extend(inner),
+                Err(_) => violate(
+                    violations,
+                    pointer,
+                    format!("{} does not match any allowed schema", type_name(value)),
+                ),
+            }
+        }
+    }
+}
+
+/// Checks the length of a string or array against the given minimum and maximum keywords.
+fn check_len(
+    schema: &JsonValue,
+    min_key: &str,
+    max_key: &str,
+    len: usize,
+    unit: &str,
+    pointer: &str,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    if let Some(JsonValue::Number(min)) = schema.get(min_key) {
+        if JsonNumber::from(len) < *min {
+            let message = format!("expected at least {} {}, found {}", min, unit, len);
+            violate(violations, pointer, message);
+        }
+    }
+    if let Some(JsonValue::Number(max)) = schema.get(max_key) {
+        if JsonNumber::from(len) > *max {
+            let message = format!("expected at most {} {}, found {}", max, unit, len);
+            violate(violations, pointer, message);
+        }
+    }
+}
+
+/// Records a [`SchemaViolation`] for the value at the given pointer.
+fn violate(violations: &mut Vec<SchemaViolation>, pointer: &str, message: String) {
+    violations.push(SchemaViolation {
+        pointer: pointer.to_owned(),
+        message,
+    });
+}
+
+/// Calls `f` with the given component temporarily appended to a JSON pointer.
+pub(crate) fn with_component(pointer: &mut String, component: &str, f: impl FnOnce(&mut String)) {
+    let len = pointer.len();
+    pointer.push('/');
+    pointer.push_str(&component.replace('~', "~0").replace('/', "~1"));
+    f(pointer);
+    pointer.truncate(len);
+}
+
+/// Determines whether a value satisfies the `type` keyword of a schema, if it has one.
+fn type_matches(schema: &JsonValue, value: &JsonValue) -> bool {
+    let matches = |ty: &JsonValue| match (ty.as_str(), value) {
+        (Some("integer"), JsonValue::Number(num)) => num.as_f64().fract() == 0.0,
+        (Some("number"), JsonValue::Number(_)) => true,
+        (Some(ty), value) => ty == type_name(value),
+        (None, _) => false,
+    };
+    match schema.get("type") {
+        None => true,
+        Some(JsonValue::Array(types)) => types.iter().any(matches),
+        Some(ty) => matches(ty),
+    }
+}
+
+/// Describes the value of the `type` keyword of a schema.
+fn display_types(ty: &JsonValue) -> String {
+    match ty {
+        JsonValue::Array(types) => {
+            let types = types
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .collect::<Vec<_>>();
+            types.join(" or ")
+        }
+        ty => ty.as_str().unwrap_or("?").to_owned(),
+    }
+}
+
+/// Gets the name of the JSON Schema type of a value.
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// A named collection of [`JsonSchema`]s, such as the config types of an application, which can
+/// be exported for use by editors.
+#[derive(Debug, Clone, Default)]
+pub struct JsonSchemaRegistry {
+    schemas: Vec<RegisteredSchema>,
+}
+
+/// A schema in a [`JsonSchemaRegistry`].
+#[derive(Debug, Clone)]
+struct RegisteredSchema {
+    name: String,
+    schema: JsonValue,
+
+    /// Loads a JSON file asset as the type that the schema was registered for, if it was
+    /// registered using [`JsonSchemaRegistry::register_loadable`].
+    load: Option<fn(&AssetPath, &Tracker) -> AssetLoadResult<()>>,
+}
+
+impl JsonSchemaRegistry {
+    /// Adds the schema for type `T` to this registry under the given name.
+    pub fn register<T: JsonSchema + ?Sized>(&mut self, name: &str) -> &mut Self {
+        let mut schema = T::json_schema();
+        if let JsonValue::Object(entries) = &mut schema {
+            entries.insert(
+                0,
+                (
+                    "$schema".to_owned(),
+                    JsonValue::String("https://json-schema.org/draft/2020-12/schema".to_owned()),
+                ),
+            );
+            entries.insert(1, ("title".to_owned(), JsonValue::String(name.to_owned())));
+        }
+        self.schemas.retain(|other| other.name != name);
+        self.schemas.push(RegisteredSchema {
+            name: name.to_owned(),
+            schema,
+            load: None,
+        });
+        self
+    }
+
+    /// Adds the schema for type `T` to this registry under the given name, as with
+    /// [`JsonSchemaRegistry::register`], and allows JSON files to be validated by loading them
+    /// as `T` using [`JsonSchemaRegistry::load`].
+    pub fn register_loadable<T: JsonSchema + for<'a> Deserialize<JsonDeserializer<'a>>>(
+        &mut self,
+        name: &str,
+    ) -> &mut Self {
+        self.register::<T>(name);
+        self.schemas.last_mut().unwrap().load = Some(|asset, tracker| {
+            asset.load_json_validated::<T>(tracker)?;
+            Ok(())
+        });
+        self
+    }
+
+    /// Gets the schema registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&JsonValue> {
+        self.schemas
+            .iter()
+            .find(|other| other.name == name)
+            .map(|registered| &registered.schema)
+    }
+
+    /// Loads a JSON file asset as the type registered under the given name using
+    /// [`JsonSchemaRegistry::register_loadable`], exactly as
+    /// [`AssetPathJsonExt::load_json_validated`] would, and discards the result. Returns [`None`]
+    /// if no such type is registered.
+    pub fn load(
+        &self,
+        name: &str,
+        asset: &AssetPath,
+        tracker: &Tracker,
+    ) -> Option<AssetLoadResult<()>> {
+        let registered = self.schemas.iter().find(|other| other.name == name)?;
+        Some(registered.load?(asset, tracker))
+    }
+
+    /// Iterates over the names and schemas in this registry, in order of registration.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &JsonValue)> {
+        self.schemas
+            .iter()
+            .map(|registered| (registered.name.as_str(), &registered.schema))
+    }
+}
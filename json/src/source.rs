@@ -0,0 +1,309 @@
+use crate::cooked::{CookedJson, CookedProgress, CookedText};
+use crate::value::write_string;
+use crate::{context, JsonDeserializer, JsonDeserializerError, JsonError, JsonValue};
+use assetman::{AssetLoadError, AssetLoadInnerError, AssetLoadResult, AssetPath};
+use serdere::{Utf8Reader, Value};
+use serdere_json::{TextDeserializer, TextDeserializerConfig};
+use std::cell::Cell;
+
+/// Deserializes JSON text using a deserializer interface. If an error occurs, it is returned along
+/// with the number of bytes of the text which had been consumed, so that it can be located.
+///
+/// `dirs` gives the offsets in the text where the file it was copied from changes, along with
+/// the directory containing that file, or [`None`] for the file being loaded.
+pub(crate) fn deserialize_text<R>(
+    source: &[u8],
+    dirs: &[(usize, Option<AssetPath>)],
+    config: TextDeserializerConfig,
+    f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+) -> Result<R, (usize, AssetLoadInnerError)> {
+    deserialize_source(SourceText::Bytes(source), dirs, config, f)
+}
+
+/// Deserializes a [`JsonValue`] using a deserializer interface, without formatting it first.
+/// Errors are located in the value's text as formatted by
+/// [`AssetPathJsonExt::save_json`](crate::AssetPathJsonExt::save_json).
+pub(crate) fn deserialize_value<R>(
+    value: &JsonValue,
+    config: TextDeserializerConfig,
+    f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+) -> Result<R, JsonError> {
+    deserialize_source(SourceText::Value(ValueText::new(value)), &[], config, f).map_err(
+        |(consumed, inner)| {
+            // The text is only needed to locate the error, so it is only formatted now
+            let text = format!("{:#}", value);
+            JsonError::new(text.as_bytes(), consumed, inner)
+        },
+    )
+}
+
+/// Deserializes the cooked sibling of the given JSON file asset using a deserializer interface.
+/// The deserializer reads text generated from the cooked tokens as it goes (see [`CookedText`]),
+/// so the value is never decoded or formatted in full.
+///
+/// Errors are attributed to the JSON file asset and located in the generated text, except for
+/// errors in the cooked data itself, which are attributed to the cooked sibling.
+pub(crate) fn deserialize_cooked<R>(
+    asset: &AssetPath,
+    cooked: &CookedJson,
+    config: TextDeserializerConfig,
+    f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+) -> AssetLoadResult<R> {
+    let progress = CookedProgress::default();
+    let text = SourceText::Cooked(cooked.text(&progress));
+    deserialize_source(text, &[], config, f).map_err(|(consumed, inner)| {
+        match progress.take_error() {
+            Some(err) => AssetLoadError {
+                asset: cooked.asset().clone(),
+                inner: err.into(),
+            },
+            None => AssetLoadError {
+                asset: asset.clone(),
+                inner: progress.json_error(consumed, inner).into(),
+            },
+        }
+    })
+}
+
+/// Deserializes the given text using a deserializer interface. See [`deserialize_text`].
+fn deserialize_source<R>(
+    text: SourceText,
+    dirs: &[(usize, Option<AssetPath>)],
+    config: TextDeserializerConfig,
+    f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+) -> Result<R, (usize, AssetLoadInnerError)> {
+    let consumed = Cell::new(0);
+    let source = JsonSource {
+        text,
+        consumed: &consumed,
+        dirs,
+        dir: 0,
+    };
+    let outer = context::enter();
+    let res = deserialize_with(source, config, f);
+    context::exit(outer);
+    res.map_err(|err| (consumed.get(), err))
+}
+
+/// Deserializes the text from the given source using a deserializer interface.
+fn deserialize_with<R>(
+    source: JsonSource,
+    config: TextDeserializerConfig,
+    f: impl FnOnce(Value<JsonDeserializer>) -> Result<R, JsonDeserializerError>,
+) -> Result<R, AssetLoadInnerError> {
+    let reader = Utf8Reader::new(source)?;
+    let mut deserializer = TextDeserializer::new(config, reader)?;
+    Ok(Value::with(&mut deserializer, f)?)
+}
+
+/// Produces an error from a [`JsonDeserializer`], for stopping deserialization after a failure
+/// which the deserializer has no way to report. The failure is kept elsewhere, such as in the
+/// [`JsonAssetContext`](crate::JsonAssetContext), to be reported in place of the error.
+pub(crate) fn abort_error() -> JsonDeserializerError {
+    let consumed = Cell::new(0);
+    let source = JsonSource {
+        text: SourceText::Bytes(b"null"),
+        consumed: &consumed,
+        dirs: &[],
+        dir: 0,
+    };
+    let reader = Utf8Reader::new(source).expect("valid UTF-8");
+    TextDeserializer::new(TextDeserializerConfig::strict(), reader)
+        .and_then(|mut deserializer| Value::with(&mut deserializer, |value| value.get::<String>()))
+        .expect_err("null is not a string")
+}
+
+/// The source text for a [`JsonDeserializer`], which keeps track of how much of the text has been
+/// consumed so that errors can be located.
+pub struct JsonSource<'a> {
+    text: SourceText<'a>,
+    consumed: &'a Cell<usize>,
+
+    /// The offsets in the text where the file it was copied from changes, along with the
+    /// directory containing that file. See [`deserialize_text`]. For cooked JSON, these are
+    /// given by the [`CookedText`] instead.
+    dirs: &'a [(usize, Option<AssetPath>)],
+
+    /// The index in `dirs` for the last byte consumed.
+    dir: usize,
+}
+
+/// The text provided by a [`JsonSource`].
+enum SourceText<'a> {
+    /// Text which is already in memory.
+    Bytes(&'a [u8]),
+
+    /// The text of a [`JsonValue`], generated as it is read.
+    Value(ValueText<'a>),
+
+    /// Text generated from the tokens of cooked JSON as it is read.
+    Cooked(CookedText<'a>),
+}
+
+impl JsonSource<'_> {
+    /// Marks the given number of bytes as consumed, updating the directory that paths are resolved
+    /// relative to if the file the text was copied from changes.
+    fn advance(&mut self, amt: usize) {
+        let consumed = self.consumed.get() + amt;
+        self.consumed.set(consumed);
+        let dirs = match &self.text {
+            SourceText::Cooked(text) => text.dirs(),
+            _ => self.dirs,
+        };
+        let dir = self.dir;
+        while self.dir + 1 < dirs.len() && dirs[self.dir + 1].0 < consumed {
+            self.dir += 1;
+        }
+        if self.dir != dir {
+            context::set_include_dir(dirs[self.dir].1.clone());
+        }
+    }
+}
+
+impl std::io::Read for JsonSource<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = std::io::Read::read(&mut std::io::BufRead::fill_buf(self)?, buf)?;
+        std::io::BufRead::consume(self, len);
+        Ok(len)
+    }
+}
+
+impl std::io::BufRead for JsonSource<'_> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(match &mut self.text {
+            SourceText::Bytes(source) => &source[self.consumed.get()..],
+            SourceText::Value(text) => text.fill_buf(),
+            SourceText::Cooked(text) => text.fill_buf()?,
+        })
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match &mut self.text {
+            SourceText::Bytes(_) => {}
+            SourceText::Value(text) => text.pos += amt,
+            SourceText::Cooked(text) => text.consume(amt),
+        }
+        self.advance(amt);
+    }
+}
+
+/// Generates the text of a [`JsonValue`] a piece at a time, exactly as it is formatted by
+/// [`AssetPathJsonExt::save_json`](crate::AssetPathJsonExt::save_json).
+struct ValueText<'a> {
+    /// The most recently generated piece of text.
+    chunk: String,
+
+    /// The number of bytes of `chunk` which have been consumed.
+    pos: usize,
+
+    /// The values, and the remaining items of the arrays and objects, whose text has yet to be
+    /// generated, innermost last.
+    stack: Vec<Pending<'a>>,
+}
+
+/// Part of a [`JsonValue`] whose text has yet to be generated by a [`ValueText`]. Each has the
+/// indentation level of its container.
+enum Pending<'a> {
+    Value(&'a JsonValue, usize),
+    Items(std::slice::Iter<'a, JsonValue>, usize, bool),
+    Entries(std::slice::Iter<'a, (String, JsonValue)>, usize, bool),
+}
+
+impl<'a> ValueText<'a> {
+    /// Constructs a [`ValueText`] for the given value.
+    fn new(value: &'a JsonValue) -> Self {
+        Self {
+            chunk: String::new(),
+            pos: 0,
+            stack: vec![Pending::Value(value, 0)],
+        }
+    }
+
+    /// Gets the text which hasn't been consumed yet, generating the next piece if all of the
+    /// current piece has been consumed. This is empty at the end of the text.
+    fn fill_buf(&mut self) -> &[u8] {
+        if self.pos == self.chunk.len() {
+            self.chunk.clear();
+            self.pos = 0;
+            while self.chunk.is_empty() {
+                let Some(pending) = self.stack.pop() else {
+                    break;
+                };
+                self.generate(pending);
+            }
+        }
+        &self.chunk.as_bytes()[self.pos..]
+    }
+
+    /// Generates the next piece of text for the given pending part of the value.
+    fn generate(&mut self, pending: Pending<'a>) {
+        let chunk = &mut self.chunk;
+        match pending {
+            Pending::Value(value, indent) => match value {
+                JsonValue::Null => chunk.push_str("null"),
+                JsonValue::Bool(value) => chunk.push_str(if *value { "true" } else { "false" }),
+                JsonValue::Number(value) => chunk.push_str(value.as_str()),
+                JsonValue::String(value) => write_string(chunk, value).unwrap(),
+                JsonValue::Array(items) => {
+                    chunk.push('[');
+                    self.stack.push(Pending::Items(items.iter(), indent, true));
+                }
+                JsonValue::Object(entries) => {
+                    chunk.push('{');
+                    self.stack
+                        .push(Pending::Entries(entries.iter(), indent, true));
+                }
+            },
+            Pending::Items(mut items, indent, first) => match items.next() {
+                Some(item) => {
+                    push_separator(chunk, first, indent);
+                    self.stack.push(Pending::Items(items, indent, false));
+                    self.stack.push(Pending::Value(item, indent + 1));
+                }
+                None => {
+                    push_end(chunk, first, indent);
+                    chunk.push(']');
+                }
+            },
+            Pending::Entries(mut entries, indent, first) => match entries.next() {
+                Some((key, value)) => {
+                    push_separator(chunk, first, indent);
+                    write_string(chunk, key).unwrap();
+                    chunk.push_str(": ");
+                    self.stack.push(Pending::Entries(entries, indent, false));
+                    self.stack.push(Pending::Value(value, indent + 1));
+                }
+                None => {
+                    push_end(chunk, first, indent);
+                    chunk.push('}');
+                }
+            },
+        }
+    }
+}
+
+/// Appends the separator preceding an item in an array or object with the given indentation
+/// level.
+fn push_separator(chunk: &mut String, first: bool, indent: usize) {
+    if !first {
+        chunk.push(',');
+    }
+    chunk.push('\n');
+    push_tabs(chunk, indent + 1);
+}
+
+/// Appends the whitespace preceding the closing bracket of an array or object with the given
+/// indentation level.
+fn push_end(chunk: &mut String, empty: bool, indent: usize) {
+    if !empty {
+        chunk.push('\n');
+        push_tabs(chunk, indent);
+    }
+}
+
+/// Appends the given number of tabs.
+fn push_tabs(chunk: &mut String, count: usize) {
+    for _ in 0..count {
+        chunk.push('\t');
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{deserialize_json, JsonDeserializer, JsonLoadOptions, JsonParseError, JsonValue};
+use assetman::{AssetLoadError, AssetLoadInnerError, AssetLoadResult, AssetPath, AssetReader};
+use serdere::Deserialize;
+use std::io::BufRead;
+use std::marker::PhantomData;
+
+/// Splits the elements of a JSON array out of a byte stream, one at a time, without reading the
+/// rest of the stream into memory.
+///
+/// Elements are only checked for balanced brackets and terminated strings. Their contents are
+/// checked when they are deserialized.
+pub(crate) struct ArrayScanner<R> {
+    reader: R,
+    options: JsonLoadOptions,
+
+    /// The line of the next byte in the stream, starting at 1.
+    line: usize,
+
+    /// The column of the next byte in the stream, in characters, starting at 1.
+    column: usize,
+
+    /// The index of the next element of the array.
+    index: usize,
+
+    /// Indicates whether the end of the array has been reached, or an error has occurred.
+    done: bool,
+
+    /// If capturing, the bytes consumed since capturing started.
+    capture: Option<Vec<u8>>,
+}
+
+/// An element of a JSON array, as produced by [`ArrayScanner::next_element`].
+pub(crate) struct ArrayElement {
+    /// The index of the element in the array.
+    pub index: usize,
+
+    /// The line where the element starts.
+    pub line: usize,
+
+    /// The JSON text of the element.
+    pub text: Vec<u8>,
+}
+
+impl<R: BufRead> ArrayScanner<R> {
+    /// Constructs a scanner for the given stream, which must be positioned at the start of a
+    /// JSON document.
+    pub(crate) fn new(reader: R, options: JsonLoadOptions) -> Self {
+        Self {
+            reader,
+            options,
+            line: 1,
+            column: 1,
+            index: 0,
+            done: false,
+            capture: None,
+        }
+    }
+
+    /// Constructs an error at the current position of the scanner.
+    fn error(&self, message: &str) -> AssetLoadInnerError {
+        JsonParseError {
+            line: self.line,
+            column: self.column,
+            message: message.to_owned(),
+        }
+        .into()
+    }
+
+    /// Gets the next byte in the stream without consuming it.
+    fn peek(&mut self) -> Result<Option<u8>, AssetLoadInnerError> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    /// Consumes the next byte in the stream.
+    fn next(&mut self) -> Result<Option<u8>, AssetLoadInnerError> {
+        let Some(byte) = self.peek()? else {
+            return Ok(None);
+        };
+        self.reader.consume(1);
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if byte & 0xc0 != 0x80 {
+            // Continuation bytes of multi-byte characters don't start a new column
+            self.column += 1;
+        }
+        if let Some(capture) = &mut self.capture {
+            capture.push(byte);
+        }
+        Ok(Some(byte))
+    }
+
+    /// Consumes the given byte, or returns an error if it is not next.
+    fn expect(&mut self, expected: u8) -> Result<(), AssetLoadInnerError> {
+        if self.peek()? == Some(expected) {
+            self.next()?;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected as char)))
+        }
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<(), AssetLoadInnerError> {
+        loop {
+            match self.peek()? {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => {
+                    self.next()?;
+                }
+                Some(b'/') => {
+                    if !self.options.allow_comments {
+                        return Err(self.error("comments are not allowed"));
+                    }
+                    self.next()?;
+                    match self.next()? {
+                        Some(b'/') => while !matches!(self.next()?, Some(b'\n') | None) {},
+                        Some(b'*') => loop {
+                            match self.next()? {
+                                Some(b'*') if self.peek()? == Some(b'/') => {
+                                    self.next()?;
+                                    break;
+                                }
+                                Some(_) => {}
+                                None => return Err(self.error("unterminated comment")),
+                            }
+                        },
+                        _ => return Err(self.error("unexpected character")),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips the remainder of a string, after its opening quote.
+    fn skip_string(&mut self) -> Result<(), AssetLoadInnerError> {
+        loop {
+            match self.next()? {
+                Some(b'"') => return Ok(()),
+                Some(b'\\') => {
+                    self.next()?;
+                }
+                Some(b'\n') | None => return Err(self.error("unterminated string")),
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// Skips a value, including any leading whitespace.
+    fn skip_value(&mut self) -> Result<(), AssetLoadInnerError> {
+        self.skip_whitespace()?;
+        let mut depth = 0usize;
+        loop {
+            match self.peek()? {
+                Some(b'"') => {
+                    self.next()?;
+                    self.skip_string()?;
+                }
+                Some(b'[' | b'{') => {
+                    self.next()?;
+                    depth += 1;
+                }
+                Some(b']' | b'}') if depth > 0 => {
+                    self.next()?;
+                    depth -= 1;
+                }
+                Some(b',' | b':') if depth > 0 => {
+                    self.next()?;
+                }
+                Some(b' ' | b'\t' | b'\n' | b'\r' | b'/') if depth > 0 => self.skip_whitespace()?,
+                Some(byte) if !matches!(byte, b',' | b']' | b'}' | b':') => {
+                    // A literal, which ends at the next delimiter
+                    while let Some(byte) = self.peek()? {
+                        if matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'/')
+                            || matches!(byte, b',' | b':' | b'[' | b']' | b'{' | b'}' | b'"')
+                        {
+                            break;
+                        }
+                        self.next()?;
+                    }
+                }
+                Some(_) => return Err(self.error("unexpected character")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads an object key, including any leading whitespace.
+    fn read_key(&mut self) -> Result<String, AssetLoadInnerError> {
+        self.skip_whitespace()?;
+        if self.peek()? != Some(b'"') {
+            return Err(self.error("expected a key"));
+        }
+        let (line, column) = (self.line, self.column);
+        self.capture = Some(Vec::new());
+        let res = self.next().and_then(|_| self.skip_string());
+        let text = self.capture.take().unwrap();
+        res?;
+        match std::str::from_utf8(&text).ok().map(JsonValue::parse) {
+            Some(Ok(JsonValue::String(key))) => Ok(key),
+            _ => Err(JsonParseError {
+                line,
+                column,
+                message: "invalid key".to_owned(),
+            }
+            .into()),
+        }
+    }
+
+    /// Skips a comma or the given closing bracket following an item in an array or object,
+    /// returning `false` if the closing bracket was reached.
+    fn separator(&mut self, close: u8) -> Result<bool, AssetLoadInnerError> {
+        self.skip_whitespace()?;
+        match self.next()? {
+            Some(b',') => {
+                self.skip_whitespace()?;
+                if self.peek()? == Some(close) {
+                    if !self.options.allow_trailing_commas {
+                        return Err(self.error("trailing commas are not allowed"));
+                    }
+                    self.next()?;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Some(byte) if byte == close => Ok(false),
+            _ => Err(self.error(&format!("expected ',' or '{}'", close as char))),
+        }
+    }
+
+    /// Consumes the stream up to the start of the elements of the array at the given
+    /// [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901), which is empty for the root value.
+    pub(crate) fn seek_array(&mut self, pointer: &str) -> Result<(), AssetLoadInnerError> {
+        let missing = || -> AssetLoadInnerError { format!("no value at {:?}", pointer).into() };
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(missing());
+        }
+        for component in pointer.split('/').skip(1) {
+            let component = component.replace("~1", "/").replace("~0", "~");
+            self.skip_whitespace()?;
+            match self.next()? {
+                Some(b'{') => {
+                    self.skip_whitespace()?;
+                    if self.peek()? == Some(b'}') {
+                        return Err(missing());
+                    }
+                    loop {
+                        let key = self.read_key()?;
+                        self.skip_whitespace()?;
+                        self.expect(b':')?;
+                        if key == component {
+                            break;
+                        }
+                        self.skip_value()?;
+                        if !self.separator(b'}')? {
+                            return Err(missing());
+                        }
+                    }
+                }
+                Some(b'[') => {
+                    let index = component.parse::<usize>().map_err(|_| missing())?;
+                    self.skip_whitespace()?;
+                    if self.peek()? == Some(b']') {
+                        return Err(missing());
+                    }
+                    for _ in 0..index {
+                        self.skip_value()?;
+                        if !self.separator(b']')? {
+                            return Err(missing());
+                        }
+                    }
+                }
+                _ => return Err(missing()),
+            }
+        }
+        self.skip_whitespace()?;
+        if self.peek()? != Some(b'[') {
+            return Err(self.error("expected an array"));
+        }
+        self.next()?;
+        self.skip_whitespace()?;
+        if self.peek()? == Some(b']') {
+            self.next()?;
+            self.done = true;
+        }
+        Ok(())
+    }
+
+    /// Reads the next element of the array, after [`ArrayScanner::seek_array`], returning
+    /// [`None`] at the end of the array. After an error, no further elements are returned.
+    pub(crate) fn next_element(&mut self) -> Result<Option<ArrayElement>, AssetLoadInnerError> {
+        if self.done {
+            return Ok(None);
+        }
+        let res = (|| -> Result<ArrayElement, AssetLoadInnerError> {
+            self.skip_whitespace()?;
+            let line = self.line;
+            self.capture = Some(Vec::new());
+            let res = self.skip_value();
+            let text = self.capture.take().unwrap();
+            res?;
+            let element = ArrayElement {
+                index: self.index,
+                line,
+                text,
+            };
+            self.index += 1;
+            self.done = !self.separator(b']')?;
+            Ok(element)
+        })();
+        if res.is_err() {
+            self.done = true;
+        }
+        res.map(Some)
+    }
+
+    /// Gets the index of the next element of the array.
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// An iterator over the elements of a JSON array in a file asset, deserializing each into a
+/// value of type `T`, as returned by
+/// [`AssetPathJsonExt::stream_json_array`](crate::AssetPathJsonExt::stream_json_array).
+///
+/// Only one element is held in memory at a time. Errors for an element are reported as a
+/// [`JsonElementError`]. If an element can't be deserialized, iteration may continue with the
+/// next element, but a syntax error ends the iteration.
+pub struct JsonArrayStream<T> {
+    asset: AssetPath,
+    scanner: ArrayScanner<std::io::BufReader<Box<dyn AssetReader>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonArrayStream<T> {
+    /// Opens a stream over the elements of the array at the given JSON pointer in the given
+    /// asset.
+    pub(crate) fn open(
+        asset: &AssetPath,
+        reader: Box<dyn AssetReader>,
+        options: JsonLoadOptions,
+        pointer: &str,
+    ) -> AssetLoadResult<Self> {
+        let reader = std::io::BufReader::new(reader);
+        let mut scanner = ArrayScanner::new(reader, options);
+        assetman::with_asset(asset, || scanner.seek_array(pointer))?;
+        Ok(Self {
+            asset: asset.clone(),
+            scanner,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: for<'a> Deserialize<JsonDeserializer<'a>>> Iterator for JsonArrayStream<T> {
+    type Item = AssetLoadResult<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.scanner.index();
+        let line = self.scanner.line;
+        let res = self.scanner.next_element().and_then(|element| {
+            let Some(element) = element else {
+                return Ok(None);
+            };
+            let config = crate::deserializer_config(&self.scanner.options);
+            deserialize_json(&element.text, config, |value| value.get())
+                .map(Some)
+                .map_err(|err| {
+                    JsonElementError {
+                        index: element.index,
+                        line: element.line,
+                        inner: err.into(),
+                    }
+                    .into()
+                })
+        });
+        match res {
+            Ok(value) => value.map(Ok),
+            Err(inner) => {
+                // Syntax errors found by the scanner are attributed to the element being read
+                let inner = match inner.downcast::<JsonElementError>() {
+                    Ok(inner) => inner,
+                    Err(inner) => Box::new(JsonElementError { index, line, inner }),
+                };
+                Some(Err(AssetLoadError {
+                    asset: self.asset.clone(),
+                    inner,
+                }))
+            }
+        }
+    }
+}
+
+/// The type of error produced when an element of a JSON array streamed using
+/// [`AssetPathJsonExt::stream_json_array`](crate::AssetPathJsonExt::stream_json_array) fails to
+/// load.
+#[derive(thiserror::Error, Debug)]
+#[error("element {index} (starting at line {line}): {inner}")]
+pub struct JsonElementError {
+    /// The index of the element in the array.
+    pub index: usize,
+
+    /// The line in the file where the element starts, starting at 1.
+    pub line: usize,
+
+    /// Describes the error. For a [`JsonError`](crate::JsonError), the location is relative to
+    /// the text of the element.
+    #[source]
+    pub inner: AssetLoadInnerError,
+}
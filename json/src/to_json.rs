@@ -0,0 +1,108 @@
+use crate::JsonValue;
+use std::collections::{BTreeMap, HashMap};
+
+/// A type which can be converted into a [`JsonValue`], in the same format it is deserialized from
+/// by [`AssetPathJsonExt::load_json`](crate::AssetPathJsonExt::load_json). This is used to save
+/// typed values with [`AssetPathJsonExt::save_json`](crate::AssetPathJsonExt::save_json).
+///
+/// This can be derived using `#[derive(ToJson)]` for the same types as
+/// [`JsonSchema`](crate::JsonSchema).
+pub trait ToJson {
+    /// Converts this value into a [`JsonValue`].
+    fn to_json(&self) -> JsonValue;
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Bool(*self)
+    }
+}
+
+macro_rules! impl_number_to_json {
+    ($($ty:ty),*) => {
+        $(impl ToJson for $ty {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::from(*self)
+            }
+        })*
+    };
+}
+
+impl_number_to_json!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_owned())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue {
+        T::to_json(self)
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue {
+        T::to_json(self)
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::Null,
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(T::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: ToJson, const N: usize> ToJson for [T; N] {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for BTreeMap<String, T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
+
+impl<T: ToJson, S> ToJson for HashMap<String, T, S> {
+    /// Entries are sorted by key, so that saving the same map always produces the same file.
+    fn to_json(&self) -> JsonValue {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        JsonValue::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v.to_json()))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,799 @@
+use crate::{DuplicateKeyPolicy, JsonLoadOptions};
+use std::cmp::Ordering;
+
+/// A generic, in-memory representation of a JSON value.
+///
+/// This is used for operations which need to inspect a JSON document without knowing its
+/// structure ahead of time, such as validation or merging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(JsonNumber),
+    String(String),
+    Array(Vec<JsonValue>),
+
+    /// A JSON object, with entries stored in the order they appear in the source text.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parses a [`JsonValue`] from JSON text.
+    ///
+    /// This is permissive in the same way as the deserializer used by
+    /// [`AssetPathJsonExt::load_json`](crate::AssetPathJsonExt::load_json): comments and trailing
+    /// commas are allowed.
+    pub fn parse(text: &str) -> Result<Self, JsonParseError> {
+        Self::parse_with(text, &JsonLoadOptions::permissive())
+    }
+
+    /// Parses a [`JsonValue`] from JSON text, accepting only the syntax allowed by the given
+    /// options.
+    pub fn parse_with(text: &str, options: &JsonLoadOptions) -> Result<Self, JsonParseError> {
+        if let Some(max_size) = options.max_size.filter(|max| text.len() > *max) {
+            return Err(JsonParseError {
+                line: 1,
+                column: 1,
+                message: format!(
+                    "size of {} bytes exceeds the maximum of {} bytes",
+                    text.len(),
+                    max_size
+                ),
+            });
+        }
+        let mut parser = Parser::new(text, options.clone());
+        let value = parser.value()?;
+        parser.skip_whitespace()?;
+        if parser.pos < text.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Gets the value for the given key, assuming this is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Gets the string content of this value, assuming it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Gets the value at the given [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901)
+    /// within this value, such as `/enemies/4/health`, or [`None`] if there is no such value.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let mut value = self;
+        for component in pointer.strip_prefix('/')?.split('/') {
+            let component = component.replace("~1", "/").replace("~0", "~");
+            value = match value {
+                JsonValue::Object(_) => value.get(&component)?,
+                JsonValue::Array(items) => items.get(component.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Converts a number into a [`JsonValue`]. Non-finite numbers become [`JsonValue::Null`], as
+/// they can't be represented in JSON.
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> Self {
+        match JsonNumber::from_f64(value) {
+            Some(number) => JsonValue::Number(number),
+            None => JsonValue::Null,
+        }
+    }
+}
+
+impl From<f32> for JsonValue {
+    fn from(value: f32) -> Self {
+        JsonValue::from(f64::from(value))
+    }
+}
+
+impl From<JsonNumber> for JsonValue {
+    fn from(value: JsonNumber) -> Self {
+        JsonValue::Number(value)
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for JsonNumber {
+            fn from(value: $ty) -> Self {
+                JsonNumber(value.to_string())
+            }
+        }
+
+        impl From<$ty> for JsonValue {
+            fn from(value: $ty) -> Self {
+                JsonValue::Number(value.into())
+            }
+        })*
+    };
+}
+
+impl_from_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// A JSON number, stored as the text it was written as, so that integers of any size and
+/// decimals are kept exactly.
+///
+/// Numbers are compared by value, so `1`, `1.0` and `1e0` are equal. Integers are compared
+/// exactly, and other numbers are compared as [`f64`]s.
+#[derive(Debug, Clone)]
+pub struct JsonNumber(String);
+
+impl JsonNumber {
+    /// Parses a number written in JSON syntax, such as `-12.5e3`, or returns [`None`] if the
+    /// text isn't a valid JSON number.
+    pub fn parse(text: &str) -> Option<Self> {
+        let bytes = text.as_bytes();
+        let digits = |pos: &mut usize| {
+            let start = *pos;
+            while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+                *pos += 1;
+            }
+            *pos - start
+        };
+        let mut pos = usize::from(bytes.first() == Some(&b'-'));
+        match bytes.get(pos) {
+            Some(b'0') => pos += 1,
+            Some(b'1'..=b'9') => {
+                digits(&mut pos);
+            }
+            _ => return None,
+        }
+        if bytes.get(pos) == Some(&b'.') {
+            pos += 1;
+            if digits(&mut pos) == 0 {
+                return None;
+            }
+        }
+        if let Some(b'e' | b'E') = bytes.get(pos) {
+            pos += 1;
+            if let Some(b'+' | b'-') = bytes.get(pos) {
+                pos += 1;
+            }
+            if digits(&mut pos) == 0 {
+                return None;
+            }
+        }
+        (pos == bytes.len()).then(|| Self(text.to_owned()))
+    }
+
+    /// Converts an [`f64`] into a [`JsonNumber`], or returns [`None`] if it isn't finite.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        // `Display` never uses exponents, which would make very large or small numbers long
+        let text = if value != 0.0 && !(1e-5..1e16).contains(&value.abs()) {
+            format!("{:?}", value)
+        } else {
+            format!("{}", value)
+        };
+        Some(Self(text))
+    }
+
+    /// Gets the text of this number, in JSON syntax.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Gets the value of this number as an [`i64`], if it is an integer in range. Numbers
+    /// written with a fraction or exponent, such as `1.0`, aren't considered integers.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.0.parse().ok()
+    }
+
+    /// Gets the value of this number as a [`u64`], if it is a non-negative integer in range.
+    /// Numbers written with a fraction or exponent, such as `1.0`, aren't considered integers.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.0.parse().ok()
+    }
+
+    /// Gets the value of this number as an [`f64`], which may be rounded.
+    pub fn as_f64(&self) -> f64 {
+        self.0.parse().unwrap()
+    }
+
+    /// Gets the value of this number as an [`i128`], which covers the range of both [`i64`] and
+    /// [`u64`], if it is written as an integer.
+    fn as_i128(&self) -> Option<i128> {
+        self.0.parse().ok()
+    }
+}
+
+impl PartialEq for JsonNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for JsonNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.as_i128(), other.as_i128()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+}
+
+impl std::fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Converts number syntax which is accepted by permissive parsing but isn't valid JSON, such as
+/// `01`, `+1` or `.5`, into the equivalent [`JsonNumber`]. Integers keep all of their digits.
+fn lenient_number(text: &str) -> Option<JsonNumber> {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text.strip_prefix('+').unwrap_or(text)),
+    };
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        let digits = digits.trim_start_matches('0');
+        let digits = if digits.is_empty() { "0" } else { digits };
+        return JsonNumber::parse(&format!("{}{}", sign, digits));
+    }
+    JsonNumber::from_f64(text.parse().ok()?)
+}
+
+/// Formats this value as compact JSON text, or as indented JSON text if the alternate flag (`{:#}`)
+/// is given.
+impl std::fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indent = if f.alternate() { Some(0) } else { None };
+        write_value(f, self, indent)
+    }
+}
+
+/// Writes a value as JSON text. If `indent` is provided, the value is written across multiple
+/// lines, with nested lines indented by one tab more than the given level.
+fn write_value(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &JsonValue,
+    indent: Option<usize>,
+) -> std::fmt::Result {
+    match value {
+        JsonValue::Null => f.write_str("null"),
+        JsonValue::Bool(value) => write!(f, "{}", value),
+        JsonValue::Number(value) => f.write_str(value.as_str()),
+        JsonValue::String(value) => write_string(f, value),
+        JsonValue::Array(items) => {
+            f.write_str("[")?;
+            for (i, item) in items.iter().enumerate() {
+                write_separator(f, i, indent)?;
+                write_value(f, item, indent.map(|indent| indent + 1))?;
+            }
+            write_end(f, items.is_empty(), indent)?;
+            f.write_str("]")
+        }
+        JsonValue::Object(entries) => {
+            f.write_str("{")?;
+            for (i, (key, value)) in entries.iter().enumerate() {
+                write_separator(f, i, indent)?;
+                write_string(f, key)?;
+                f.write_str(if indent.is_some() { ": " } else { ":" })?;
+                write_value(f, value, indent.map(|indent| indent + 1))?;
+            }
+            write_end(f, entries.is_empty(), indent)?;
+            f.write_str("}")
+        }
+    }
+}
+
+/// Writes the separator preceding the item with the given index in an array or object.
+fn write_separator(
+    f: &mut std::fmt::Formatter<'_>,
+    index: usize,
+    indent: Option<usize>,
+) -> std::fmt::Result {
+    if index > 0 {
+        f.write_str(",")?;
+    }
+    if let Some(indent) = indent {
+        f.write_str("\n")?;
+        write_tabs(f, indent + 1)?;
+    }
+    Ok(())
+}
+
+/// Writes the whitespace preceding the closing bracket of an array or object.
+fn write_end(
+    f: &mut std::fmt::Formatter<'_>,
+    empty: bool,
+    indent: Option<usize>,
+) -> std::fmt::Result {
+    match indent {
+        Some(indent) if !empty => {
+            f.write_str("\n")?;
+            write_tabs(f, indent)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Writes the given number of tabs.
+fn write_tabs(f: &mut std::fmt::Formatter<'_>, count: usize) -> std::fmt::Result {
+    for _ in 0..count {
+        f.write_str("\t")?;
+    }
+    Ok(())
+}
+
+/// Writes a string as a quoted and escaped JSON string.
+pub(crate) fn write_string(f: &mut impl std::fmt::Write, value: &str) -> std::fmt::Result {
+    f.write_str("\"")?;
+    for ch in value.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Describes an error that occurred while parsing a [`JsonValue`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} at line {line}, column {column}")]
+pub struct JsonParseError {
+    /// The line where the error occurred, starting at 1.
+    pub line: usize,
+
+    /// The column where the error occurred, starting at 1.
+    pub column: usize,
+
+    /// Describes the error.
+    pub message: String,
+}
+
+/// Gets the [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) for the innermost value in
+/// the given JSON text which contains the given byte offset, along with the offset of the start of
+/// that value if it is a string, number or literal, or the given offset otherwise.
+///
+/// This is best-effort for malformed text, giving the pointer for the values which were open when
+/// the text became malformed. Likewise, to bound the recursion, locating stops at values nested
+/// more deeply than [`JsonLoadOptions::DEFAULT_MAX_DEPTH`], giving the pointer for the values
+/// containing them.
+pub(crate) fn locate_json(text: &str, offset: usize) -> (String, usize) {
+    let mut parser = Parser::new(text, JsonLoadOptions::permissive());
+    let mut path = Vec::new();
+    let mut start = offset;
+    let _ = parser.locate(offset, &mut path, &mut start);
+    let mut pointer = String::new();
+    for component in path {
+        pointer.push('/');
+        pointer.push_str(&component.replace('~', "~0").replace('/', "~1"));
+    }
+    (pointer, start)
+}
+
+/// Parses a [`JsonValue`] from a string. This also provides the character-level operations used
+/// to find `$include` nodes.
+pub(crate) struct Parser<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) pos: usize,
+    pub(crate) line: usize,
+    pub(crate) line_start: usize,
+    options: JsonLoadOptions,
+
+    /// The number of arrays and objects containing the current position.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Constructs a parser for the given text, positioned at its start.
+    pub(crate) fn new(text: &'a str, options: JsonLoadOptions) -> Self {
+        Self {
+            text,
+            pos: 0,
+            line: 1,
+            line_start: 0,
+            options,
+            depth: 0,
+        }
+    }
+
+    /// Constructs an error at the current position of the parser.
+    pub(crate) fn error(&self, message: &str) -> JsonParseError {
+        JsonParseError {
+            line: self.line,
+            column: self.text[self.line_start..self.pos].chars().count() + 1,
+            message: message.to_owned(),
+        }
+    }
+
+    /// Gets the next character in the text without consuming it.
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    /// Consumes the next character in the text.
+    pub(crate) fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.line_start = self.pos;
+        }
+        Some(ch)
+    }
+
+    /// Consumes the given character, or returns an error if it is not next.
+    pub(crate) fn expect(&mut self, expected: char) -> Result<(), JsonParseError> {
+        if self.peek() == Some(expected) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    /// Skips whitespace and comments.
+    pub(crate) fn skip_whitespace(&mut self) -> Result<(), JsonParseError> {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\n' | '\r') => {
+                    self.next();
+                }
+                Some('/') => {
+                    let rest = &self.text[self.pos..];
+                    if !self.options.allow_comments
+                        && (rest.starts_with("//") || rest.starts_with("/*"))
+                    {
+                        return Err(self.error("comments are not allowed"));
+                    }
+                    if rest.starts_with("//") {
+                        while !matches!(self.next(), Some('\n') | None) {}
+                    } else if rest.starts_with("/*") {
+                        self.next();
+                        self.next();
+                        loop {
+                            if self.text[self.pos..].starts_with("*/") {
+                                self.next();
+                                self.next();
+                                break;
+                            } else if self.next().is_none() {
+                                return Err(self.error("unterminated comment"));
+                            }
+                        }
+                    } else {
+                        return Ok(());
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Parses a value, including any leading whitespace.
+    pub(crate) fn value(&mut self) -> Result<JsonValue, JsonParseError> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some('{') => {
+                self.enter()?;
+                let mut entries = Vec::<(String, JsonValue)>::new();
+                loop {
+                    self.skip_whitespace()?;
+                    if self.peek() == Some('}') {
+                        self.end_container(!entries.is_empty())?;
+                        break;
+                    }
+                    let key_state = (self.pos, self.line, self.line_start);
+                    let key = self.string()?;
+                    if self.options.duplicate_keys == DuplicateKeyPolicy::Reject
+                        && entries.iter().any(|(other, _)| *other == key)
+                    {
+                        (self.pos, self.line, self.line_start) = key_state;
+                        return Err(self.error(&format!("duplicate key {:?}", key)));
+                    }
+                    self.skip_whitespace()?;
+                    self.expect(':')?;
+                    let value = self.value()?;
+                    entries.push((key, value));
+                    self.skip_whitespace()?;
+                    match self.next() {
+                        Some(',') => {}
+                        Some('}') => {
+                            self.depth -= 1;
+                            break;
+                        }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+                Ok(JsonValue::Object(entries))
+            }
+            Some('[') => {
+                self.enter()?;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace()?;
+                    if self.peek() == Some(']') {
+                        self.end_container(!items.is_empty())?;
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_whitespace()?;
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => {
+                            self.depth -= 1;
+                            break;
+                        }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+                Ok(JsonValue::Array(items))
+            }
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => {
+                for (keyword, value) in [
+                    ("null", JsonValue::Null),
+                    ("true", JsonValue::Bool(true)),
+                    ("false", JsonValue::Bool(false)),
+                ] {
+                    if self.text[self.pos..].starts_with(keyword) {
+                        self.pos += keyword.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("unexpected character"))
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    /// Skips a value, including any leading whitespace, pushing the keys and indices leading to
+    /// the innermost value containing the given offset onto `path`, and setting `start` to the
+    /// start of that value if it is a scalar. Returns `true` if the value contains the offset, or
+    /// it occurs in whitespace before the value.
+    fn locate(
+        &mut self,
+        offset: usize,
+        path: &mut Vec<String>,
+        start: &mut usize,
+    ) -> Result<bool, JsonParseError> {
+        self.skip_whitespace()?;
+        if offset < self.pos {
+            return Ok(true);
+        }
+        match self.peek() {
+            Some('{') => {
+                self.enter()?;
+                loop {
+                    self.skip_whitespace()?;
+                    if offset < self.pos {
+                        return Ok(true);
+                    }
+                    if self.peek() == Some('}') {
+                        self.next();
+                        break;
+                    }
+                    path.push(self.string()?);
+                    self.skip_whitespace()?;
+                    self.expect(':')?;
+                    if self.locate(offset, path, start)? {
+                        return Ok(true);
+                    }
+                    path.pop();
+                    self.skip_whitespace()?;
+                    match self.next() {
+                        Some(',') => {}
+                        Some('}') => break,
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+                self.depth -= 1;
+            }
+            Some('[') => {
+                self.enter()?;
+                for index in 0.. {
+                    self.skip_whitespace()?;
+                    if offset < self.pos {
+                        return Ok(true);
+                    }
+                    if self.peek() == Some(']') {
+                        self.next();
+                        break;
+                    }
+                    path.push(index.to_string());
+                    if self.locate(offset, path, start)? {
+                        return Ok(true);
+                    }
+                    path.pop();
+                    self.skip_whitespace()?;
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => break,
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+                self.depth -= 1;
+            }
+            _ => {
+                let value_start = self.pos;
+                self.value()?;
+                if offset < self.pos {
+                    *start = value_start;
+                }
+            }
+        }
+        Ok(offset < self.pos)
+    }
+
+    /// Moves from the start of an array or object, or whitespace before it, to the start of its
+    /// entry with the given key, or for arrays, its item with the given index. Returns `false` if
+    /// there is no such value.
+    pub(crate) fn enter_child(&mut self, key: &str) -> Result<bool, JsonParseError> {
+        self.skip_whitespace()?;
+        let (close, index) = match self.peek() {
+            Some('{') => ('}', None),
+            Some('[') => match key.parse::<usize>() {
+                Ok(index) => (']', Some(index)),
+                Err(_) => return Ok(false),
+            },
+            _ => return Ok(false),
+        };
+        self.next();
+        for i in 0.. {
+            self.skip_whitespace()?;
+            if self.peek() == Some(close) {
+                break;
+            }
+            let found = match index {
+                Some(index) => i == index,
+                None => {
+                    let entry_key = self.string()?;
+                    self.skip_whitespace()?;
+                    self.expect(':')?;
+                    entry_key == key
+                }
+            };
+            if found {
+                self.skip_whitespace()?;
+                return Ok(true);
+            }
+            self.value()?;
+            self.skip_whitespace()?;
+            if self.next() != Some(',') {
+                break;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Consumes the opening bracket of an array or object, checking the nesting depth.
+    fn enter(&mut self) -> Result<(), JsonParseError> {
+        self.depth += 1;
+        if let Some(max_depth) = self.options.max_depth.filter(|max| self.depth > *max) {
+            return Err(self.error(&format!(
+                "nesting depth exceeds the maximum of {}",
+                max_depth
+            )));
+        }
+        self.next();
+        Ok(())
+    }
+
+    /// Consumes the closing bracket of an array or object which is not preceded by a value, which
+    /// means that it follows a trailing comma if the container is not empty.
+    fn end_container(&mut self, trailing_comma: bool) -> Result<(), JsonParseError> {
+        if trailing_comma && !self.options.allow_trailing_commas {
+            return Err(self.error("trailing commas are not allowed"));
+        }
+        self.next();
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Parses a number. Unless the options are strict, this also accepts syntax such as leading
+    /// zeros, which is converted to the equivalent JSON number.
+    fn number(&mut self) -> Result<JsonValue, JsonParseError> {
+        let start = self.pos;
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.next();
+        }
+        let text = &self.text[start..self.pos];
+        let number = match JsonNumber::parse(text) {
+            Some(number) => Some(number),
+            None if !self.options.strict => lenient_number(text),
+            None => None,
+        };
+        match number {
+            Some(number) => Ok(JsonValue::Number(number)),
+            None => {
+                self.pos = start;
+                Err(self.error("invalid number"))
+            }
+        }
+    }
+
+    /// Parses a quoted string. If the options are strict, control characters must be escaped.
+    pub(crate) fn string(&mut self) -> Result<String, JsonParseError> {
+        self.expect('"')?;
+        let mut res = String::new();
+        loop {
+            if self.options.strict && self.peek().is_some_and(|ch| ch < ' ') {
+                return Err(self.error("control characters in strings must be escaped"));
+            }
+            match self.next() {
+                Some('"') => return Ok(res),
+                Some('\\') => match self.next() {
+                    Some('"') => res.push('"'),
+                    Some('\\') => res.push('\\'),
+                    Some('/') => res.push('/'),
+                    Some('b') => res.push('\u{8}'),
+                    Some('f') => res.push('\u{c}'),
+                    Some('n') => res.push('\n'),
+                    Some('r') => res.push('\r'),
+                    Some('t') => res.push('\t'),
+                    Some('u') => res.push(self.unicode_escape()?),
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(ch) => res.push(ch),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Parses the rest of a `\u` escape sequence following the `u`, including the second escape
+    /// sequence of a surrogate pair.
+    pub(crate) fn unicode_escape(&mut self) -> Result<char, JsonParseError> {
+        let start = self.pos;
+        let high = self.hex_escape()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                let low_start = self.pos;
+                let low = match self.text[self.pos..].starts_with("\\u") {
+                    true => {
+                        self.pos += 2;
+                        self.hex_escape()?
+                    }
+                    false => 0,
+                };
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    self.pos = low_start;
+                    return Err(self.error("unpaired surrogate"));
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            _ => high,
+        };
+        char::from_u32(code).ok_or_else(|| {
+            self.pos = start;
+            self.error("unpaired surrogate")
+        })
+    }
+
+    /// Parses the four hexadecimal digits of a `\u` escape sequence.
+    fn hex_escape(&mut self) -> Result<u32, JsonParseError> {
+        let digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let code = u32::from_str_radix(digits, 16).unwrap();
+        self.pos += 4;
+        Ok(code)
+    }
+}
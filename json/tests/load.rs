@@ -1,12 +1,26 @@
-use assetman::{AssetPath, Tracker};
-use assetman_json::AssetPathJsonExt;
+use assetman::{
+    AssetCache, AssetLoadError, AssetLoadResult, AssetPath, Handle, Tracker, WriteOptions,
+};
+use assetman_json::{
+    cooked_json_path, decode_cooked_json, encode_cooked_json, is_cooked_json_current,
+    ArrayMergePolicy, AssetPathConfigExt, AssetPathJsonExt, CookedJsonError, JsonAssetContext,
+    JsonElementError, JsonError, JsonIncludeError, JsonLoadOptions, JsonParseError, JsonSchema,
+    JsonSchemaError, JsonValue, ToJson, UnsupportedConfigFormatError,
+};
+use assetman_test_util::TempDir;
 
-#[derive(serdere::Deserialize)]
+#[derive(serdere::Deserialize, JsonSchema, ToJson)]
 pub struct Config {
     name: String,
     keywords: Vec<String>,
 }
 
+#[derive(serdere::Deserialize)]
+pub struct LocalizedString {
+    key: String,
+    text: String,
+}
+
 #[test]
 fn test_load_config() {
     let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
@@ -24,3 +38,559 @@ fn test_load_config() {
         vec!["test".to_owned(), "config".to_owned(), "json".to_owned()]
     );
 }
+
+#[test]
+fn test_load_config_error() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let err = root
+        .relative("bad_config.json")
+        .load_json::<Config>(&tracker)
+        .err()
+        .unwrap();
+    let json_err = err.inner.downcast_ref::<JsonError>().unwrap();
+    assert_eq!(json_err.pointer, "/keywords/1");
+    assert_eq!((json_err.line, json_err.column), (5, 3));
+    assert!(err.to_string().contains("5 | \t\t5\n  | \t\t^"));
+
+    // Errors in deeply nested text are located without overflowing the stack
+    let text = "[".repeat(100_000) + &"]".repeat(100_000);
+    let json_err = JsonError::new(text.as_bytes(), text.len(), "too deep".into());
+    assert_eq!(json_err.line, 1);
+}
+
+#[test]
+fn test_load_config_include() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/include"
+    )));
+    let tracker = Tracker::default();
+    let config = root
+        .relative("main.json")
+        .load_json::<Config>(&tracker)
+        .unwrap();
+    assert_eq!(config.name, "Included Config".to_owned());
+    assert_eq!(
+        config.keywords,
+        vec!["test".to_owned(), "include".to_owned()]
+    );
+
+    // Errors are located in the included file which contains them
+    let err = root
+        .relative("bad_main.json")
+        .load_json::<Config>(&tracker)
+        .unwrap_err();
+    assert_eq!(err.asset, root.relative("bad_keywords.json"));
+    let json_err = err.inner.downcast_ref::<JsonError>().unwrap();
+    assert_eq!(json_err.pointer, "/1");
+    assert_eq!((json_err.line, json_err.column), (3, 2));
+
+    // References within the same file aren't includes
+    let value = root
+        .relative("local_ref.json")
+        .load_json_value_resolved(&tracker)
+        .unwrap();
+    assert_eq!(
+        value.pointer("/value/$ref").and_then(JsonValue::as_str),
+        Some("#/definitions/x")
+    );
+
+    let err = root
+        .relative("cycle_a.json")
+        .load_json_value_resolved(&tracker)
+        .unwrap_err();
+    let Some(JsonIncludeError::Cycle(cycle)) = err.inner.downcast_ref::<JsonIncludeError>() else {
+        panic!("expected include cycle, got {}", err);
+    };
+    assert_eq!(
+        *cycle,
+        ["cycle_a.json", "cycle_b.json", "cycle_a.json"].map(|name| root.relative(name))
+    );
+
+    // A file may include other values from itself
+    let value = root
+        .relative("self_include.json")
+        .load_json_value_resolved(&tracker)
+        .unwrap();
+    assert_eq!(value.pointer("/value").and_then(JsonValue::as_i64), Some(1));
+
+    // Files which include the same values many times over are rejected
+    let err = root
+        .relative("laughs.json")
+        .load_json_value_resolved(&tracker)
+        .unwrap_err();
+    assert!(matches!(
+        err.inner.downcast_ref::<JsonIncludeError>(),
+        Some(JsonIncludeError::TooLarge(_))
+    ));
+}
+
+#[test]
+fn test_load_config_value() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let config = root
+        .relative("config.json")
+        .load_json_value(&tracker)
+        .unwrap();
+    assert_eq!(config.get("name").unwrap().as_str(), Some("Test Config"));
+    let err = JsonValue::parse("{\n  \"a\": [1, 2,, 3]\n}").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 14));
+}
+
+#[test]
+fn test_parse_value() {
+    // Numbers are kept exactly
+    let value = JsonValue::parse("[18446744073709551615, 9007199254740993, 0.1, 1e400]").unwrap();
+    assert_eq!(
+        value.to_string(),
+        "[18446744073709551615,9007199254740993,0.1,1e400]"
+    );
+    let JsonValue::Array(items) = &value else {
+        panic!("expected array");
+    };
+    let JsonValue::Number(max) = &items[0] else {
+        panic!("expected number");
+    };
+    assert_eq!(max.as_u64(), Some(u64::MAX));
+    assert_eq!(u64::MAX.to_json(), items[0]);
+    assert_eq!(JsonValue::parse("1.0").unwrap(), JsonValue::from(1));
+
+    // Permissive parsing converts leading zeros, which strict parsing rejects
+    assert_eq!(JsonValue::parse("007").unwrap().to_string(), "7");
+    let strict = JsonLoadOptions::strict();
+    let err = JsonValue::parse_with("[1, 01]", &strict).unwrap_err();
+    assert_eq!((err.column, err.message.as_str()), (5, "invalid number"));
+    let err = JsonValue::parse_with("\"a\tb\"", &strict).unwrap_err();
+    assert_eq!(err.column, 3);
+    assert!(JsonValue::parse("\"a\tb\"").is_ok());
+
+    // Escapes must be well-formed
+    assert_eq!(
+        JsonValue::parse(r#""\ud83d\ude00""#).unwrap(),
+        JsonValue::String("\u{1f600}".to_owned())
+    );
+    let err = JsonValue::parse(r#""\ud83d\u0041""#).unwrap_err();
+    assert_eq!(err.message, "unpaired surrogate");
+    let err = JsonValue::parse(r#""\u+abc""#).unwrap_err();
+    assert_eq!(err.message, "invalid unicode escape");
+
+    // Deeply nested text is rejected rather than overflowing the stack
+    let deep = "[".repeat(100_000);
+    let err = JsonValue::parse(&deep).unwrap_err();
+    assert!(err.message.starts_with("nesting depth exceeds"));
+}
+
+#[test]
+fn test_load_config_validated() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let config = root
+        .relative("config.json")
+        .load_json_validated::<Config>(&tracker)
+        .unwrap();
+    assert_eq!(config.name, "Test Config".to_owned());
+    let err = root
+        .relative("bad_config.json")
+        .load_json_validated::<Config>(&tracker)
+        .err()
+        .unwrap();
+    let JsonSchemaError(violations) = err.inner.downcast_ref::<JsonSchemaError>().unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].pointer, "/keywords/1");
+}
+
+#[test]
+fn test_load_options() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let sloppy = root.relative("sloppy.json");
+    let config = sloppy.load_json::<Config>(&tracker).unwrap();
+    assert_eq!(
+        config.keywords,
+        vec!["test".to_owned(), "config".to_owned()]
+    );
+
+    // Strict options reject the comment
+    let err = sloppy
+        .load_json_with_options(&tracker, &JsonLoadOptions::strict(), |value| {
+            value.get::<Config>()
+        })
+        .err()
+        .unwrap();
+    let parse_err = err.inner.downcast_ref::<JsonParseError>().unwrap();
+    assert_eq!((parse_err.line, parse_err.column), (2, 2));
+
+    // Options can also be set as the default for a root
+    let strict_root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let options = JsonLoadOptions {
+        allow_comments: true,
+        ..JsonLoadOptions::strict()
+    };
+    JsonLoadOptions::set_root_default(&strict_root, options.clone());
+    assert_eq!(
+        JsonLoadOptions::default_for(&strict_root.relative("a.json")),
+        options
+    );
+    let err = strict_root
+        .relative("sloppy.json")
+        .load_json::<Config>(&tracker)
+        .err()
+        .unwrap();
+    let parse_err = err.inner.downcast_ref::<JsonParseError>().unwrap();
+    assert_eq!(parse_err.message, "trailing commas are not allowed");
+    assert!(strict_root
+        .relative("config.json")
+        .load_json::<Config>(&tracker)
+        .is_ok());
+
+    // ...without affecting other roots for the same directory
+    assert_eq!(
+        JsonLoadOptions::default_for(&root.relative("a.json")),
+        JsonLoadOptions::permissive()
+    );
+}
+
+#[test]
+fn test_load_layers() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/layers"
+    )));
+    let tracker = Tracker::default();
+    let layers = [
+        root.relative("base.json"),
+        root.relative("platform/linux.json"),
+        root.relative("user.json"),
+    ];
+    let config: Config =
+        assetman_json::load_json_layers(&layers, &tracker, ArrayMergePolicy::Append).unwrap();
+    assert_eq!(config.name, "User Config");
+    assert_eq!(config.keywords, vec!["base".to_owned(), "linux".to_owned()]);
+
+    let mut merged = JsonValue::Null;
+    for layer in layers.iter() {
+        let value = layer.load_json_value(&tracker).unwrap();
+        assetman_json::merge_json(&mut merged, value, ArrayMergePolicy::Replace);
+    }
+    assert_eq!(
+        merged.to_string(),
+        r#"{"name":"User Config","keywords":["linux"]}"#
+    );
+
+    // A null layer leaves the layers below it unchanged
+    assetman_json::merge_json(&mut merged, JsonValue::Null, ArrayMergePolicy::Replace);
+    assert_eq!(
+        merged.to_string(),
+        r#"{"name":"User Config","keywords":["linux"]}"#
+    );
+
+    // Errors are located in the merged value, as it would be saved
+    let layers = [root.relative("base.json"), root.relative("bad.json")];
+    let err =
+        assetman_json::load_json_layers::<Config>(&layers, &tracker, ArrayMergePolicy::Append)
+            .err()
+            .unwrap();
+    assert_eq!(err.asset, root.relative("bad.json"));
+    let json_err = err.inner.downcast_ref::<JsonError>().unwrap();
+    assert_eq!(json_err.pointer, "/keywords/1");
+    assert_eq!((json_err.line, json_err.column), (5, 3));
+}
+
+#[derive(serdere::Deserialize)]
+pub struct Material {
+    name: String,
+    textures: Vec<AssetPath>,
+}
+
+#[test]
+fn test_load_relative() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let material = root
+        .relative("materials/wood.json")
+        .load_json_relative::<Material>(&tracker)
+        .unwrap();
+    assert_eq!(material.name, "Wood");
+    assert_eq!(
+        material.textures,
+        [
+            root.relative("img/wood.png"),
+            root.relative("materials/wood_normal.png")
+        ]
+    );
+}
+
+impl assetman::CacheSize for Material {
+    fn cache_size(&self) -> usize {
+        self.name.len() + self.textures.len() * std::mem::size_of::<AssetPath>()
+    }
+}
+
+impl assetman_json::LoadHandle for Material {
+    fn load_handle(asset: &AssetPath, ctx: &JsonAssetContext) -> AssetLoadResult<Handle<Self>> {
+        ctx.load_json_handle(asset)
+    }
+}
+
+#[derive(serdere::Deserialize)]
+pub struct Scene {
+    name: String,
+    floor: Handle<Material>,
+    walls: Handle<Material>,
+    detail: AssetPath,
+}
+
+#[test]
+fn test_load_relative_handles() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let cache = AssetCache::new(1 << 20);
+    let scene = root
+        .relative("scenes/forest.json")
+        .load_json_relative_cached::<Scene>(&cache, &tracker)
+        .unwrap();
+    assert_eq!(scene.name, "Forest");
+    assert!(Handle::ptr_eq(&scene.floor, &scene.walls));
+    assert_eq!(scene.floor.get().textures[0], root.relative("img/wood.png"));
+
+    // Paths in included values are relative to the file they were included from
+    assert_eq!(scene.detail, root.relative("materials/wood_normal.png"));
+
+    // Failures to load handles are reported where the path occurs
+    let err = root
+        .relative("scenes/quarry.json")
+        .load_json_relative_cached::<Scene>(&cache, &tracker)
+        .err()
+        .unwrap();
+    assert_eq!(err.asset, root.relative("scenes/quarry.json"));
+    let json_err = err.inner.downcast_ref::<JsonError>().unwrap();
+    assert_eq!(json_err.pointer, "/floor");
+    let inner = json_err.inner.downcast_ref::<AssetLoadError>().unwrap();
+    assert_eq!(inner.asset, root.relative("materials/stone.json"));
+}
+
+#[test]
+fn test_asset_reference() {
+    assert!(assetman_json::is_asset_reference("../img/wood.png"));
+    assert!(assetman_json::is_asset_reference("level.json"));
+    assert!(!assetman_json::is_asset_reference("Test Config"));
+    assert!(!assetman_json::is_asset_reference("1.5"));
+    assert!(!assetman_json::is_asset_reference("1.0b"));
+    assert!(!assetman_json::is_asset_reference("Hello.World"));
+    assert!(assetman_json::is_asset_reference("sub/Hello.World"));
+    assert!(!assetman_json::is_asset_reference(
+        "https://example.com/a.png"
+    ));
+}
+
+#[test]
+fn test_save_json() {
+    let dir = TempDir::new("save-json");
+    let root = AssetPath::new_root_fs(&dir);
+    let tracker = Tracker::default();
+    let value = JsonValue::parse(r#"{"name": "Saved", "items": [1, [], {}]}"#).unwrap();
+    let asset = root.relative("saved.json");
+    asset
+        .save_json(&value, &assetman::WriteOptions::default())
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("saved.json")).unwrap(),
+        "{\n\t\"name\": \"Saved\",\n\t\"items\": [\n\t\t1,\n\t\t[],\n\t\t{}\n\t]\n}\n"
+    );
+    assert_eq!(asset.load_json_value(&tracker).unwrap(), value);
+
+    // Typed values are saved in the format they are loaded from
+    let config = Config {
+        name: "Saved".to_owned(),
+        keywords: vec!["typed".to_owned()],
+    };
+    let asset = root.relative("config.json");
+    asset
+        .save_json(&config, &assetman::WriteOptions::default())
+        .unwrap();
+    let loaded = asset.load_json::<Config>(&tracker).unwrap();
+    assert_eq!(
+        (loaded.name, loaded.keywords),
+        (config.name, config.keywords)
+    );
+}
+
+#[test]
+fn test_load_config_formats() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let mut names = vec!["config.json"];
+    if cfg!(feature = "json5") {
+        names.push("config.json5");
+    }
+    if cfg!(feature = "ron") {
+        names.push("config.ron");
+    }
+    if cfg!(feature = "toml") {
+        names.push("config.toml");
+    }
+    for name in names {
+        let config = root.relative(name).load_config::<Config>(&tracker).unwrap();
+        assert_eq!(config.name, "Test Config".to_owned());
+        assert_eq!(
+            config.keywords,
+            vec!["test".to_owned(), "config".to_owned(), "json".to_owned()]
+        );
+    }
+
+    // Unknown extensions are reported as an error
+    let err = root
+        .relative("materials/wood.txt")
+        .load_config_value(&tracker)
+        .err()
+        .unwrap();
+    assert!(err.inner.is::<UnsupportedConfigFormatError>());
+}
+
+#[test]
+fn test_load_cooked() {
+    let dir = TempDir::new("cooked");
+    let root = AssetPath::new_root_fs(&dir);
+    let source = root.relative("config.json");
+    let write = |asset: &AssetPath, data: &[u8]| {
+        // Ensure modification times differ
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        asset.write_bytes(data, &WriteOptions::default()).unwrap();
+    };
+    write(
+        &source,
+        br#"{"name": "Source", "keywords": [{"$include": "keyword.json"}]}"#,
+    );
+    write(&root.relative("keyword.json"), br#""cooked""#);
+    let tracker = Tracker::default();
+    let bytes = source.cook_json(&tracker).unwrap();
+    assert_eq!(
+        decode_cooked_json(&bytes).unwrap().to_string(),
+        r#"{"name":"Source","keywords":["cooked"]}"#
+    );
+
+    // Cooked siblings are only used where enabled
+    let value = JsonValue::parse(r#"{"name": "Cooked", "keywords": []}"#).unwrap();
+    write(&cooked_json_path(&source), &encode_cooked_json(&value));
+    let config = source.load_json::<Config>(&Tracker::default()).unwrap();
+    assert_eq!(config.name, "Source");
+    let options = JsonLoadOptions {
+        use_cooked: true,
+        ..JsonLoadOptions::permissive()
+    };
+    JsonLoadOptions::set_root_default(&root, options);
+
+    // A newer cooked sibling is loaded in place of the source
+    let tracker = Tracker::default();
+    let config = source.load_json::<Config>(&tracker).unwrap();
+    assert_eq!(config.name, "Cooked");
+
+    // ...until the source is modified
+    write(&source, br#"{"name": "Edited", "keywords": []}"#);
+    assert!(!tracker.get().is_valid());
+    let config = source.load_json::<Config>(&Tracker::default()).unwrap();
+    assert_eq!(config.name, "Edited");
+
+    // Cooked data is also ignored once a file it includes is modified
+    write(
+        &source,
+        br#"{"name": "Included", "keywords": [{"$include": "keyword.json"}]}"#,
+    );
+    let bytes = source.cook_json(&Tracker::default()).unwrap();
+    write(&cooked_json_path(&source), &bytes);
+    assert!(is_cooked_json_current(&source, &Tracker::default()));
+    let tracker = Tracker::default();
+    let config = source.load_json::<Config>(&tracker).unwrap();
+    assert_eq!(config.keywords, vec!["cooked".to_owned()]);
+    write(&root.relative("keyword.json"), br#""edited""#);
+    assert!(!tracker.get().is_valid());
+    assert!(!is_cooked_json_current(&source, &Tracker::default()));
+    let config = source.load_json::<Config>(&Tracker::default()).unwrap();
+    assert_eq!(config.keywords, vec!["edited".to_owned()]);
+
+    // Errors are located in the cooked value, while errors in the cooked data itself are
+    // attributed to the cooked sibling
+    let value = JsonValue::parse(r#"{"name": 5, "keywords": []}"#).unwrap();
+    write(&cooked_json_path(&source), &encode_cooked_json(&value));
+    let err = source
+        .load_json::<Config>(&Tracker::default())
+        .err()
+        .unwrap();
+    assert_eq!(err.asset, source);
+    let json_err = err.inner.downcast_ref::<JsonError>().unwrap();
+    assert_eq!((json_err.pointer.as_str(), json_err.line), ("/name", 2));
+    let value = JsonValue::parse(r#"{"name": "Cooked", "keywords": []}"#).unwrap();
+    let bytes = encode_cooked_json(&value);
+    write(&cooked_json_path(&source), &bytes[..bytes.len() - 2]);
+    let err = source
+        .load_json::<Config>(&Tracker::default())
+        .err()
+        .unwrap();
+    assert_eq!(err.asset, cooked_json_path(&source));
+    assert!(err.inner.is::<CookedJsonError>());
+
+    // A sibling which isn't cooked JSON, such as a glTF buffer, is ignored
+    write(&cooked_json_path(&source), &[0, 1, 2, 3]);
+    let config = source.load_json::<Config>(&Tracker::default()).unwrap();
+    assert_eq!(config.keywords, vec!["edited".to_owned()]);
+}
+
+#[test]
+fn test_stream_array() {
+    let root = AssetPath::new_root_fs(std::path::Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests"
+    )));
+    let tracker = Tracker::default();
+    let strings = root
+        .relative("strings.json")
+        .stream_json_array::<LocalizedString>(&tracker, "/strings")
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(strings.len(), 4);
+    let string = strings[1].as_ref().unwrap();
+    assert_eq!(
+        (string.key.as_str(), string.text.as_str()),
+        ("menu.options", "Options")
+    );
+
+    // Errors are tagged with the index of the element, and don't prevent later elements from
+    // loading
+    let err = strings[2].as_ref().err().unwrap();
+    let element_err = err.inner.downcast_ref::<JsonElementError>().unwrap();
+    assert_eq!((element_err.index, element_err.line), (2, 6));
+    assert!(element_err.inner.is::<JsonError>());
+    assert_eq!(strings[3].as_ref().unwrap().text, "Back");
+
+    // The pointer must refer to an array
+    assert!(root
+        .relative("strings.json")
+        .stream_json_array::<LocalizedString>(&tracker, "/language")
+        .is_err());
+}
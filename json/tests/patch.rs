@@ -0,0 +1,97 @@
+use assetman::{AssetPath, WriteOptions};
+use assetman_json::{apply_json_patch, diff_json, JsonPatchOp, JsonValue, JsonWatcher};
+use assetman_test_util::TempDir;
+
+#[derive(serdere::Deserialize)]
+pub struct Balance {
+    weapons: Vec<Weapon>,
+}
+
+#[derive(serdere::Deserialize)]
+pub struct Weapon {
+    damage: u32,
+}
+
+#[test]
+fn test_diff_patch() {
+    let old = JsonValue::parse(
+        r#"{"weapons": [{"damage": 10}, {"damage": 5, "name": "Bow"}, 3], "gone": 1}"#,
+    )
+    .unwrap();
+    let new =
+        JsonValue::parse(r#"{"weapons": [{"damage": 12}, {"damage": 5}], "added": true}"#).unwrap();
+    let ops = diff_json(&old, &new);
+    let descriptions = ops.iter().map(|op| op.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        [
+            "/weapons/0/damage 10 -> 12",
+            "/weapons/1/name removed \"Bow\"",
+            "/weapons/2 removed 3",
+            "/gone removed 1",
+            "/added added true",
+        ]
+    );
+    assert_eq!(
+        ops[0].to_json().to_string(),
+        r#"{"op":"replace","path":"/weapons/0/damage","value":12}"#
+    );
+
+    // Applying the diff produces the new value
+    let mut value = old.clone();
+    apply_json_patch(&mut value, &ops).unwrap();
+    assert!(diff_json(&value, &new).is_empty());
+
+    // Operations with missing paths fail
+    let op = JsonPatchOp::Remove {
+        path: "/missing/0".to_owned(),
+        old: JsonValue::Null,
+    };
+    assert_eq!(
+        apply_json_patch(&mut value, &[op]).unwrap_err().path,
+        "/missing/0"
+    );
+}
+
+#[test]
+fn test_watcher_reload() {
+    let dir = TempDir::new("watcher");
+    let root = AssetPath::new_root_fs(&dir);
+    let asset = root.relative("balance.json");
+    let write = |text: &str| {
+        // Ensure modification times differ
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        asset
+            .write_bytes(text.as_bytes(), &WriteOptions::default())
+            .unwrap();
+    };
+    write(r#"{"weapons": [{"damage": 10}]}"#);
+    let mut watcher = JsonWatcher::<Balance>::load(&asset).unwrap();
+    assert!(watcher.reload().unwrap().is_empty());
+
+    // Reloading reports what changed
+    write(r#"{"weapons": [{"damage": 12}]}"#);
+    assert!(watcher.is_outdated());
+    let ops = watcher.reload().unwrap();
+    assert_eq!(ops.len(), 1);
+    assert_eq!(ops[0].to_string(), "/weapons/0/damage 10 -> 12");
+    assert_eq!(watcher.get().weapons[0].damage, 12);
+
+    // If the asset breaks, the previous version is kept
+    write(r#"{"weapons": ["#);
+    assert!(watcher.reload().is_err());
+    assert!(!watcher.is_outdated());
+    assert_eq!(
+        watcher.value().pointer("/weapons/0/damage"),
+        Some(&JsonValue::from(12))
+    );
+
+    // ...including if it no longer deserializes
+    write(r#"{"weapons": [{"damage": -1}]}"#);
+    assert!(watcher.reload().is_err());
+    assert_eq!(watcher.get().weapons[0].damage, 12);
+    assert_eq!(
+        watcher.value().pointer("/weapons/0/damage"),
+        Some(&JsonValue::from(12))
+    );
+}
@@ -0,0 +1,109 @@
+use assetman::AssetPath;
+use assetman_json::{validate_json, JsonSchema, JsonSchemaRegistry, JsonValue, SchemaViolation};
+
+/// Describes an enemy.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+pub struct Enemy {
+    /// The display name of the enemy.
+    name: String,
+    health: u32,
+    #[serde(rename = "spawnWeight")]
+    #[serde(default)]
+    spawn_weight: f32,
+    sprite: Option<AssetPath>,
+    tags: Vec<String>,
+    kind: EnemyKind,
+}
+
+#[derive(JsonSchema)]
+pub enum EnemyKind {
+    Melee,
+    #[serde(rename = "ranged")]
+    Ranged,
+}
+
+#[derive(JsonSchema)]
+pub enum Layer {
+    Background = 0,
+    Foreground = 10,
+}
+
+#[test]
+fn test_schema_derive() {
+    let schema = Enemy::json_schema();
+    assert_eq!(
+        schema.get("description"),
+        Some(&JsonValue::String("Describes an enemy.".to_owned()))
+    );
+    let properties = schema.get("properties").unwrap();
+    assert_eq!(
+        properties.pointer("/name/description").unwrap().as_str(),
+        Some("The display name of the enemy.")
+    );
+    assert!(properties.get("spawnWeight").is_some());
+    assert_eq!(
+        schema.get("required").unwrap().to_string(),
+        r#"["name","health","tags","kind"]"#
+    );
+    assert_eq!(
+        properties.get("kind").unwrap().to_string(),
+        r#"{"enum":["Melee","ranged"]}"#
+    );
+    assert_eq!(Layer::json_schema().to_string(), r#"{"enum":[0,10]}"#);
+
+    let mut registry = JsonSchemaRegistry::default();
+    registry.register::<Enemy>("enemy");
+    assert_eq!(
+        registry
+            .get("enemy")
+            .unwrap()
+            .get("title")
+            .unwrap()
+            .as_str(),
+        Some("enemy")
+    );
+}
+
+#[test]
+fn test_schema_validate() {
+    let schema = Enemy::json_schema();
+    let valid = JsonValue::parse(
+        r#"{"name": "Slime", "health": 10, "sprite": null, "tags": [], "kind": "Melee"}"#,
+    )
+    .unwrap();
+    assert_eq!(validate_json(&schema, &valid), []);
+
+    // All violations are reported at once
+    let invalid = JsonValue::parse(
+        r#"{"name": 5, "health": -1, "sprite": 3, "tags": ["a", false], "kind": "Magic"}"#,
+    )
+    .unwrap();
+    let violations = validate_json(&schema, &invalid);
+    let violation = |pointer: &str, message: &str| SchemaViolation {
+        pointer: pointer.to_owned(),
+        message: message.to_owned(),
+    };
+    assert_eq!(
+        violations,
+        [
+            violation("/name", "expected string, found number"),
+            violation("/health", "-1 is less than 0"),
+            violation("/sprite", "number does not match any allowed schema"),
+            violation("/tags/1", "expected string, found boolean"),
+            violation(
+                "/kind",
+                r#"expected one of "Melee", "ranged", found "Magic""#
+            ),
+        ]
+    );
+    let violations = validate_json(&schema, &JsonValue::parse(r#"{"name": "Bat"}"#).unwrap());
+    assert_eq!(
+        violations,
+        [
+            violation("", r#"missing required property "health""#),
+            violation("", r#"missing required property "tags""#),
+            violation("", r#"missing required property "kind""#),
+        ]
+    );
+}
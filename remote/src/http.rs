@@ -0,0 +1,150 @@
+//! A minimal implementation of the subset of HTTP/1.1 used by the asset server protocol.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// The maximum amount of time to wait for a peer to send or accept data, once connected.
+pub const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum amount of time a client waits for a response. This must allow for the time a
+/// server may hold a `/changes` request before responding.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Performs a `GET` request for the given target, returning the status code and response body.
+pub fn get(addr: SocketAddr, target: &str) -> std::io::Result<(u16, Vec<u8>)> {
+    get_on(TcpStream::connect(addr)?, addr, target)
+}
+
+/// Performs a `GET` request for the given target on an existing connection to the given address,
+/// returning the status code and response body.
+pub fn get_on(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    target: &str,
+) -> std::io::Result<(u16, Vec<u8>)> {
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        target, addr
+    )?;
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid_data("malformed HTTP status line"))?;
+    let mut content_length = None;
+    for header in read_headers(&mut reader)? {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = Vec::new();
+    match content_length {
+        Some(len) => {
+            body.resize(len, 0);
+            reader.read_exact(&mut body)?;
+        }
+        None => {
+            reader.read_to_end(&mut body)?;
+        }
+    }
+    Ok((status, body))
+}
+
+/// Reads a request from the given stream, returning its method and target.
+pub fn read_request(stream: &TcpStream) -> std::io::Result<(String, String)> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid_data("malformed HTTP request line"));
+    };
+    let (method, target) = (method.to_owned(), target.to_owned());
+    read_headers(&mut reader)?;
+    Ok((method, target))
+}
+
+/// Writes a complete response to the given stream.
+pub fn write_response(
+    mut stream: &TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        416 => "Range Not Satisfiable",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Reads header lines up to and including the blank line that ends them.
+fn read_headers(reader: &mut impl BufRead) -> std::io::Result<Vec<String>> {
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        headers.push(line.to_owned());
+    }
+}
+
+/// Percent-encodes an asset path for use in a request target. `/` is left as-is.
+pub fn encode_path(path: &str) -> String {
+    let mut res = String::new();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            res.push(byte as char);
+        } else {
+            res.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    res
+}
+
+/// Decodes a percent-encoded asset path from a request target.
+pub fn decode_path(path: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut iter = path.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Constructs an [`std::io::Error`] for malformed protocol data.
+pub fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
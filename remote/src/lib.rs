@@ -0,0 +1,23 @@
+//! Serves assets over a simple HTTP protocol, and provides an [`assetman::AssetSource`] which
+//! loads assets from such a server.
+//!
+//! The protocol consists of the following `GET` endpoints:
+//!  * `/files/<path>`: responds with the contents of the file at the given path. If
+//!    `?offset=<offset>&len=<len>` is given, responds with only that range of the file, or `416`
+//!    if the file is too short.
+//!  * `/list/<path>`: responds with a JSON array of the names of the immediate children of the
+//!    directory at the given path. The names of directories end with `/`.
+//!  * `/changes?since=<version>`: waits until there are changes newer than the given version, or
+//!    until a timeout elapses, then responds with a JSON object of the form
+//!    `{"version": <version>, "changed": [<path>...]}`. `changed` is `null` if the changes since
+//!    the given version are no longer known, in which case everything should be assumed to have
+//!    changed. If `since` is omitted, the response is immediate and `changed` is empty.
+//!
+//! Paths are percent-encoded and use `/` as a separator. A change to a file also implies a change
+//! to the listing of every directory containing it.
+mod http;
+mod server;
+mod source;
+
+pub use server::*;
+pub use source::*;
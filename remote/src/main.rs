@@ -0,0 +1,29 @@
+use assetman::AssetPath;
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: assetman-server <dir> [--addr <address>]";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (dir, addr) = match args.as_slice() {
+        [dir] => (dir, "127.0.0.1:7878"),
+        [dir, flag, addr] if flag == "--addr" => (dir, addr.as_str()),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let root = AssetPath::new_root_fs(std::path::Path::new(dir));
+    let res = TcpListener::bind(addr).and_then(|listener| {
+        println!("serving {} on {}", dir, listener.local_addr()?);
+        assetman_remote::serve(root, listener)
+    });
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crate::http;
+use assetman::{AssetPath, Tracker};
+use assetman_json::JsonValue;
+use std::collections::{HashMap, VecDeque};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+/// The maximum number of changes remembered by the server for the purposes of `/changes`
+/// requests.
+const MAX_CHANGES: usize = 4096;
+
+/// The maximum amount of time a `/changes` request will wait before responding.
+const CHANGES_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The interval at which the server checks whether served assets have changed.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The maximum number of connections handled at once. Further connections are refused with a
+/// `503` response until existing connections are closed.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Serves the assets in the given root over the protocol described in the [crate]
+/// documentation, accepting connections from the given listener. This does not return unless
+/// accepting a connection fails.
+pub fn serve(root: AssetPath, listener: TcpListener) -> std::io::Result<()> {
+    let state = Arc::new(ServerState {
+        root,
+        watched: Mutex::new(HashMap::new()),
+        changes: Mutex::new(ChangeLog {
+            version: 0,
+            paths: VecDeque::new(),
+        }),
+        changed: Condvar::new(),
+        connections: AtomicUsize::new(0),
+    });
+    let watch_state = Arc::downgrade(&state);
+    std::thread::spawn(move || ServerState::watch(watch_state));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        if state.connections.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+            state.connections.fetch_sub(1, Ordering::AcqRel);
+            let _ = stream.set_write_timeout(Some(http::IO_TIMEOUT));
+            let _ = http::write_response(&stream, 503, "text/plain", b"too many connections");
+            continue;
+        }
+        let state = state.clone();
+        std::thread::spawn(move || {
+            let res = stream
+                .set_read_timeout(Some(http::IO_TIMEOUT))
+                .and_then(|()| stream.set_write_timeout(Some(http::IO_TIMEOUT)))
+                .and_then(|()| state.handle(&stream));
+            state.connections.fetch_sub(1, Ordering::AcqRel);
+            if let Err(err) = res {
+                log::warn!(target: "assetman", "Failed to handle asset server request: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// The shared state of an asset server.
+struct ServerState {
+    root: AssetPath,
+
+    /// The tokens for the files and directories that have been served, which are invalidated when
+    /// they change.
+    watched: Mutex<HashMap<String, renege::Token>>,
+    changes: Mutex<ChangeLog>,
+
+    /// Notified when a change is added to `changes`.
+    changed: Condvar,
+
+    /// The number of connections currently being handled.
+    connections: AtomicUsize,
+}
+
+/// The set of recent changes known to an asset server.
+struct ChangeLog {
+    /// The total number of changes that have occurred.
+    version: u64,
+
+    /// The paths for the most recent changes, with the last being the change for `version`.
+    paths: VecDeque<String>,
+}
+
+impl ServerState {
+    /// Repeatedly checks whether watched files and directories have changed, adding them to the
+    /// change log if so, until the server stops.
+    fn watch(state: Weak<Self>) {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let Some(state) = state.upgrade() else {
+                return;
+            };
+            state.check_changes();
+        }
+    }
+
+    /// Adds the watched files and directories that have changed to the change log.
+    fn check_changes(&self) {
+        let mut changed = Vec::new();
+        self.watched.lock().unwrap().retain(|path, token| {
+            let valid = token.is_valid();
+            if !valid {
+                changed.push(path.clone());
+            }
+            valid
+        });
+        if !changed.is_empty() {
+            let mut changes = self.changes.lock().unwrap();
+            for path in changed {
+                changes.version += 1;
+                changes.paths.push_back(path);
+            }
+            while changes.paths.len() > MAX_CHANGES {
+                changes.paths.pop_front();
+            }
+            self.changed.notify_all();
+        }
+    }
+
+    /// Records that the given file or directory was served, using the token from the given
+    /// [`Tracker`].
+    fn watch_path(&self, path: String, tracker: Tracker) {
+        let mut watched = self.watched.lock().unwrap();
+        let token = match watched.get(&path) {
+            Some(existing) => *existing & tracker.get(),
+            None => tracker.get(),
+        };
+        watched.insert(path, token);
+    }
+
+    /// Handles a request on the given stream.
+    fn handle(&self, stream: &TcpStream) -> std::io::Result<()> {
+        let (method, target) = http::read_request(stream)?;
+        if method != "GET" {
+            return http::write_response(stream, 405, "text/plain", b"method not allowed");
+        }
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let tracker = Tracker::default();
+        if let Some(path) = path.strip_prefix("/files/") {
+            let Some(path) = http::decode_path(path) else {
+                return http::write_response(stream, 400, "text/plain", b"malformed path");
+            };
+            let param = |name: &str| {
+                query
+                    .split('&')
+                    .find_map(|param| param.strip_prefix(name)?.strip_prefix('='))
+            };
+            let res = match (param("offset"), param("len")) {
+                (None, None) => self.root.relative(&path).load_bytes(&tracker),
+                (Some(offset), Some(len)) => match (offset.parse(), len.parse()) {
+                    (Ok(offset), Ok(len)) => {
+                        self.root.relative(&path).load_range(&tracker, offset, len)
+                    }
+                    _ => {
+                        return http::write_response(stream, 400, "text/plain", b"malformed range")
+                    }
+                },
+                _ => return http::write_response(stream, 400, "text/plain", b"incomplete range"),
+            };
+            self.watch_path(path, tracker);
+            match res {
+                Ok(bytes) => http::write_response(stream, 200, "application/octet-stream", &bytes),
+                Err(err) => write_error(stream, err),
+            }
+        } else if let Some(path) = path.strip_prefix("/list/") {
+            let Some(path) = http::decode_path(path) else {
+                return http::write_response(stream, 400, "text/plain", b"malformed path");
+            };
+            let dir = self.root.relative(&path);
+            let res = dir.get_children(&tracker);
+            let res = res.map(|children| {
+                children
+                    .into_iter()
+                    .map(|name| match dir.relative(&name).is_dir(&tracker) {
+                        true => JsonValue::String(format!("{}/", name)),
+                        false => JsonValue::String(name),
+                    })
+                    .collect()
+            });
+            self.watch_path(path, tracker);
+            match res {
+                Ok(children) => {
+                    let body = JsonValue::Array(children).to_string();
+                    http::write_response(stream, 200, "application/json", body.as_bytes())
+                }
+                Err(err) => write_error(stream, err),
+            }
+        } else if path == "/changes" {
+            let since = query
+                .split('&')
+                .find_map(|param| param.strip_prefix("since="))
+                .map(|since| since.parse::<u64>());
+            let since = match since {
+                Some(Ok(since)) => Some(since),
+                Some(Err(_)) => {
+                    return http::write_response(stream, 400, "text/plain", b"malformed version")
+                }
+                None => None,
+            };
+            let body = self.wait_changes(since).to_string();
+            http::write_response(stream, 200, "application/json", body.as_bytes())
+        } else {
+            http::write_response(stream, 404, "text/plain", b"not found")
+        }
+    }
+
+    /// Waits for changes newer than the given version, returning the response for a `/changes`
+    /// request.
+    fn wait_changes(&self, since: Option<u64>) -> JsonValue {
+        let changes = self.changes.lock().unwrap();
+        let (changes, since) = match since {
+            Some(since) => {
+                let (changes, _) = self
+                    .changed
+                    .wait_timeout_while(changes, CHANGES_TIMEOUT, |changes| {
+                        changes.version == since
+                    })
+                    .unwrap();
+                (changes, since)
+            }
+            None => {
+                let version = changes.version;
+                (changes, version)
+            }
+        };
+        // If the server has restarted, the client may have a version newer than ours
+        let first_version = changes.version - changes.paths.len() as u64;
+        let changed = if since < first_version || since > changes.version {
+            JsonValue::Null
+        } else {
+            let new = (changes.version - since) as usize;
+            let paths = changes.paths.iter().skip(changes.paths.len() - new);
+            JsonValue::Array(paths.cloned().map(JsonValue::String).collect())
+        };
+        JsonValue::Object(vec![
+            ("version".to_owned(), JsonValue::from(changes.version)),
+            ("changed".to_owned(), changed),
+        ])
+    }
+}
+
+/// Writes the response for an asset that failed to load.
+fn write_error(stream: &TcpStream, err: assetman::AssetLoadError) -> std::io::Result<()> {
+    let kind = err
+        .inner
+        .downcast_ref::<std::io::Error>()
+        .map(|err| err.kind());
+    let status = match kind {
+        Some(std::io::ErrorKind::NotFound) => 404,
+        Some(std::io::ErrorKind::UnexpectedEof) => 416,
+        _ => 500,
+    };
+    http::write_response(stream, status, "text/plain", err.to_string().as_bytes())
+}
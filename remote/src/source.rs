@@ -0,0 +1,282 @@
+use crate::http;
+use assetman::{AssetPath, AssetSource, Tracker};
+use assetman_json::JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// The time to wait before retrying after failing to poll the server for changes.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An [`AssetSource`] which loads assets from an asset server (see [`crate::serve`]).
+///
+/// Changes reported by the server are used to notify trackers, so hot reloading works the same
+/// way as for local assets.
+///
+/// This is cheap to clone, and all clones refer to the same connection.
+#[derive(Clone)]
+pub struct RemoteSource {
+    inner: Arc<RemoteSourceInner>,
+}
+
+/// The shared state of a [`RemoteSource`].
+struct RemoteSourceInner {
+    /// The address of the server.
+    addr: SocketAddr,
+
+    /// A mapping from files and directories that have been observed to the [`renege::Condition`]
+    /// that must be invalidated when they change.
+    conditions: Mutex<HashMap<String, renege::Condition>>,
+
+    /// The names of the subdirectories in each directory that has been listed, so that
+    /// [`AssetSource::is_dir`] doesn't need a request per file. Entries are removed along with
+    /// the conditions for their directories.
+    subdirs: Mutex<HashMap<String, HashSet<String>>>,
+
+    /// The connection for the `/changes` request currently in progress, which is shut down when
+    /// the source is dropped so that the thread polling for changes stops promptly.
+    poll_stream: Mutex<Option<TcpStream>>,
+}
+
+impl RemoteSource {
+    /// Connects to the asset server at the given address.
+    pub fn connect(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| http::invalid_data("no address given"))?;
+        let inner = Arc::new(RemoteSourceInner {
+            addr,
+            conditions: Mutex::new(HashMap::new()),
+            subdirs: Mutex::new(HashMap::new()),
+            poll_stream: Mutex::new(None),
+        });
+        let (version, _) = poll_changes(TcpStream::connect(addr)?, addr, None)?;
+        let weak = Arc::downgrade(&inner);
+        std::thread::spawn(move || RemoteSourceInner::watch(weak, addr, version));
+        Ok(Self { inner })
+    }
+
+    /// Constructs a root [`AssetPath`] for the assets on the server.
+    ///
+    /// As with [`AssetPath::new_root`], this should be called once per connection.
+    pub fn root(&self) -> AssetPath {
+        AssetPath::new_root(self.clone())
+    }
+}
+
+impl RemoteSourceInner {
+    /// Repeatedly polls the server for changes, invalidating the conditions for changed paths,
+    /// until the source is dropped.
+    fn watch(inner: Weak<Self>, addr: SocketAddr, mut version: u64) {
+        loop {
+            let res = TcpStream::connect(addr).and_then(|stream| {
+                if let Some(inner) = inner.upgrade() {
+                    *inner.poll_stream.lock().unwrap() = Some(stream.try_clone()?);
+                }
+                poll_changes(stream, addr, Some(version))
+            });
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            match res {
+                Ok((new_version, changed)) => {
+                    let mut conditions = inner.conditions.lock().unwrap();
+                    let mut subdirs = inner.subdirs.lock().unwrap();
+                    match changed {
+                        Some(changed) => {
+                            for path in changed {
+                                invalidate(&mut conditions, &path);
+                                invalidate(&mut subdirs, &path);
+                            }
+                        }
+                        None => {
+                            conditions.clear();
+                            subdirs.clear();
+                        }
+                    }
+                    version = new_version;
+                }
+                Err(err) => {
+                    log::warn!(
+                        target: "assetman",
+                        "Failed to poll asset server {} for changes: {}",
+                        addr,
+                        err
+                    );
+                    drop(inner);
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Ensures that the given [`Tracker`] is notified when the given file or directory changes.
+    fn track(&self, tracker: &Tracker, path: &str) {
+        use std::collections::hash_map::Entry::*;
+        let mut conditions = self.conditions.lock().unwrap();
+        let token = match conditions.entry(path.to_owned()) {
+            Occupied(entry) => entry.get().token(),
+            Vacant(entry) => entry.insert(renege::Condition::new()).token(),
+        };
+        tracker.set(tracker.get() & token);
+    }
+
+    /// Performs a `/list` request for the given directory, returning the names of its children
+    /// along with whether each of them is a directory.
+    fn list(&self, path: &str) -> std::io::Result<Vec<(String, bool)>> {
+        let body = self.get("list", path)?;
+        let body = std::str::from_utf8(&body).map_err(|_| http::invalid_data("invalid UTF-8"))?;
+        let Ok(JsonValue::Array(names)) = JsonValue::parse(body) else {
+            return Err(http::invalid_data("malformed directory listing"));
+        };
+        let entries = names
+            .into_iter()
+            .map(|name| match name {
+                JsonValue::String(name) => match name.strip_suffix('/') {
+                    Some(dir_name) => Ok((dir_name.to_owned(), true)),
+                    None => Ok((name, false)),
+                },
+                _ => Err(http::invalid_data("malformed directory listing")),
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let dirs = entries.iter().filter(|(_, is_dir)| *is_dir);
+        let dirs = dirs.map(|(name, _)| name.clone()).collect();
+        self.subdirs.lock().unwrap().insert(path.to_owned(), dirs);
+        Ok(entries)
+    }
+
+    /// Performs a `GET` request for a file or directory, returning the response body.
+    fn get(&self, endpoint: &str, path: &str) -> std::io::Result<Vec<u8>> {
+        self.request(&format!("/{}/{}", endpoint, http::encode_path(path)))
+    }
+
+    /// Performs a `GET` request for the given target, returning the response body.
+    fn request(&self, target: &str) -> std::io::Result<Vec<u8>> {
+        match http::get(self.addr, target)? {
+            (200, body) => Ok(body),
+            (404, _) => Err(std::io::ErrorKind::NotFound.into()),
+            (416, _) => Err(std::io::ErrorKind::UnexpectedEof.into()),
+            (status, body) => Err(std::io::Error::other(format!(
+                "asset server responded with status {}: {}",
+                status,
+                String::from_utf8_lossy(&body)
+            ))),
+        }
+    }
+}
+
+impl Drop for RemoteSourceInner {
+    fn drop(&mut self) {
+        if let Some(stream) = self.poll_stream.get_mut().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Performs a `/changes` request on the given connection to the server at the given address,
+/// returning the new version and the paths that have changed, or [`None`] if everything should be
+/// assumed to have changed.
+fn poll_changes(
+    stream: TcpStream,
+    addr: SocketAddr,
+    since: Option<u64>,
+) -> std::io::Result<(u64, Option<Vec<String>>)> {
+    let target = match since {
+        Some(since) => format!("/changes?since={}", since),
+        None => "/changes".to_owned(),
+    };
+    let (status, body) = http::get_on(stream, addr, &target)?;
+    if status != 200 {
+        return Err(http::invalid_data("unexpected status for changes request"));
+    }
+    let body = std::str::from_utf8(&body).map_err(|_| http::invalid_data("invalid UTF-8"))?;
+    let value = JsonValue::parse(body).map_err(|err| http::invalid_data(&err.to_string()))?;
+    let Some(version) = value.get("version").and_then(|version| match version {
+        JsonValue::Number(version) => version.as_u64(),
+        _ => None,
+    }) else {
+        return Err(http::invalid_data("missing version in changes response"));
+    };
+    let changed = match value.get("changed") {
+        Some(JsonValue::Array(paths)) => Some(
+            paths
+                .iter()
+                .filter_map(|path| path.as_str().map(|path| path.to_owned()))
+                .collect(),
+        ),
+        _ => None,
+    };
+    Ok((version, changed))
+}
+
+/// Removes the entries for the given path and all of the directories containing it, such as the
+/// conditions to invalidate when it changes.
+fn invalidate<T>(conditions: &mut HashMap<String, T>, path: &str) {
+    conditions.remove(path);
+    for (i, _) in path.match_indices('/') {
+        conditions.remove(&path[..i]);
+    }
+    conditions.remove("");
+}
+
+impl AssetSource for RemoteSource {
+    fn load_bytes(&self, tracker: &Tracker, path: &str) -> std::io::Result<Box<[u8]>> {
+        self.inner.track(tracker, path);
+        Ok(self.inner.get("files", path)?.into_boxed_slice())
+    }
+
+    fn load_range(
+        &self,
+        tracker: &Tracker,
+        path: &str,
+        offset: u64,
+        len: usize,
+    ) -> std::io::Result<Box<[u8]>> {
+        self.inner.track(tracker, path);
+        let target = format!(
+            "/files/{}?offset={}&len={}",
+            http::encode_path(path),
+            offset,
+            len
+        );
+        let body = self.inner.request(&target)?;
+        if body.len() != len {
+            return Err(http::invalid_data("unexpected length for range request"));
+        }
+        Ok(body.into_boxed_slice())
+    }
+
+    fn get_children(&self, tracker: &Tracker, path: &str) -> std::io::Result<Vec<String>> {
+        self.inner.track(tracker, path);
+        let entries = self.inner.list(path)?;
+        Ok(entries.into_iter().map(|(name, _)| name).collect())
+    }
+
+    fn is_dir(&self, tracker: &Tracker, path: &str) -> bool {
+        if path.is_empty() {
+            return true;
+        }
+
+        // Whether a path is a directory is known from the listing of its parent, which is cached
+        // so that checking the other children doesn't need another request
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        self.inner.track(tracker, path);
+        self.inner.track(tracker, parent);
+        let cached = self.inner.subdirs.lock().unwrap();
+        if let Some(dirs) = cached.get(parent) {
+            return dirs.contains(name);
+        }
+        drop(cached);
+        self.inner.list(parent).is_ok_and(|entries| {
+            entries
+                .iter()
+                .any(|(entry_name, is_dir)| *is_dir && entry_name == name)
+        })
+    }
+
+    fn track(&self, tracker: &Tracker, path: &str) {
+        self.inner.track(tracker, path);
+    }
+}
@@ -0,0 +1,40 @@
+use assetman::{AssetPath, Tracker};
+use assetman_remote::RemoteSource;
+use assetman_test_util::TempDir;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_remote() {
+    let dir = TempDir::new("remote");
+    std::fs::create_dir_all(dir.join("sub dir")).unwrap();
+    std::fs::write(dir.join("sub dir/data.txt"), "first").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_root = AssetPath::new_root_fs(&dir);
+    std::thread::spawn(move || assetman_remote::serve(server_root, listener));
+
+    let root = RemoteSource::connect(addr).unwrap().root();
+    let tracker = Tracker::default();
+    assert_eq!(root.get_children(&tracker).unwrap(), ["sub dir"]);
+    assert!(root.relative("sub dir").is_dir(&tracker));
+    assert_eq!(
+        root.get_descendants(&tracker).unwrap(),
+        [root.relative("sub dir/data.txt")]
+    );
+    let asset = root.relative("sub dir/data.txt");
+    assert_eq!(&*asset.load_bytes(&tracker).unwrap(), b"first");
+    assert!(root.relative("missing.txt").load_bytes(&tracker).is_err());
+    assert_eq!(&*asset.load_range(&tracker, 1, 3).unwrap(), b"irs");
+    assert!(asset.load_range(&tracker, 3, 4).is_err());
+
+    // Changes on the server are reported to the client
+    std::fs::write(dir.join("sub dir/data.txt"), "second").unwrap();
+    let start = Instant::now();
+    while tracker.get().is_valid() && start.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(!tracker.get().is_valid());
+    let tracker = Tracker::default();
+    assert_eq!(&*asset.load_bytes(&tracker).unwrap(), b"second");
+}
@@ -17,10 +17,10 @@ impl AssetPathShaderExt for AssetPath {
         tracker: &Tracker,
         device: &wgpu::Device,
     ) -> AssetLoadResult<wgpu::ShaderModule> {
-        let mut file = self.open_file(tracker)?;
+        let _scope = assetman::stats::LoadScope::new(self, "shader");
+        let bytes = self.load_bytes(tracker)?;
         assetman::with_asset(self, || {
-            let mut source = String::new();
-            std::io::Read::read_to_string(&mut file, &mut source)?;
+            let source = std::str::from_utf8(&bytes)?.to_owned();
             device.push_error_scope(wgpu::ErrorFilter::Validation);
             let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: None,
@@ -0,0 +1,36 @@
+//! Helpers shared by the tests of the crates in this workspace.
+use std::path::{Path, PathBuf};
+
+/// A temporary directory for a test, which is deleted when dropped, including when the test
+/// panics.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty temporary directory whose name is derived from the given name and the ID
+    /// of the current process, so that tests running concurrently don't share directories.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("assetman-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}